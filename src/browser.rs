@@ -0,0 +1,215 @@
+//! Finding and starting a web browser for launched apps
+//!
+//! Browsers are tried in order: each entry of `$BROWSER`, `[browser] command`,
+//! then `[browser] alternatives`. Chromium-based browsers get the configured
+//! flags (with `--app` pointing at the app), Firefox a new window, and
+//! anything else just the URL.
+
+use std::path::Path;
+
+use crate::settings::BrowserSettings;
+
+/// Browser families that take different command-line flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrowserKind {
+    /// Chrome, Chromium, Edge, Brave, Vivaldi, Opera
+    Chromium,
+    Firefox,
+    /// Safari, started through `open -a` on macOS
+    Safari,
+    Other,
+}
+
+impl BrowserKind {
+    /// Recognise a browser by its executable name
+    pub fn detect(program: &str) -> Self {
+        let name = Path::new(program)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or(program)
+            .to_lowercase();
+        if [
+            "chrom",
+            "msedge",
+            "microsoft-edge",
+            "brave",
+            "vivaldi",
+            "opera",
+        ]
+        .iter()
+        .any(|family| name.contains(family))
+        {
+            BrowserKind::Chromium
+        } else if name.contains("firefox") || name == "librewolf" {
+            BrowserKind::Firefox
+        } else if name == "safari" {
+            BrowserKind::Safari
+        } else {
+            BrowserKind::Other
+        }
+    }
+}
+
+/// A way of opening a URL: a program and its arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    /// How the browser was named in `$BROWSER` or the configuration
+    pub name: String,
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+/// Browsers to try for `url`, most preferred first, without duplicates
+///
+/// `env_browser` is the value of `$BROWSER`: a `:`-separated list of commands
+/// in which `%s` stands for the URL.
+pub fn candidates(
+    settings: &BrowserSettings,
+    env_browser: Option<&str>,
+    url: &str,
+) -> Vec<Invocation> {
+    let mut invocations: Vec<Invocation> = Vec::new();
+    let mut push = |invocation: Option<Invocation>| {
+        if let Some(invocation) = invocation {
+            if !invocations.iter().any(|i| i.name == invocation.name) {
+                invocations.push(invocation);
+            }
+        }
+    };
+
+    for entry in env_browser.unwrap_or_default().split(':') {
+        push(from_env_entry(entry, url));
+    }
+    push(configured(&settings.command, &settings.flags, url));
+    for alternative in &settings.alternatives {
+        push(configured(alternative, &settings.flags, url));
+    }
+    invocations
+}
+
+/// One `$BROWSER` entry, used as given
+fn from_env_entry(entry: &str, url: &str) -> Option<Invocation> {
+    let mut words = entry.split_whitespace();
+    let program = words.next()?.to_string();
+    let mut args: Vec<String> = words.map(|w| w.replace("%s", url)).collect();
+    if !entry.contains("%s") {
+        args.push(url.to_string());
+    }
+    Some(Invocation {
+        name: entry.trim().to_string(),
+        program,
+        args,
+    })
+}
+
+/// A configured browser with the flags for its family
+fn configured(command: &str, flags: &[String], url: &str) -> Option<Invocation> {
+    let command = command.trim();
+    if command.is_empty() {
+        return None;
+    }
+    let (program, args) = match BrowserKind::detect(command) {
+        BrowserKind::Chromium => {
+            let mut app_mode = false;
+            let mut args: Vec<String> = flags
+                .iter()
+                .map(|flag| {
+                    if flag == "--app" {
+                        app_mode = true;
+                        format!("--app={}", url)
+                    } else {
+                        flag.clone()
+                    }
+                })
+                .collect();
+            if !app_mode {
+                args.push(url.to_string());
+            }
+            (command.to_string(), args)
+        }
+        BrowserKind::Firefox => (
+            command.to_string(),
+            vec!["--new-window".to_string(), url.to_string()],
+        ),
+        BrowserKind::Safari if cfg!(target_os = "macos") => (
+            "open".to_string(),
+            vec!["-a".to_string(), "Safari".to_string(), url.to_string()],
+        ),
+        // Safari only exists as an app bundle on macOS
+        BrowserKind::Safari => return None,
+        BrowserKind::Other => (command.to_string(), vec![url.to_string()]),
+    };
+    Some(Invocation {
+        name: command.to_string(),
+        program,
+        args,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> BrowserSettings {
+        BrowserSettings {
+            command: "chromium".to_string(),
+            alternatives: vec![
+                "firefox".to_string(),
+                "chromium".to_string(),
+                "/opt/vivaldi/vivaldi".to_string(),
+            ],
+            flags: vec!["--app".to_string(), "--new-window".to_string()],
+            local_server_port: 0,
+            launch_timeout: 10,
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            BrowserKind::detect("google-chrome-stable"),
+            BrowserKind::Chromium
+        );
+        assert_eq!(
+            BrowserKind::detect("C:\\Edge\\msedge.exe"),
+            BrowserKind::Chromium
+        );
+        assert_eq!(
+            BrowserKind::detect("/usr/bin/firefox-esr"),
+            BrowserKind::Firefox
+        );
+        assert_eq!(BrowserKind::detect("Safari"), BrowserKind::Safari);
+        assert_eq!(BrowserKind::detect("w3m"), BrowserKind::Other);
+    }
+
+    #[test]
+    fn test_candidates() {
+        let url = "http://127.0.0.1:8080/";
+        let list = candidates(&settings(), Some("w3m -o x:lynx %s"), url);
+        let names: Vec<&str> = list.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "w3m -o x",
+                "lynx %s",
+                "chromium",
+                "firefox",
+                "/opt/vivaldi/vivaldi"
+            ]
+        );
+
+        assert_eq!(list[0].args, ["-o", "x", url]);
+        assert_eq!(list[1].args, [url]);
+        assert_eq!(
+            list[2].args,
+            [format!("--app={}", url), "--new-window".to_string()]
+        );
+        assert_eq!(list[3].args, ["--new-window", url]);
+        assert_eq!(list[4].program, "/opt/vivaldi/vivaldi");
+
+        // Without --app the URL is passed on its own
+        let mut plain = settings();
+        plain.flags.clear();
+        assert_eq!(candidates(&plain, None, url)[0].args, [url]);
+    }
+}
@@ -0,0 +1,358 @@
+//! Code and data embedded in sView documents
+//!
+//! A document carries runnable snippets as `<sview:code lang="python">`
+//! elements, `sview` standing for whatever prefix binds [`SVIEW_NAMESPACE`],
+//! and its data as JSON in `<metadata><data-store>`. New versions of
+//! the data are inserted before the old ones with a `version` attribute, so
+//! the first `<data-store>` (the one a script's `querySelector` finds) is
+//! always the current one and earlier versions stay as history.
+
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
+use crate::metadata::{parse_xml, SVIEW_NAMESPACE};
+
+/// A `<sview:code>` element
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeBlock {
+    /// The `id` attribute, or `block-N` counting from 1 in document order
+    pub id: String,
+    pub language: String,
+    pub code: String,
+}
+
+/// The current `<data-store>` of a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataStore {
+    /// From the `version` attribute; a store without one is version 1
+    pub version: u32,
+    /// The JSON text, trimmed
+    pub content: String,
+}
+
+/// A parsed sView document
+pub struct Document {
+    text: String,
+}
+
+impl Document {
+    pub fn parse(text: String) -> Result<Self> {
+        parse_xml(&text).context("Not a well-formed SVG document")?;
+        Ok(Self { text })
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read SVG file: {}", path.display()))?;
+        Self::parse(text).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Write the document to `path`, replacing it atomically
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("svg.tmp");
+        fs::write(&tmp, &self.text)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, path).with_context(|| format!("Failed to update {}", path.display()))
+    }
+
+    /// Code blocks in document order; blocks without a `lang` are skipped
+    pub fn code_blocks(&self) -> Vec<CodeBlock> {
+        let doc = self.xml();
+        doc.descendants()
+            .filter(|node| {
+                node.tag_name().name() == "code"
+                    && node.tag_name().namespace() == Some(SVIEW_NAMESPACE)
+            })
+            .enumerate()
+            .filter_map(|(i, node)| {
+                Some(CodeBlock {
+                    id: node
+                        .attribute("id")
+                        .map(str::to_string)
+                        .unwrap_or_else(|| format!("block-{}", i + 1)),
+                    language: node.attribute("lang")?.to_string(),
+                    code: dedent(&text_content(node)),
+                })
+            })
+            .collect()
+    }
+
+    /// The first `<data-store>` in `<metadata>`, if any
+    pub fn data_store(&self) -> Option<DataStore> {
+        let doc = self.xml();
+        let mut stores = data_stores(&doc);
+        let current = stores.next()?;
+        Some(DataStore {
+            version: version_of(current),
+            content: text_content(current).trim().to_string(),
+        })
+    }
+
+    /// Add `json` as a new current data-store version
+    ///
+    /// It goes before the current store, matching its indentation, or at the
+    /// end of `<metadata>`, which is created if the document has none.
+    pub fn push_data_store(&mut self, json: &str) {
+        let doc = self.xml();
+        let version = data_stores(&doc).map(version_of).max().unwrap_or(0) + 1;
+        let current = data_stores(&doc).next().map(|node| node.range().start);
+        let metadata = doc
+            .root_element()
+            .children()
+            .find(|node| node.tag_name().name() == "metadata")
+            .map(|node| (node.range(), node.has_children()));
+        let root = doc.root_element();
+        let root_indent = root
+            .first_element_child()
+            .map(|child| indent_at(&self.text, child.range().start).to_string())
+            .unwrap_or_else(|| "  ".to_string());
+        let root_start = root
+            .children()
+            .next()
+            .map(|child| child.range().start)
+            .unwrap_or(root.range().end);
+
+        let element = |indent: &str| {
+            let json = indent_lines(&escape_text(json.trim()), &format!("{}  ", indent));
+            format!(
+                "<data-store version=\"{}\">\n{}\n{}</data-store>",
+                version, json, indent
+            )
+        };
+        let (replace, insert) = match (current, metadata) {
+            (Some(start), _) => {
+                let indent = indent_at(&self.text, start);
+                (start..start, format!("{}\n{}", element(indent), indent))
+            }
+            (None, Some((range, true))) => {
+                // Before the closing tag, one level deeper than `<metadata>`
+                let inner = format!("{}  ", indent_at(&self.text, range.start));
+                let close = self.text[..range.end].rfind("</").unwrap_or(range.end);
+                let at = self.text[..close].trim_end().len();
+                (at..at, format!("\n{}{}", inner, element(&inner)))
+            }
+            (None, Some((range, false))) => {
+                // An empty `<metadata/>` is replaced by a full one
+                let indent = indent_at(&self.text, range.start);
+                let inner = format!("{}  ", indent);
+                let metadata = format!(
+                    "<metadata>\n{}{}\n{}</metadata>",
+                    inner,
+                    element(&inner),
+                    indent
+                );
+                (range, metadata)
+            }
+            (None, None) => {
+                let inner = format!("{}  ", root_indent);
+                let metadata = format!(
+                    "\n{}<metadata>\n{}{}\n{}</metadata>",
+                    root_indent,
+                    inner,
+                    element(&inner),
+                    root_indent
+                );
+                (root_start..root_start, metadata)
+            }
+        };
+        self.text.replace_range(replace, &insert);
+    }
+
+    /// The current data with `results` merged into its `results` object
+    ///
+    /// Results replace earlier ones with the same key and keep the others.
+    pub fn data_with_results(&self, results: Map<String, Value>) -> Result<Value> {
+        let mut data = match self.data_store() {
+            Some(store) => {
+                serde_json::from_str(&store.content).context("The data-store is not valid JSON")?
+            }
+            None => Value::Object(Map::new()),
+        };
+        let object = data
+            .as_object_mut()
+            .context("The data-store is not a JSON object")?;
+        let merged = object
+            .entry("results")
+            .or_insert_with(|| Value::Object(Map::new()));
+        if !merged.is_object() {
+            *merged = Value::Object(Map::new());
+        }
+        if let Some(merged) = merged.as_object_mut() {
+            merged.extend(results);
+        }
+        Ok(data)
+    }
+
+    fn xml(&self) -> roxmltree::Document<'_> {
+        parse_xml(&self.text).expect("checked in Document::parse")
+    }
+}
+
+/// `<data-store>` elements inside `<metadata>`, current first
+fn data_stores<'a, 'input>(
+    doc: &'a roxmltree::Document<'input>,
+) -> impl Iterator<Item = roxmltree::Node<'a, 'input>> {
+    doc.descendants().filter(|node| {
+        node.tag_name().name() == "data-store"
+            && node
+                .parent_element()
+                .is_some_and(|parent| parent.tag_name().name() == "metadata")
+    })
+}
+
+fn version_of(node: roxmltree::Node) -> u32 {
+    node.attribute("version")
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(1)
+}
+
+/// All text inside `node`, including CDATA sections
+fn text_content(node: roxmltree::Node) -> String {
+    node.descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect()
+}
+
+/// Remove blank lines around `code` and the indentation its lines share
+///
+/// Snippets are indented to fit the SVG, which matters to Python.
+fn dedent(code: &str) -> String {
+    let lines: Vec<&str> = code
+        .lines()
+        .skip_while(|line| line.trim().is_empty())
+        .collect();
+    let end = lines
+        .iter()
+        .rposition(|line| !line.trim().is_empty())
+        .map_or(0, |i| i + 1);
+    let lines = &lines[..end];
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The whitespace between the start of the line and byte `pos`
+fn indent_at(text: &str, pos: usize) -> &str {
+    let line_start = text[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let prefix = &text[line_start..pos];
+    if prefix.trim().is_empty() {
+        prefix
+    } else {
+        ""
+    }
+}
+
+fn indent_lines(text: &str, indent: &str) -> String {
+    text.lines()
+        .map(|line| format!("{}{}", indent, line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Escape text for use as XML character data
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:sview="http://sview.veridock.com/schema/v1">
+  <metadata>
+    <data-store>
+    {"data": [1, 2]}
+    </data-store>
+  </metadata>
+  <sview:code lang="python" id="total">
+    import json, sys
+    if True:
+        print(sum(json.load(sys.stdin)["data"]))
+  </sview:code>
+  <sview:code lang="javascript"><![CDATA[console.log(1 < 2)]]></sview:code>
+  <sview:code>ignored</sview:code>
+  <other:code xmlns:other="urn:other" lang="bash">ignored</other:code>
+</svg>"#;
+
+    #[test]
+    fn test_code_blocks() -> Result<()> {
+        let doc = Document::parse(SVG.to_string())?;
+        let blocks = doc.code_blocks();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].id, "total");
+        assert_eq!(blocks[0].language, "python");
+        assert_eq!(
+            blocks[0].code,
+            "import json, sys\nif True:\n    print(sum(json.load(sys.stdin)[\"data\"]))"
+        );
+        assert_eq!(blocks[1].id, "block-2");
+        assert_eq!(blocks[1].code, "console.log(1 < 2)");
+
+        let results = Map::from_iter([("total".to_string(), Value::from(3))]);
+        assert_eq!(
+            doc.data_with_results(results)?,
+            serde_json::json!({"data": [1, 2], "results": {"total": 3}})
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_push_data_store() -> Result<()> {
+        let mut doc = Document::parse(SVG.to_string())?;
+        assert_eq!(
+            doc.data_store(),
+            Some(DataStore {
+                version: 1,
+                content: r#"{"data": [1, 2]}"#.to_string()
+            })
+        );
+
+        doc.push_data_store("{\n  \"note\": \"a < b\"\n}");
+        let doc = Document::parse(doc.text.to_string())?;
+        assert_eq!(doc.data_store().unwrap().version, 2);
+        assert_eq!(
+            doc.data_store().unwrap().content,
+            "{\n        \"note\": \"a < b\"\n      }"
+        );
+        assert!(doc
+            .text
+            .contains("    <data-store version=\"2\">\n      {\n        \"note\": \"a &lt; b\"\n      }\n    </data-store>\n    <data-store>"));
+
+        // Documents without metadata get some
+        let mut bare = Document::parse("<svg>\n  <rect/>\n</svg>".to_string())?;
+        bare.push_data_store("{}");
+        assert_eq!(
+            bare.text,
+            "<svg>\n  <metadata>\n    <data-store version=\"1\">\n      {}\n    </data-store>\n  </metadata>\n  <rect/>\n</svg>"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_internal_dtd_subset() -> Result<()> {
+        let mut doc = Document::parse(
+            r#"<!DOCTYPE svg [<!ENTITY ns_ai "http://ns.adobe.com/AdobeIllustrator/10.0/">]>
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:i="&ns_ai;" xmlns:sview="http://sview.veridock.com/schema/v1">
+  <sview:code lang="bash">echo hi</sview:code>
+</svg>"#
+                .to_string(),
+        )?;
+        assert_eq!(doc.code_blocks()[0].code, "echo hi");
+        doc.push_data_store("{}");
+        assert_eq!(doc.data_store().unwrap().version, 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+//! Rasterizing SVGs to image files
+//!
+//! SVG user units are CSS pixels, so an SVG exported at the default
+//! 96 DPI and scale 1 gets one image pixel per unit.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use image::{DynamicImage, ImageOutputFormat, RgbaImage};
+use resvg::usvg::FitTo;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::svg2utf;
+
+/// Resolution at which one SVG user unit is one pixel
+pub const CSS_DPI: f64 = 96.0;
+
+/// The largest image an ICO file can hold
+const ICO_MAX_SIZE: u32 = 256;
+
+/// Raster formats `sview export` can write
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Png,
+    #[value(alias = "jpg")]
+    Jpeg,
+    /// Lossless WebP
+    Webp,
+    Bmp,
+    #[value(alias = "tif")]
+    Tiff,
+    Ico,
+}
+
+impl ExportFormat {
+    /// File extension for the format
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Png => "png",
+            ExportFormat::Jpeg => "jpg",
+            ExportFormat::Webp => "webp",
+            ExportFormat::Bmp => "bmp",
+            ExportFormat::Tiff => "tiff",
+            ExportFormat::Ico => "ico",
+        }
+    }
+
+    /// The format named by `name` (case-insensitive, `jpg` and `tif` accepted)
+    pub fn from_name(name: &str) -> Option<Self> {
+        <Self as ValueEnum>::from_str(name, true).ok()
+    }
+
+    /// The format matching the extension of `path`
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::from_name)
+    }
+
+    fn has_alpha(self) -> bool {
+        self != ExportFormat::Jpeg
+    }
+}
+
+/// How an SVG is sized and written by [`export`]
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    /// Output width in pixels; with only one of width and height the other keeps the aspect ratio
+    pub width: Option<u32>,
+    /// Output height in pixels; with both set the SVG is fitted inside them
+    pub height: Option<u32>,
+    /// Zoom factor, ignored when a width or height is set
+    pub scale: f64,
+    /// Resolution, ignored when a width or height is set
+    pub dpi: f64,
+    /// Colour painted behind the SVG; `None` keeps transparency (JPEG gets white)
+    pub background: Option<[u8; 4]>,
+    /// JPEG quality, 1-100
+    pub quality: u8,
+}
+
+impl ExportOptions {
+    fn fit_to(&self) -> FitTo {
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => FitTo::Size(width, height),
+            (Some(width), None) => FitTo::Width(width),
+            (None, Some(height)) => FitTo::Height(height),
+            (None, None) => FitTo::Zoom((self.scale * self.dpi / CSS_DPI) as f32),
+        }
+    }
+}
+
+/// Parse a background colour: `#rgb`, `#rrggbb`, `#rrggbbaa`, or `none`/`transparent`
+pub fn parse_color(value: &str) -> Result<Option<[u8; 4]>> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("none") || value.eq_ignore_ascii_case("transparent") {
+        return Ok(None);
+    }
+
+    let invalid = || anyhow::anyhow!("Invalid colour '{}': expected #rrggbb or none", value);
+    let hex = value.strip_prefix('#').ok_or_else(invalid)?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let digits: Vec<u8> = match hex.len() {
+        // #rgb is shorthand for #rrggbb
+        3 => hex.chars().flat_map(|c| [c, c]).map(|c| c as u8).collect(),
+        6 | 8 => hex.bytes().collect(),
+        _ => return Err(invalid()),
+    };
+    let channel = |i: usize| {
+        let pair = std::str::from_utf8(&digits[i * 2..i * 2 + 2]).unwrap_or_default();
+        u8::from_str_radix(pair, 16).unwrap_or_default()
+    };
+    let alpha = if digits.len() == 8 { channel(3) } else { 255 };
+    Ok(Some([channel(0), channel(1), channel(2), alpha]))
+}
+
+/// Render the SVG at `svg_path` as described by `options`
+pub fn render(svg_path: &Path, options: &ExportOptions) -> Result<RgbaImage> {
+    let svg_data = fs::read(svg_path)
+        .with_context(|| format!("Failed to read SVG file: {}", svg_path.display()))?;
+    let rtree = usvg::Tree::from_data(&svg_data, &usvg::Options::default())
+        .with_context(|| format!("Failed to parse SVG: {}", svg_path.display()))?;
+
+    let fit_to = options.fit_to();
+    let size = fit_to
+        .fit_to(rtree.size.to_screen_size())
+        .ok_or_else(|| anyhow::anyhow!("Invalid output size for {}", svg_path.display()))?;
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Cannot create a {}x{} image for {}",
+            size.width(),
+            size.height(),
+            svg_path.display()
+        )
+    })?;
+
+    let background = match options.background {
+        None if !options.format.has_alpha() => Some([255; 4]),
+        background => background,
+    };
+    if let Some([r, g, b, a]) = background {
+        pixmap.fill(tiny_skia::Color::from_rgba8(r, g, b, a));
+    }
+    resvg::render(
+        &rtree,
+        fit_to,
+        tiny_skia::Transform::default(),
+        pixmap.as_mut(),
+    );
+
+    // Image files expect straight alpha
+    svg2utf::pixmap_to_image(&pixmap)
+}
+
+/// Render `svg_path` and write it to `output`, returning the image size
+pub fn export(svg_path: &Path, output: &Path, options: &ExportOptions) -> Result<(u32, u32)> {
+    let img = render(svg_path, options)?;
+    let size = img.dimensions();
+    if options.format == ExportFormat::Ico && (size.0 > ICO_MAX_SIZE || size.1 > ICO_MAX_SIZE) {
+        return Err(anyhow::anyhow!(
+            "ICO images are at most {0}x{0} pixels, {1} would be {2}x{3}; pass --width or --height",
+            ICO_MAX_SIZE,
+            svg_path.display(),
+            size.0,
+            size.1
+        ));
+    }
+
+    let img = DynamicImage::ImageRgba8(img);
+    let (img, format) = match options.format {
+        ExportFormat::Png => (img, ImageOutputFormat::Png),
+        ExportFormat::Jpeg => (
+            DynamicImage::ImageRgb8(img.to_rgb8()),
+            ImageOutputFormat::Jpeg(options.quality.clamp(1, 100)),
+        ),
+        ExportFormat::Webp => (img, ImageOutputFormat::WebP),
+        ExportFormat::Bmp => (img, ImageOutputFormat::Bmp),
+        ExportFormat::Tiff => (img, ImageOutputFormat::Tiff),
+        ExportFormat::Ico => (img, ImageOutputFormat::Ico),
+    };
+
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let file =
+        File::create(output).with_context(|| format!("Failed to create {}", output.display()))?;
+    img.write_to(&mut BufWriter::new(file), format)
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const SVG: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20"><rect x="10" width="20" height="20" fill="#f00"/></svg>"##;
+
+    fn options(format: ExportFormat) -> ExportOptions {
+        ExportOptions {
+            format,
+            width: None,
+            height: None,
+            scale: 1.0,
+            dpi: CSS_DPI,
+            background: None,
+            quality: 90,
+        }
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#fff").unwrap(), Some([255, 255, 255, 255]));
+        assert_eq!(
+            parse_color("#336699").unwrap(),
+            Some([0x33, 0x66, 0x99, 255])
+        );
+        assert_eq!(parse_color("#00000080").unwrap(), Some([0, 0, 0, 0x80]));
+        assert_eq!(parse_color("transparent").unwrap(), None);
+        assert!(parse_color("red").is_err());
+        assert!(parse_color("#12345").is_err());
+    }
+
+    #[test]
+    fn test_render_sizes() -> Result<()> {
+        let dir = tempdir()?;
+        let svg = dir.path().join("a.svg");
+        fs::write(&svg, SVG)?;
+
+        let size = |options: ExportOptions| render(&svg, &options).map(|img| img.dimensions());
+        let png = options(ExportFormat::Png);
+        assert_eq!(size(png.clone())?, (40, 20));
+        assert_eq!(
+            size(ExportOptions {
+                scale: 2.0,
+                ..png.clone()
+            })?,
+            (80, 40)
+        );
+        assert_eq!(
+            size(ExportOptions {
+                dpi: 192.0,
+                ..png.clone()
+            })?,
+            (80, 40)
+        );
+        assert_eq!(
+            size(ExportOptions {
+                width: Some(100),
+                ..png.clone()
+            })?,
+            (100, 50)
+        );
+        assert_eq!(
+            size(ExportOptions {
+                width: Some(100),
+                height: Some(10),
+                ..png.clone()
+            })?,
+            (20, 10)
+        );
+
+        // Transparent by default, filled when asked
+        let img = render(&svg, &png)?;
+        assert_eq!(img.get_pixel(0, 0).0[3], 0);
+        assert_eq!(img.get_pixel(20, 10).0, [255, 0, 0, 255]);
+        let img = render(
+            &svg,
+            &ExportOptions {
+                background: Some([0, 0, 255, 255]),
+                ..png
+            },
+        )?;
+        assert_eq!(img.get_pixel(0, 0).0, [0, 0, 255, 255]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_formats() -> Result<()> {
+        let dir = tempdir()?;
+        let svg = dir.path().join("a.svg");
+        fs::write(&svg, SVG)?;
+
+        for format in ExportFormat::value_variants() {
+            let output = dir
+                .path()
+                .join("out")
+                .join(format!("a.{}", format.extension()));
+            assert_eq!(export(&svg, &output, &options(*format))?, (40, 20));
+            assert_eq!(ExportFormat::from_path(&output), Some(*format));
+
+            let img = image::open(&output)?.to_rgba8();
+            assert_eq!(img.dimensions(), (40, 20), "{:?}", format);
+            if *format == ExportFormat::Jpeg {
+                // No alpha channel: transparent areas become white
+                assert!(img.get_pixel(0, 0).0.iter().all(|&c| c > 240));
+            }
+        }
+
+        let big = ExportOptions {
+            scale: 10.0,
+            ..options(ExportFormat::Ico)
+        };
+        assert!(export(&svg, &dir.path().join("big.ico"), &big).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+//! App icon sets generated from one SVG master
+//!
+//! Every bitmap is rendered straight from the SVG at its final size through
+//! [`svg2utf::render_square`], the pipeline behind the mini icons in listings,
+//! so small sizes stay sharp instead of being downscaled from a large render.
+
+use anyhow::{Context, Result};
+use image::codecs::ico::{IcoEncoder, IcoFrame};
+use image::{ColorType, RgbaImage};
+use serde_json::{json, Value};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use crate::svg2utf;
+
+/// Default sizes of the `icon-<size>x<size>.png` set
+pub const PNG_SIZES: [u32; 10] = [16, 32, 48, 64, 128, 180, 192, 256, 512, 1024];
+
+/// Sizes packed into `icon.ico`, for desktop shortcuts and installers
+const APP_ICO_SIZES: [u32; 7] = [16, 24, 32, 48, 64, 128, 256];
+
+/// Sizes packed into `favicon.ico`
+const FAVICON_ICO_SIZES: [u32; 3] = [16, 32, 48];
+
+/// Size of `apple-touch-icon.png`
+const APPLE_TOUCH_SIZE: u32 = 180;
+
+/// Where manifest icon entries start; smaller PNGs are only useful as favicons
+const MANIFEST_MIN_SIZE: u32 = 48;
+
+/// Files written by [`generate`]
+#[derive(Debug, Default)]
+pub struct IconSet {
+    pub files: Vec<PathBuf>,
+}
+
+/// Renders an SVG at any number of square sizes, each once
+struct Renderer<'a> {
+    svg_path: &'a Path,
+    background: Option<[u8; 4]>,
+    rendered: Vec<(u32, RgbaImage)>,
+}
+
+impl<'a> Renderer<'a> {
+    fn render(&mut self, size: u32) -> Result<&RgbaImage> {
+        let index = match self.rendered.iter().position(|(s, _)| *s == size) {
+            Some(index) => index,
+            None => {
+                let img = svg2utf::render_square(self.svg_path, size, self.background)?;
+                self.rendered.push((size, img));
+                self.rendered.len() - 1
+            }
+        };
+        Ok(&self.rendered[index].1)
+    }
+}
+
+/// Write the PNG set, `icon.ico`, the favicon bundle and `manifest.json` for `svg_path` into `out_dir`
+///
+/// An existing `manifest.json` keeps everything but its `icons` array.
+pub fn generate(
+    svg_path: &Path,
+    out_dir: &Path,
+    sizes: &[u32],
+    background: Option<[u8; 4]>,
+) -> Result<IconSet> {
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("Failed to create {}", out_dir.display()))?;
+    let mut renderer = Renderer {
+        svg_path,
+        background,
+        rendered: Vec::new(),
+    };
+    let mut set = IconSet::default();
+
+    let mut sizes = sizes.to_vec();
+    sizes.sort_unstable();
+    sizes.dedup();
+    for &size in &sizes {
+        let path = out_dir.join(format!("icon-{0}x{0}.png", size));
+        write_png(renderer.render(size)?, &path)?;
+        set.files.push(path);
+    }
+
+    let path = out_dir.join("icon.ico");
+    write_ico(&mut renderer, &APP_ICO_SIZES, &path)?;
+    set.files.push(path);
+
+    // The favicon bundle: what browsers and iOS look for by name
+    let path = out_dir.join("favicon.ico");
+    write_ico(&mut renderer, &FAVICON_ICO_SIZES, &path)?;
+    set.files.push(path);
+    for size in [16, 32] {
+        let path = out_dir.join(format!("favicon-{0}x{0}.png", size));
+        write_png(renderer.render(size)?, &path)?;
+        set.files.push(path);
+    }
+    let path = out_dir.join("apple-touch-icon.png");
+    write_png(renderer.render(APPLE_TOUCH_SIZE)?, &path)?;
+    set.files.push(path);
+    let path = out_dir.join("favicon.svg");
+    fs::copy(svg_path, &path).with_context(|| format!("Failed to write {}", path.display()))?;
+    set.files.push(path);
+
+    let path = out_dir.join("manifest.json");
+    let icons: Vec<u32> = sizes
+        .iter()
+        .copied()
+        .filter(|&s| s >= MANIFEST_MIN_SIZE)
+        .collect();
+    write_manifest(&path, &manifest_icons(&icons))?;
+    set.files.push(path);
+
+    Ok(set)
+}
+
+/// The `icons` block of a web app manifest for the PNG set
+fn manifest_icons(sizes: &[u32]) -> Value {
+    let mut icons: Vec<Value> = sizes
+        .iter()
+        .map(|size| {
+            json!({
+                "src": format!("icon-{0}x{0}.png", size),
+                "sizes": format!("{0}x{0}", size),
+                "type": "image/png",
+            })
+        })
+        .collect();
+    icons.push(json!({
+        "src": "favicon.svg",
+        "sizes": "any",
+        "type": "image/svg+xml",
+    }));
+    Value::Array(icons)
+}
+
+/// `<link>` tags that reference the favicon bundle and manifest
+pub fn html_links() -> &'static str {
+    concat!(
+        "<link rel=\"icon\" href=\"/favicon.ico\" sizes=\"48x48\">\n",
+        "<link rel=\"icon\" href=\"/favicon.svg\" type=\"image/svg+xml\">\n",
+        "<link rel=\"apple-touch-icon\" href=\"/apple-touch-icon.png\">\n",
+        "<link rel=\"manifest\" href=\"/manifest.json\">",
+    )
+}
+
+fn write_png(img: &RgbaImage, path: &Path) -> Result<()> {
+    img.save_with_format(path, image::ImageFormat::Png)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Write a multi-resolution ICO with one PNG-compressed frame per size
+fn write_ico(renderer: &mut Renderer, sizes: &[u32], path: &Path) -> Result<()> {
+    let mut frames = Vec::with_capacity(sizes.len());
+    for &size in sizes {
+        let img = renderer.render(size)?;
+        frames.push(
+            IcoFrame::as_png(img.as_raw(), size, size, ColorType::Rgba8)
+                .with_context(|| format!("Failed to encode the {0}x{0} icon", size))?,
+        );
+    }
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    IcoEncoder::new(BufWriter::new(file))
+        .encode_images(&frames)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Set the `icons` of the manifest at `path`, creating it if needed
+fn write_manifest(path: &Path, icons: &Value) -> Result<()> {
+    let mut manifest = if path.exists() {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse {}", path.display()))?
+    } else {
+        json!({})
+    };
+    match manifest.as_object_mut() {
+        Some(object) => object.insert("icons".to_string(), icons.clone()),
+        None => return Err(anyhow::anyhow!("{} is not a JSON object", path.display())),
+    };
+
+    let text = serde_json::to_string_pretty(&manifest)?;
+    fs::write(path, text + "\n").with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_generate_icon_set() -> Result<()> {
+        let dir = tempdir()?;
+        let svg = dir.path().join("logo.svg");
+        fs::write(
+            &svg,
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20"><rect width="40" height="20" fill="#00f"/></svg>"##,
+        )?;
+        let out = dir.path().join("icons");
+        fs::create_dir(&out)?;
+        fs::write(
+            out.join("manifest.json"),
+            r#"{"name": "Demo", "icons": []}"#,
+        )?;
+
+        let set = generate(&svg, &out, &[64, 16, 192], None)?;
+        assert_eq!(set.files.len(), 10);
+
+        // Wide SVGs are centred in a square
+        let img = image::open(out.join("icon-64x64.png"))?.to_rgba8();
+        assert_eq!(img.dimensions(), (64, 64));
+        assert_eq!(img.get_pixel(32, 32).0, [0, 0, 255, 255]);
+        assert_eq!(img.get_pixel(32, 2).0[3], 0);
+
+        let ico = image::codecs::ico::IcoDecoder::new(File::open(out.join("favicon.ico"))?)?;
+        assert_eq!(image::ImageDecoder::dimensions(&ico), (48, 48));
+        let ico = fs::read(out.join("icon.ico"))?;
+        assert_eq!(
+            u16::from_le_bytes([ico[4], ico[5]]),
+            APP_ICO_SIZES.len() as u16
+        );
+
+        let manifest: Value =
+            serde_json::from_str(&fs::read_to_string(out.join("manifest.json"))?)?;
+        assert_eq!(manifest["name"], "Demo");
+        let icons = manifest["icons"].as_array().unwrap();
+        assert_eq!(icons.len(), 3);
+        assert_eq!(icons[0]["src"], "icon-64x64.png");
+        assert_eq!(icons[1]["sizes"], "192x192");
+        assert_eq!(icons[2]["type"], "image/svg+xml");
+        Ok(())
+    }
+}
@@ -0,0 +1,419 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::metadata::SvgInfo;
+use crate::scanner::{self, FileEntry, ScannerConfig};
+
+/// Version of the on-disk index format
+const INDEX_VERSION: u32 = 3;
+
+/// Name of the index file inside the cache directory
+const INDEX_FILE: &str = "index.json";
+
+/// The exclusion settings an index was built with
+///
+/// An index built with different exclusions would hide or invent files, so it
+/// is only used when these match the current configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexFilter {
+    pub exclude_dirs: Vec<String>,
+    pub exclude_patterns: Vec<String>,
+    pub include_hidden: bool,
+}
+
+impl IndexFilter {
+    pub fn from_config(config: &ScannerConfig) -> Self {
+        Self {
+            exclude_dirs: config.exclude_dirs.clone(),
+            exclude_patterns: config
+                .exclude_patterns
+                .iter()
+                .map(|re| re.as_str().to_string())
+                .collect(),
+            include_hidden: config.include_hidden,
+        }
+    }
+}
+
+/// One directory as it looked when it was last read
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedDir {
+    /// Directory mtime; a change means entries were added, removed or renamed
+    modified: Option<SystemTime>,
+    files: Vec<FileEntry>,
+    subdirs: Vec<PathBuf>,
+}
+
+/// Every file below one root directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedRoot {
+    /// Canonical path of the indexed directory
+    pub root: PathBuf,
+    pub built_at: DateTime<Local>,
+    pub refreshed_at: DateTime<Local>,
+    pub filter: IndexFilter,
+    dirs: BTreeMap<PathBuf, IndexedDir>,
+}
+
+/// What a refresh had to do
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RefreshStats {
+    /// Directories visited
+    pub dirs: usize,
+    /// Directories whose mtime changed and were read again
+    pub rescanned: usize,
+    /// Files in the index afterwards
+    pub files: usize,
+}
+
+impl IndexedRoot {
+    /// Walk `root` and record every file the `config` exclusions let through
+    pub fn build(root: &Path, config: &ScannerConfig) -> Result<(Self, RefreshStats)> {
+        let root = root
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve {}", root.display()))?;
+        if !root.is_dir() {
+            return Err(anyhow::anyhow!("Not a directory: {}", root.display()));
+        }
+
+        let now = Local::now();
+        let mut indexed = Self {
+            root,
+            built_at: now,
+            refreshed_at: now,
+            filter: IndexFilter::from_config(config),
+            dirs: BTreeMap::new(),
+        };
+        let stats = indexed.refresh(config);
+        Ok((indexed, stats))
+    }
+
+    /// Bring the index up to date, re-reading only directories whose mtime changed
+    ///
+    /// Edits that rewrite a file in place do not touch the directory mtime and
+    /// are picked up by the next `sview index build`.
+    pub fn refresh(&mut self, config: &ScannerConfig) -> RefreshStats {
+        let mut old = std::mem::take(&mut self.dirs);
+        let mut stats = RefreshStats::default();
+        let root = self.root.clone();
+        refresh_dir(&root, config, &mut old, &mut self.dirs, &mut stats);
+        stats.files = self.file_count();
+        self.refreshed_at = Local::now();
+        stats
+    }
+
+    /// Number of directories whose mtime no longer matches the index
+    pub fn stale_dirs(&self) -> usize {
+        self.dirs
+            .iter()
+            .filter(|(path, dir)| dir_modified(path) != dir.modified)
+            .count()
+    }
+
+    pub fn dir_count(&self) -> usize {
+        self.dirs.len()
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.dirs.values().map(|dir| dir.files.len()).sum()
+    }
+
+    /// Whether `path` (canonical) lies inside this root
+    pub fn contains(&self, path: &Path) -> bool {
+        path.starts_with(&self.root)
+    }
+
+    /// All indexed files below the canonical directory `dir`
+    pub fn files_under<'a>(&'a self, dir: &'a Path) -> impl Iterator<Item = &'a FileEntry> + 'a {
+        self.dirs
+            .range(dir.to_path_buf()..)
+            .take_while(move |(path, _)| path.starts_with(dir))
+            .flat_map(|(_, indexed)| indexed.files.iter())
+    }
+}
+
+fn dir_modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn refresh_dir(
+    dir: &Path,
+    config: &ScannerConfig,
+    old: &mut BTreeMap<PathBuf, IndexedDir>,
+    new: &mut BTreeMap<PathBuf, IndexedDir>,
+    stats: &mut RefreshStats,
+) {
+    stats.dirs += 1;
+    let modified = dir_modified(dir);
+    let indexed = match old.remove(dir) {
+        Some(indexed) if modified.is_some() && indexed.modified == modified => indexed,
+        _ => {
+            stats.rescanned += 1;
+            read_dir(dir, modified, config)
+        }
+    };
+
+    let subdirs = indexed.subdirs.clone();
+    new.insert(dir.to_path_buf(), indexed);
+    for subdir in subdirs {
+        refresh_dir(&subdir, config, old, new, stats);
+    }
+}
+
+fn read_dir(dir: &Path, modified: Option<SystemTime>, config: &ScannerConfig) -> IndexedDir {
+    let mut indexed = IndexedDir {
+        modified,
+        files: Vec::new(),
+        subdirs: Vec::new(),
+    };
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return indexed,
+    };
+
+    for entry in entries.filter_map(Result::ok) {
+        // Like the walker, symlinks are recorded but never followed
+        let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+        if scanner::is_excluded(&entry.file_name().to_string_lossy(), is_dir, config) {
+            continue;
+        }
+
+        let path = entry.path();
+        if is_dir {
+            indexed.subdirs.push(path);
+            continue;
+        }
+
+        let metadata = match fs::metadata(&path) {
+            Ok(m) => m,
+            Err(_) => continue,
+        };
+        let file_type = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|s| s.to_string());
+        let svg = file_type
+            .as_deref()
+            .filter(|ext| ext.eq_ignore_ascii_case("svg"))
+            .and_then(|_| SvgInfo::read(&path));
+
+        indexed.files.push(FileEntry {
+            path,
+            is_dir: false,
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            file_type,
+            svg,
+        });
+    }
+
+    indexed.subdirs.sort();
+    indexed
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexFile {
+    version: u32,
+    roots: Vec<IndexedRoot>,
+}
+
+/// The on-disk scan index (`<cache_dir>/index.json`)
+pub struct ScanIndex {
+    path: PathBuf,
+    roots: Vec<IndexedRoot>,
+}
+
+impl ScanIndex {
+    /// Location of the index file inside `cache_dir`
+    pub fn path_in(cache_dir: &Path) -> PathBuf {
+        cache_dir.join(INDEX_FILE)
+    }
+
+    /// Open the index at `path`, starting empty if it does not exist yet
+    ///
+    /// An index written by another format version is discarded.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let roots = match fs::read(&path) {
+            Ok(data) => {
+                let file: IndexFile = serde_json::from_slice(&data)
+                    .with_context(|| format!("Corrupt scan index: {}", path.display()))?;
+                if file.version == INDEX_VERSION {
+                    file.roots
+                } else {
+                    Vec::new()
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", path.display()));
+            }
+        };
+        Ok(Self { path, roots })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn roots(&self) -> &[IndexedRoot] {
+        &self.roots
+    }
+
+    /// Add or replace the index for `indexed.root`
+    pub fn insert(&mut self, indexed: IndexedRoot) {
+        self.roots.retain(|r| r.root != indexed.root);
+        self.roots.push(indexed);
+        self.roots.sort_by(|a, b| a.root.cmp(&b.root));
+    }
+
+    /// Forget the root at `root` (canonical); returns whether it was indexed
+    pub fn remove(&mut self, root: &Path) -> bool {
+        let before = self.roots.len();
+        self.roots.retain(|r| r.root != root);
+        self.roots.len() != before
+    }
+
+    /// Index covering `path` built with the same exclusions as `config`,
+    /// refreshed against the filesystem
+    ///
+    /// The innermost indexed root containing `path` wins. The index file is
+    /// rewritten if the refresh found changes.
+    pub fn lookup(&mut self, path: &Path, config: &ScannerConfig) -> Result<Option<IndexedRoot>> {
+        let path = match path.canonicalize() {
+            Ok(path) => path,
+            Err(_) => return Ok(None),
+        };
+        let filter = IndexFilter::from_config(config);
+        let found = self
+            .roots
+            .iter_mut()
+            .filter(|r| r.contains(&path) && r.filter == filter)
+            .max_by_key(|r| r.root.components().count());
+        let indexed = match found {
+            Some(indexed) => indexed,
+            None => return Ok(None),
+        };
+
+        let stats = indexed.refresh(config);
+        let indexed = indexed.clone();
+        if stats.rescanned > 0 {
+            self.save()?;
+        }
+        Ok(Some(indexed))
+    }
+
+    /// Write the index atomically
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+
+        let file = IndexFile {
+            version: INDEX_VERSION,
+            roots: self.roots.clone(),
+        };
+        let data = serde_json::to_vec(&file)?;
+
+        // Write to a sibling file first so a crash never leaves a truncated index
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, data)
+            .with_context(|| format!("Failed to write scan index: {}", tmp.display()))?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Failed to replace scan index: {}", self.path.display()))?;
+        Ok(())
+    }
+
+    /// Delete the index file
+    pub fn drop_all(self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e).with_context(|| format!("Failed to remove {}", self.path.display())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_refresh_rereads_only_changed_dirs() -> Result<()> {
+        let dir = tempdir()?;
+        let icons = dir.path().join("icons");
+        fs::create_dir_all(icons.join("small"))?;
+        fs::create_dir_all(dir.path().join("node_modules"))?;
+        File::create(dir.path().join("node_modules").join("skip.svg"))?;
+        fs::write(
+            icons.join("logo.svg"),
+            r#"<svg width="32" height="16" xmlns="http://www.w3.org/2000/svg"/>"#,
+        )?;
+        File::create(icons.join("small").join("dot.svg"))?;
+
+        let config = ScannerConfig::default();
+        let (mut indexed, stats) = IndexedRoot::build(dir.path(), &config)?;
+        assert_eq!((stats.dirs, stats.rescanned, stats.files), (3, 3, 2));
+
+        let icons_dir = indexed.root.join("icons");
+        let logo = indexed
+            .files_under(&icons_dir)
+            .find(|f| f.path.ends_with("logo.svg"))
+            .unwrap();
+        assert_eq!(logo.svg.as_ref().unwrap().width, Some(32.0));
+
+        // Nothing changed, nothing re-read
+        let stats = indexed.refresh(&config);
+        assert_eq!((stats.dirs, stats.rescanned), (3, 0));
+
+        File::create(icons.join("small").join("new.svg"))?;
+        assert_eq!(indexed.stale_dirs(), 1);
+        let stats = indexed.refresh(&config);
+        assert_eq!((stats.rescanned, stats.files), (1, 3));
+
+        fs::remove_dir_all(icons.join("small"))?;
+        let stats = indexed.refresh(&config);
+        assert_eq!((stats.dirs, stats.files), (2, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_roundtrip_and_lookup() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path().join("svgs");
+        fs::create_dir_all(root.join("sub"))?;
+        File::create(root.join("sub").join("a.svg"))?;
+
+        let config = ScannerConfig::default();
+        let path = ScanIndex::path_in(&dir.path().join("cache"));
+        let mut index = ScanIndex::open(&path)?;
+        index.insert(IndexedRoot::build(&root, &config)?.0);
+        index.save()?;
+
+        let mut index = ScanIndex::open(&path)?;
+        assert_eq!(index.roots().len(), 1);
+        let indexed = index.lookup(&root.join("sub"), &config)?.unwrap();
+        assert_eq!(indexed.file_count(), 1);
+
+        // A different exclusion filter does not reuse the index
+        let other = ScannerConfig {
+            include_hidden: false,
+            ..Default::default()
+        };
+        assert!(index.lookup(&root, &other)?.is_none());
+        assert!(index.lookup(dir.path(), &config)?.is_none());
+
+        assert!(index.remove(&root.canonicalize()?));
+        index.drop_all()?;
+        assert!(!path.exists());
+        Ok(())
+    }
+}
@@ -1,225 +1,485 @@
+//! Launching SVGs as progressive web apps and running code snippets
+//!
+//! The SVG is inlined into an HTML wrapper and served from localhost together
+//! with a web app manifest, icons and a service worker, so the browser sees an
+//! installable PWA. sView-enhanced documents get a wrapper with the memory
+//! panel and install button.
+
+use anyhow::{Context, Result};
+use image::{DynamicImage, ImageOutputFormat};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
 use std::fs;
+use std::io::Cursor;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::path::{Path, PathBuf};
-use std::process::Command;
-use std::io::{self, Write};
-use serde::{Deserialize, Serialize};
-use clap::{App, Arg, SubCommand};
-use walkdir::WalkDir;
-use rayon::prelude::*;
-use base64::{Engine as _, engine::general_purpose};
-
-#[derive(Debug, Serialize, Deserialize)]
-struct SvgFile {
-    path: PathBuf,
-    name: String,
-    size: u64,
-    modified: std::time::SystemTime,
-    thumbnail: String, // UTF-8 icon representation
-    is_sview_enhanced: bool,
-    metadata: Option<sViewMetadata>,
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use crate::browser;
+use crate::metadata::{SvgInfo, SviewMetadata};
+use crate::runtime::{Runtime, RUNTIMES};
+use crate::sandbox::{ExecOutput, Sandbox};
+use crate::scanner::{FileEntry, FileScanner, ScannerConfig, SortBy};
+use crate::server::{Asset, StaticServer};
+use crate::settings::{BrowserSettings, Settings};
+use crate::svg2utf;
+
+/// PNG icon sizes listed in the manifest; browsers want 192 and 512 to offer installation
+const MANIFEST_ICON_SIZES: [u32; 2] = [192, 512];
+
+/// Theme colour of wrappers for sView-enhanced SVGs
+const SVIEW_THEME: &str = "#667eea";
+
+/// Theme colour of wrappers for plain SVGs
+const STANDARD_THEME: &str = "#4CAF50";
+
+/// An SVG found by [`SvgScanner`]
+#[derive(Debug, Clone, Serialize)]
+pub struct SvgFile {
+    pub path: PathBuf,
+    pub name: String,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub is_sview_enhanced: bool,
+    pub metadata: Option<SviewMetadata>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct sViewMetadata {
-    version: String,
-    memory_enabled: bool,
-    pwa_capable: bool,
-    interactive: bool,
+/// Finds launchable SVGs with the shared [`FileScanner`]
+pub struct SvgScanner {
+    scanner: FileScanner,
 }
 
-struct SviewConfig {
-    home_dir: PathBuf,
-    cache_dir: PathBuf,
-    browser_command: String,
-    supported_languages: Vec<String>,
-}
+impl SvgScanner {
+    /// A scanner honouring the configured exclusions
+    ///
+    /// `max_depth` overrides `[general] scan_depth`; 0 means unlimited.
+    pub fn new(settings: &Settings, max_depth: Option<usize>) -> Result<Self> {
+        let defaults = ScannerConfig::from_settings(settings)?;
+        let config = ScannerConfig {
+            max_depth: match max_depth {
+                Some(0) => None,
+                Some(depth) => Some(depth),
+                None => defaults.max_depth,
+            },
+            extensions: Some(vec!["svg".to_string()]),
+            sort: Some(SortBy::Name),
+            ..defaults
+        };
+        Ok(Self {
+            scanner: FileScanner::new().with_config(config),
+        })
+    }
 
-impl Default for SviewConfig {
-    fn default() -> Self {
-        let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
-        Self {
-            home_dir: home.clone(),
-            cache_dir: home.join(".sview/cache"),
-            browser_command: "chromium".to_string(),
-            supported_languages: vec![
-                "javascript".to_string(), "python".to_string(), "rust".to_string(),
-                "go".to_string(), "ruby".to_string(), "php".to_string(),
-            ],
-        }
+    /// Every readable SVG under `root`, sorted by file name
+    pub fn scan_svg_files(&self, root: &Path) -> Result<Vec<SvgFile>> {
+        let entries = self.scanner.scan(root)?;
+        Ok(entries
+            .into_iter()
+            .filter(|entry| !entry.is_dir)
+            .filter_map(|entry| Self::process_svg_file(entry).ok())
+            .collect())
+    }
+
+    fn process_svg_file(entry: FileEntry) -> Result<SvgFile> {
+        let content = fs::read_to_string(&entry.path)
+            .with_context(|| format!("Failed to read SVG file: {}", entry.path.display()))?;
+
+        let metadata = SvgInfo::parse(&content).and_then(|info| info.sview);
+        let is_sview_enhanced = metadata.as_ref().is_some_and(|m| m.enhanced);
+
+        Ok(SvgFile {
+            name: entry
+                .path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("unknown")
+                .to_string(),
+            path: entry.path,
+            size: entry.size,
+            modified: entry.modified,
+            is_sview_enhanced,
+            metadata,
+        })
     }
 }
 
-struct SvgScanner {
-    config: SviewConfig,
+/// Opens SVGs as PWAs and runs code in the configured languages
+pub struct SvgLauncher {
+    bind_address: String,
+    browser: BrowserSettings,
+    supported_languages: Vec<String>,
+    sandbox: Sandbox,
 }
 
-impl SvgScanner {
-    fn new() -> Self {
+impl SvgLauncher {
+    pub fn new(settings: &Settings) -> Self {
         Self {
-            config: SviewConfig::default(),
+            bind_address: settings.networking.bind_address.clone(),
+            browser: settings.browser.clone(),
+            supported_languages: settings.languages.supported.clone(),
+            sandbox: Sandbox::from_settings(&settings.languages),
         }
     }
 
-    /// Efektywne skanowanie ext4 - używa parallel walkdir dla prędkości
-    fn scan_svg_files(&self) -> Result<Vec<SvgFile>, Box<dyn std::error::Error>> {
-        println!("🔍 Skanowanie plików SVG w: {:?}", self.config.home_dir);
-
-        let svg_files: Result<Vec<_>, _> = WalkDir::new(&self.config.home_dir)
-            .follow_links(false)
-            .max_depth(10) // Ograniczenie głębokości dla wydajności
-            .into_iter()
-            .par_bridge() // Parallel processing
-            .filter_map(|entry| {
-                let entry = entry.ok()?;
-                let path = entry.path();
-
-                // Szybki filtr po rozszerzeniu
-                if path.extension()?.to_str()? == "svg" {
-                    Some(path.to_path_buf())
-                } else {
-                    None
+    /// Serve `svg_path` as a PWA on localhost and open it in the browser
+    ///
+    /// The app is served until [`PwaSession::wait`] returns.
+    pub fn launch_svg_as_pwa(&self, svg_path: &Path) -> Result<PwaSession> {
+        let assets = self.create_pwa_assets(svg_path)?;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("Failed to start the async runtime")?;
+        let server = runtime.block_on(StaticServer::bind(
+            &self.bind_address,
+            self.browser.local_server_port,
+            assets,
+        ))?;
+
+        // Browsers only treat loopback addresses as secure for PWAs
+        let mut addr = server.local_addr()?;
+        if addr.ip().is_unspecified() {
+            addr.set_ip(match addr.ip() {
+                IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            });
+        }
+        let url = format!("http://{}/", addr);
+
+        let mut requests = server.requests();
+        let server = runtime.spawn(server.run());
+        let env_browser = std::env::var("BROWSER").ok();
+        let mut attempts = Vec::new();
+        let mut used = None;
+        for invocation in browser::candidates(&self.browser, env_browser.as_deref(), &url) {
+            let attempt = runtime.block_on(self.try_browser(&invocation, &mut requests));
+            match attempt {
+                Ok(()) => {
+                    used = Some(invocation.name);
+                    break;
                 }
-            })
-            .map(|path| self.process_svg_file(path))
-            .collect();
+                Err(reason) => attempts.push(format!("{}: {}", invocation.name, reason)),
+            }
+        }
+        let browser = match used {
+            Some(name) => name,
+            None => {
+                opener::open(&url).with_context(|| {
+                    format!(
+                        "Failed to open {} in a browser ({}); install one or change [browser] command",
+                        url,
+                        attempts.join("; ")
+                    )
+                })?;
+                "the system default browser".to_string()
+            }
+        };
+
+        Ok(PwaSession {
+            runtime,
+            server,
+            url,
+            browser,
+            attempts,
+        })
+    }
 
-        svg_files
+    /// Start one browser and wait up to `launch_timeout` for it to request the app
+    ///
+    /// Fails if the browser cannot be started or exits with an error before
+    /// loading the page. A browser that is still running, or that handed the
+    /// URL to an existing window, counts as used even if the page is slow.
+    async fn try_browser(
+        &self,
+        invocation: &browser::Invocation,
+        requests: &mut watch::Receiver<u64>,
+    ) -> std::result::Result<(), String> {
+        requests.borrow_and_update();
+        let mut child = tokio::process::Command::new(&invocation.program)
+            .args(&invocation.args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| e.to_string())?;
+
+        let timeout = tokio::time::sleep(Duration::from_secs(self.browser.launch_timeout));
+        tokio::pin!(timeout);
+        let mut exited = false;
+        loop {
+            tokio::select! {
+                _ = requests.changed() => return Ok(()),
+                status = child.wait(), if !exited => match status {
+                    Ok(status) if status.success() => exited = true,
+                    Ok(status) => return Err(format!("exited with {}", status)),
+                    Err(e) => return Err(e.to_string()),
+                },
+                _ = &mut timeout => {
+                    eprintln!(
+                        "Warning: {} did not load the app within {}s",
+                        invocation.name, self.browser.launch_timeout
+                    );
+                    return Ok(());
+                }
+            }
+        }
     }
 
-    fn process_svg_file(&self, path: PathBuf) -> Result<SvgFile, Box<dyn std::error::Error>> {
-        let metadata = fs::metadata(&path)?;
-        let content = fs::read_to_string(&path)?;
+    /// The wrapper page, the SVG, the manifest, icons and service worker, by URL path
+    fn create_pwa_assets(&self, svg_path: &Path) -> Result<HashMap<String, Asset>> {
+        let svg_content = fs::read_to_string(svg_path)
+            .with_context(|| format!("Failed to read SVG file: {}", svg_path.display()))?;
+        let svg_name = svg_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("SVG App");
 
-        // Sprawdź czy to plik sView-enhanced
-        let is_sview_enhanced = content.contains("xmlns:sview=") || content.contains("sview:enhanced=");
-        let sview_metadata = if is_sview_enhanced {
-            Some(self.extract_sview_metadata(&content))
+        let enhanced = SvgInfo::parse(&svg_content)
+            .and_then(|info| info.sview)
+            .is_some_and(|sview| sview.enhanced);
+        let (html, theme) = if enhanced {
+            (
+                create_sview_pwa_wrapper(&svg_content, svg_name),
+                SVIEW_THEME,
+            )
         } else {
-            None
+            (
+                create_standard_pwa_wrapper(&svg_content, svg_name),
+                STANDARD_THEME,
+            )
         };
 
-        // Generuj miniaturę UTF
-        let thumbnail = self.generate_utf_thumbnail(&content, &path)?;
+        let mut assets = HashMap::new();
+        let mut icons = Vec::new();
+        for size in MANIFEST_ICON_SIZES {
+            // The SVG icon still makes the app installable if rasterizing fails
+            if let Ok(png) = render_icon(svg_path, size) {
+                assets.insert(format!("/icon-{}.png", size), Asset::new("image/png", png));
+                icons.push(size);
+            }
+        }
+        assets.insert(
+            "/index.html".to_string(),
+            Asset::new("text/html; charset=utf-8", html),
+        );
+        assets.insert(
+            "/app.svg".to_string(),
+            Asset::new("image/svg+xml", svg_content),
+        );
+        assets.insert(
+            "/manifest.webmanifest".to_string(),
+            Asset::new(
+                "application/manifest+json",
+                manifest(svg_name, theme, &icons),
+            ),
+        );
+
+        let mut cached: Vec<&str> = assets.keys().map(String::as_str).collect();
+        cached.push("/");
+        cached.sort_unstable();
+        let worker = service_worker(&cached);
+        assets.insert(
+            "/sw.js".to_string(),
+            Asset::new("text/javascript; charset=utf-8", worker),
+        );
+        Ok(assets)
+    }
 
-        Ok(SvgFile {
-            name: path.file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("unknown")
-                .to_string(),
-            path,
-            size: metadata.len(),
-            modified: metadata.modified()?,
-            thumbnail,
-            is_sview_enhanced,
-            metadata: sview_metadata,
-        })
+    /// Run `code` with the interpreter or compiler for `language` in the sandbox,
+    /// with `input` on its stdin
+    ///
+    /// Each run gets a fresh build and working directory, removed afterwards.
+    pub fn execute_language(&self, language: &str, code: &str, input: &[u8]) -> Result<ExecOutput> {
+        let runtime = self.resolve_language(language)?;
+        let program = runtime.detect().ok_or_else(|| {
+            anyhow::anyhow!(
+                "No {} runtime installed (looked for {})",
+                runtime.name,
+                runtime.programs.join(", ")
+            )
+        })?;
+
+        let work_dir = tempfile::Builder::new()
+            .prefix("sview-exec-")
+            .tempdir()
+            .context("Failed to create working directory")?;
+        let work_dir = work_dir.path();
+        let source = work_dir.join(format!("main.{}", runtime.extension));
+        let binary = work_dir
+            .join("main")
+            .with_extension(std::env::consts::EXE_EXTENSION);
+        fs::write(&source, runtime.source(code))
+            .with_context(|| format!("Failed to write {}", source.display()))?;
+
+        let run_args = Runtime::expand(runtime.run, &source, &binary);
+        let command = match runtime.build {
+            Some(build_args) => {
+                let mut build = Command::new(&program);
+                build.args(Runtime::expand(build_args, &source, &binary));
+                let output = self
+                    .sandbox
+                    .without_memory_limit()
+                    .run(build, work_dir, b"")?;
+                if !output.success() {
+                    return Ok(output);
+                }
+                let mut command = Command::new(&binary);
+                command.args(run_args);
+                command
+            }
+            None => {
+                let mut command = Command::new(&program);
+                command.args(run_args);
+                command
+            }
+        };
+        self.sandbox.run(command, work_dir, input)
     }
 
-    fn extract_sview_metadata(&self, content: &str) -> sViewMetadata {
-        sViewMetadata {
-            version: "1.0".to_string(), // TODO: Extract from content
-            memory_enabled: content.contains("memory:system"),
-            pwa_capable: content.contains("manifest") || content.contains("service-worker"),
-            interactive: content.contains("interactive=") || content.contains("onclick"),
+    /// The runtime for `language` if it is known and enabled in `[languages] supported`
+    fn resolve_language(&self, language: &str) -> Result<&'static Runtime> {
+        match Runtime::find(language) {
+            Some(runtime) if self.supported_languages.iter().any(|l| l == runtime.name) => {
+                Ok(runtime)
+            }
+            _ => Err(anyhow::anyhow!(
+                "Unsupported language: {} (available: {})",
+                language,
+                self.available_languages().join(", ")
+            )),
         }
     }
 
-    /// Generuje miniaturę SVG jako icon UTF-8
-    fn generate_utf_thumbnail(&self, content: &str, path: &Path) -> Result<String, Box<dyn std::error::Error>> {
-        // Prosta heurystyka do generowania ikon UTF na podstawie zawartości SVG
-        let icon = if content.contains("<circle") {
-            "🔵" // Koło
-        } else if content.contains("<rect") {
-            "🟪" // Prostokąt
-        } else if content.contains("<path") {
-            "🎨" // Ścieżka/rysunek
-        } else if content.contains("interactive") || content.contains("script") {
-            "⚡" // Interaktywny
-        } else if content.contains("sview:") {
-            "🧠" // sView enhanced
-        } else if content.contains("text") {
-            "📄" // Tekst
-        } else if content.contains("image") {
-            "🖼️" // Obraz
-        } else {
-            "📊" // Domyślny
-        };
-
-        Ok(icon.to_string())
+    /// Languages that are both known and enabled in the configuration
+    pub fn available_languages(&self) -> Vec<&'static str> {
+        RUNTIMES
+            .iter()
+            .map(|runtime| runtime.name)
+            .filter(|name| self.supported_languages.iter().any(|l| l == name))
+            .collect()
     }
 }
 
-struct SvgLauncher {
-    config: SviewConfig,
+/// Escape text for use in HTML content and attribute values
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
 }
 
-impl SvgLauncher {
-    fn new() -> Self {
-        Self {
-            config: SviewConfig::default(),
-        }
+/// A launched app being served from localhost
+pub struct PwaSession {
+    runtime: tokio::runtime::Runtime,
+    server: JoinHandle<Result<()>>,
+    url: String,
+    browser: String,
+    attempts: Vec<String>,
+}
+
+impl PwaSession {
+    pub fn url(&self) -> &str {
+        &self.url
     }
 
-    /// Uruchamia SVG jako aplikację PWA w przeglądarce
-    fn launch_svg_as_pwa(&self, svg_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
-        println!("🚀 Uruchamianie {} jako aplikacja PWA...", svg_path.display());
-
-        // Stwórz tymczasowy HTML wrapper dla PWA
-        let html_wrapper = self.create_pwa_wrapper(svg_path)?;
-        let temp_html = self.config.cache_dir.join("temp_pwa.html");
-
-        // Upewnij się, że katalog cache istnieje
-        fs::create_dir_all(&self.config.cache_dir)?;
-        fs::write(&temp_html, html_wrapper)?;
-
-        // Uruchom w przeglądarce z flagami PWA
-        let mut browser_cmd = Command::new(&self.config.browser_command);
-        browser_cmd
-            .arg("--app=file://".to_owned() + &temp_html.to_string_lossy())
-            .arg("--new-window")
-            .arg("--disable-web-security") // Dla lokalnych plików
-            .arg("--allow-file-access-from-files");
-
-        match browser_cmd.spawn() {
-            Ok(_) => println!("✅ Aplikacja uruchomiona w przeglądarce"),
-            Err(e) => {
-                eprintln!("❌ Błąd uruchamiania przeglądarki: {}", e);
-                println!("💡 Spróbuj zainstalować chromium lub zmienić browser_command w konfiguracji");
-            }
-        }
+    /// The browser the app was opened in
+    pub fn browser(&self) -> &str {
+        &self.browser
+    }
 
-        Ok(())
+    /// Browsers tried before it, with why each was skipped
+    pub fn skipped(&self) -> &[String] {
+        &self.attempts
     }
 
-    fn create_pwa_wrapper(&self, svg_path: &Path) -> Result<String, Box<dyn std::error::Error>> {
-        let svg_content = fs::read_to_string(svg_path)?;
-        let svg_name = svg_path.file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("SVG App");
+    /// Serve the app until Ctrl+C
+    pub fn wait(self) -> Result<()> {
+        let PwaSession {
+            runtime, server, ..
+        } = self;
+        runtime.block_on(async move {
+            tokio::select! {
+                result = server => result.context("The server stopped unexpectedly")?,
+                result = tokio::signal::ctrl_c() => result.context("Failed to listen for Ctrl+C"),
+            }
+        })
+    }
+}
 
-        // Sprawdź czy to sView enhanced SVG
-        let is_sview = svg_content.contains("xmlns:sview=");
+/// Render `svg_path` as a square PNG icon
+fn render_icon(svg_path: &Path, size: u32) -> Result<Vec<u8>> {
+    let img = svg2utf::render_square(svg_path, size, None)?;
+    let mut png = Cursor::new(Vec::new());
+    DynamicImage::ImageRgba8(img)
+        .write_to(&mut png, ImageOutputFormat::Png)
+        .context("Failed to encode PNG")?;
+    Ok(png.into_inner())
+}
 
-        let html = if is_sview {
-            self.create_sview_pwa_wrapper(&svg_content, svg_name)
-        } else {
-            self.create_standard_pwa_wrapper(&svg_content, svg_name)
-        };
+/// The web app manifest, listing the PNG icons of `icon_sizes` and the SVG itself
+fn manifest(app_name: &str, theme: &str, icon_sizes: &[u32]) -> String {
+    let mut icons: Vec<_> = icon_sizes
+        .iter()
+        .map(|size| {
+            json!({
+                "src": format!("/icon-{}.png", size),
+                "sizes": format!("{0}x{0}", size),
+                "type": "image/png",
+            })
+        })
+        .collect();
+    icons.push(json!({
+        "src": "/app.svg",
+        "sizes": "any",
+        "type": "image/svg+xml",
+    }));
+    let manifest = json!({
+        "name": app_name,
+        "short_name": app_name,
+        "start_url": "/",
+        "scope": "/",
+        "display": "standalone",
+        "background_color": theme,
+        "theme_color": theme,
+        "icons": icons,
+    });
+    manifest.to_string()
+}
 
-        Ok(html)
-    }
+/// A service worker that precaches `paths` and falls back to them offline
+fn service_worker(paths: &[&str]) -> String {
+    format!(
+        r#"const CACHE = 'sview-app-v1';
+const ASSETS = {};
+
+self.addEventListener('install', (event) => {{
+    event.waitUntil(caches.open(CACHE).then((cache) => cache.addAll(ASSETS)));
+}});
+
+self.addEventListener('fetch', (event) => {{
+    event.respondWith(fetch(event.request).catch(() => caches.match(event.request)));
+}});
+"#,
+        json!(paths)
+    )
+}
 
-    fn create_sview_pwa_wrapper(&self, svg_content: &str, app_name: &str) -> String {
-        format!(r#"<!DOCTYPE html>
-<html lang="pl">
+fn create_sview_pwa_wrapper(svg_content: &str, app_name: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
 <head>
     <meta charset="UTF-8">
     <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>{}</title>
+    <title>{title}</title>
 
     <!-- PWA Manifest -->
-    <link rel="manifest" href="data:application/json,{manifest}">
-    <meta name="theme-color" content="#667eea">
+    <link rel="manifest" href="/manifest.webmanifest">
+    <meta name="theme-color" content="{theme}">
     <meta name="apple-mobile-web-app-capable" content="yes">
     <meta name="apple-mobile-web-app-status-bar-style" content="default">
 
@@ -287,7 +547,7 @@ impl SvgLauncher {
 </head>
 <body>
     <div class="sview-toolbar">
-        <div>🧠 sView Enhanced: {}</div>
+        <div>🧠 sView Enhanced: {title}</div>
         <div>
             <button onclick="toggleMemory()" style="background: rgba(255,255,255,0.2); border: none; color: white; padding: 5px 10px; border-radius: 5px; cursor: pointer;">
                 💾 Memory
@@ -300,7 +560,7 @@ impl SvgLauncher {
 
     <div class="sview-content">
         <div class="svg-container">
-            {}
+            {svg}
         </div>
     </div>
 
@@ -311,9 +571,12 @@ impl SvgLauncher {
         </div>
     </div>
 
-    <!-- Service Worker dla PWA -->
     <script>
-        // PWA Installation
+        if ('serviceWorker' in navigator) {{
+            navigator.serviceWorker.register('/sw.js');
+        }}
+
+        // PWA installation
         let deferredPrompt;
 
         window.addEventListener('beforeinstallprompt', (e) => {{
@@ -331,11 +594,11 @@ impl SvgLauncher {
                     deferredPrompt = null;
                 }});
             }} else {{
-                alert('Aplikacja może być już zainstalowana lub instalacja nie jest dostępna');
+                alert('The app is already installed or cannot be installed here');
             }}
         }}
 
-        // sView Memory System
+        // sView memory system
         let memorySystem = {{
             factual: {{}},
             episodic: [],
@@ -369,11 +632,9 @@ impl SvgLauncher {
             `;
         }}
 
-        // Inicjalizacja sView
         document.addEventListener('DOMContentLoaded', () => {{
             console.log('🧠 sView Enhanced SVG App initialized');
 
-            // Simulated memory initialization
             memorySystem.working = {{
                 currentTask: 'SVG App Running',
                 startTime: new Date().toISOString(),
@@ -389,28 +650,24 @@ impl SvgLauncher {
     </script>
 </body>
 </html>"#,
-            app_name,
-            manifest = percent_encoding::utf8_percent_encode(
-                &format!(r#"{{"name":"{}","short_name":"{}","start_url":"/","display":"standalone","background_color":"#667eea","theme_color":"#667eea"}}"#,
-                app_name, app_name),
-                percent_encoding::NON_ALPHANUMERIC
-            ),
-            app_name,
-            svg_content
-        )
-    }
+        title = escape_html(app_name),
+        theme = SVIEW_THEME,
+        svg = svg_content,
+    )
+}
 
-    fn create_standard_pwa_wrapper(&self, svg_content: &str, app_name: &str) -> String {
-        format!(r#"<!DOCTYPE html>
-<html lang="pl">
+fn create_standard_pwa_wrapper(svg_content: &str, app_name: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
 <head>
     <meta charset="UTF-8">
     <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>{}</title>
+    <title>{title}</title>
 
     <!-- PWA Manifest -->
-    <link rel="manifest" href="data:application/json,{manifest}">
-    <meta name="theme-color" content="#4CAF50">
+    <link rel="manifest" href="/manifest.webmanifest">
+    <meta name="theme-color" content="{theme}">
     <meta name="apple-mobile-web-app-capable" content="yes">
 
     <style>
@@ -443,10 +700,14 @@ impl SvgLauncher {
 </head>
 <body>
     <div class="svg-container">
-        {}
+        {svg}
     </div>
 
     <script>
+        if ('serviceWorker' in navigator) {{
+            navigator.serviceWorker.register('/sw.js');
+        }}
+
         // Basic PWA functionality
         let deferredPrompt;
 
@@ -459,163 +720,98 @@ impl SvgLauncher {
     </script>
 </body>
 </html>"#,
-            app_name,
-            manifest = percent_encoding::utf8_percent_encode(
-                &format!(r#"{{"name":"{}","short_name":"{}","start_url":"/","display":"standalone","background_color":"#4CAF50","theme_color":"#4CAF50"}}"#,
-                app_name, app_name),
-                percent_encoding::NON_ALPHANUMERIC
-            ),
-            svg_content
-        )
-    }
-
-    /// Uruchamia kod w różnych językach programowania
-    fn execute_language(&self, language: &str, code: &str) -> Result<(), Box<dyn std::error::Error>> {
-        match language.to_lowercase().as_str() {
-            "javascript" | "js" => {
-                Command::new("node")
-                    .arg("-e")
-                    .arg(code)
-                    .status()?;
-            }
-            "python" | "py" => {
-                Command::new("python3")
-                    .arg("-c")
-                    .arg(code)
-                    .status()?;
-            }
-            "rust" | "rs" => {
-                // Dla Rust trzeba stworzyć tymczasowy plik
-                let temp_file = self.config.cache_dir.join("temp.rs");
-                fs::write(&temp_file, code)?;
-                Command::new("rustc")
-                    .arg(&temp_file)
-                    .arg("-o")
-                    .arg(self.config.cache_dir.join("temp"))
-                    .status()?;
-                Command::new(self.config.cache_dir.join("temp"))
-                    .status()?;
-            }
-            _ => {
-                eprintln!("❌ Nieobsługiwany język: {}", language);
-                println!("✅ Obsługiwane języki: {:?}", self.config.supported_languages);
-            }
-        }
-        Ok(())
-    }
+        title = escape_html(app_name),
+        theme = STANDARD_THEME,
+        svg = svg_content,
+    )
 }
 
-struct SviewCLI;
-
-impl SviewCLI {
-    /// Wyświetla listę plików SVG z ikonami UTF
-    fn list_command() -> Result<(), Box<dyn std::error::Error>> {
-        let scanner = SvgScanner::new();
-        let svg_files = scanner.scan_svg_files()?;
-
-        println!("\n📁 Znalezione pliki SVG:\n");
-
-        for file in &svg_files {
-            let sview_badge = if file.is_sview_enhanced { "🧠" } else { "  " };
-            let size_kb = file.size / 1024;
-
-            println!("{} {} {} {:>6} KB  {}",
-                file.thumbnail,
-                sview_badge,
-                file.name,
-                size_kb,
-                file.path.display()
-            );
-        }
-
-        println!("\n📊 Statystyki:");
-        println!("• Łącznie plików SVG: {}", svg_files.len());
-        println!("• sView Enhanced: {}", svg_files.iter().filter(|f| f.is_sview_enhanced).count());
-        println!("• Standardowe SVG: {}", svg_files.iter().filter(|f| !f.is_sview_enhanced).count());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
 
-        Ok(())
+    fn settings(cache_dir: &Path) -> Settings {
+        let mut settings = Settings::default();
+        settings.paths.cache_dir = cache_dir.display().to_string();
+        settings
     }
 
-    fn run_command(file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let launcher = SvgLauncher::new();
-        let path = Path::new(file_path);
-
-        if !path.exists() {
-            eprintln!("❌ Plik nie istnieje: {}", file_path);
-            return Ok(());
-        }
-
-        if path.extension().and_then(|s| s.to_str()) != Some("svg") {
-            eprintln!("❌ To nie jest plik SVG: {}", file_path);
-            return Ok(());
-        }
-
-        launcher.launch_svg_as_pwa(path)?;
+    #[test]
+    fn test_scan_svg_files() -> Result<()> {
+        let dir = tempdir()?;
+        fs::create_dir(dir.path().join("sub"))?;
+        fs::write(
+            dir.path().join("sub/app.svg"),
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:sview="http://sview.veridock.com/schema/v1" sview:version="1.0" sview:interactive="true" onclick="go()"/>"#,
+        )?;
+        fs::write(
+            dir.path().join("plain.svg"),
+            r#"<svg xmlns="http://www.w3.org/2000/svg"/>"#,
+        )?;
+        fs::write(dir.path().join("notes.txt"), "<svg/>")?;
+
+        let scanner = SvgScanner::new(&settings(dir.path()), None)?;
+        let files = scanner.scan_svg_files(dir.path())?;
+        let names: Vec<&str> = files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["app", "plain"]);
+        assert!(!files[1].is_sview_enhanced);
+        let metadata = files[0].metadata.as_ref().unwrap();
+        assert_eq!(metadata.version.as_deref(), Some("1.0"));
+        assert!(metadata.interactive);
+        assert!(!metadata.memory);
         Ok(())
     }
 
-    fn grid_command() -> Result<(), Box<dyn std::error::Error>> {
-        println!("🎨 Uruchamianie interfejsu graficznego...");
-        // TODO: Implementacja interfejsu graficznego (Electron/Tauri)
-        println!("💡 Interfejs graficzny będzie dostępny w następnej wersji");
+    #[test]
+    fn test_pwa_wrapper() -> Result<()> {
+        let dir = tempdir()?;
+        let launcher = SvgLauncher::new(&settings(dir.path()));
+        let svg = dir.path().join("a&b'.svg");
+        fs::write(
+            &svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><rect/></svg>"#,
+        )?;
+
+        let assets = launcher.create_pwa_assets(&svg)?;
+        let mut paths: Vec<&str> = assets.keys().map(String::as_str).collect();
+        paths.sort_unstable();
+        assert_eq!(
+            paths,
+            [
+                "/app.svg",
+                "/icon-192.png",
+                "/icon-512.png",
+                "/index.html",
+                "/manifest.webmanifest",
+                "/sw.js"
+            ]
+        );
+
+        let html = String::from_utf8_lossy(&assets["/index.html"].body).into_owned();
+        assert!(html.contains("<title>a&amp;b'</title>"));
+        assert!(html.contains("<rect/>"));
+        assert!(html.contains(r#"<link rel="manifest" href="/manifest.webmanifest">"#));
+        assert!(!html.contains("sView Enhanced:"));
+
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&assets["/manifest.webmanifest"].body)?;
+        assert_eq!(manifest["name"], "a&b'");
+        assert_eq!(manifest["icons"][1]["sizes"], "512x512");
+        assert!(String::from_utf8_lossy(&assets["/sw.js"].body).contains(r#""/icon-192.png""#));
         Ok(())
     }
-}
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let matches = App::new("sview")
-        .version("1.0.0")
-        .about("🧠 SView - SVG Viewer & PWA Launcher with sView Integration")
-        .author("sView Team")
-        .arg(Arg::with_name("file")
-            .help("Plik SVG do uruchomienia")
-            .index(1))
-        .subcommand(SubCommand::with_name("ls")
-            .about("📋 Listuj wszystkie pliki SVG z miniaturami"))
-        .subcommand(SubCommand::with_name("grid")
-            .about("🎨 Uruchom interfejs graficzny"))
-        .subcommand(SubCommand::with_name("exec")
-            .about("⚡ Wykonaj kod w określonym języku")
-            .arg(Arg::with_name("language")
-                .help("Język programowania")
-                .required(true)
-                .index(1))
-            .arg(Arg::with_name("code")
-                .help("Kod do wykonania")
-                .required(true)
-                .index(2)))
-        .get_matches();
-
-    // Logo i informacje o aplikacji
-    println!("🧠 SView v1.0.0 - SVG Viewer & PWA Launcher");
-    println!("🔗 sView Integration Enabled\n");
-
-    match matches.subcommand() {
-        ("ls", Some(_)) => SviewCLI::list_command()?,
-        ("grid", Some(_)) => SviewCLI::grid_command()?,
-        ("exec", Some(exec_matches)) => {
-            let language = exec_matches.value_of("language").unwrap();
-            let code = exec_matches.value_of("code").unwrap();
-            let launcher = SvgLauncher::new();
-            launcher.execute_language(language, code)?;
-        }
-        _ => {
-            if let Some(file_path) = matches.value_of("file") {
-                SviewCLI::run_command(file_path)?;
-            } else {
-                // Bez argumentów - pokaż pomoc
-                println!("Użycie:");
-                println!("  sview <file.svg>     - Uruchom plik SVG jako aplikację PWA");
-                println!("  sview ls             - Listuj wszystkie pliki SVG");
-                println!("  sview grid           - Interfejs graficzny");
-                println!("  sview exec <lang> <code> - Wykonaj kod");
-                println!("\nPrzykłady:");
-                println!("  sview examples.svg");
-                println!("  sview ls");
-                println!("  sview exec javascript 'console.log(\"Hello from JS!\")'");
-            }
-        }
+    #[test]
+    fn test_resolve_language() {
+        let mut settings = settings(Path::new("/tmp"));
+        settings.languages.supported = vec!["python".to_string(), "go".to_string()];
+        let launcher = SvgLauncher::new(&settings);
+        assert_eq!(launcher.resolve_language("PY").unwrap().name, "python");
+        assert_eq!(launcher.resolve_language("golang").unwrap().name, "go");
+        assert_eq!(launcher.available_languages(), ["python", "go"]);
+        // Known but disabled, and unknown
+        assert!(launcher.resolve_language("js").is_err());
+        assert!(launcher.resolve_language("cobol").is_err());
     }
-
-    Ok(())
-}
\ No newline at end of file
+}
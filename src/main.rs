@@ -1,16 +1,36 @@
 use chrono::Local;
-use clap::{Args, Parser, Subcommand, ValueEnum};
-use dirs;
-use humansize;
+use clap::{Args, Parser, Subcommand};
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process;
-use std::time::SystemTime;
-use terminal_size;
 
+mod browser;
+mod embedded;
+mod export;
+mod icons;
+mod index;
+mod launcher;
 mod memory;
+mod metadata;
+mod output;
+mod runtime;
+mod sandbox;
 mod scanner;
+mod server;
+mod settings;
 mod svg2utf;
+mod system;
+mod thumbnail;
+#[cfg(feature = "watch")]
+mod watch;
+
+use export::ExportFormat;
+use memory::MemoryType;
+use output::OutputFormat;
+use scanner::SortBy;
+use settings::{ConfigLayers, Settings, ViewMode};
+use svg2utf::{Dither, Fit, Protocol, Symbols};
+use thumbnail::MiniIcons;
 
 /// SView - SVG Viewer & PWA Launcher with sView Integration
 #[derive(Parser, Debug)]
@@ -23,6 +43,10 @@ struct Cli {
     /// Enable verbose output
     #[arg(short, long, global = true)]
     verbose: bool,
+
+    /// Configuration file to use instead of ~/.config/sview/config.toml
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
 }
 
 /// Available subcommands
@@ -37,6 +61,21 @@ enum Commands {
     /// Search for files by name or content
     Search(SearchArgs),
 
+    /// Render SVGs to PNG, JPEG, WebP, BMP, TIFF or ICO files
+    Export(ExportArgs),
+
+    /// Generate app icons, a favicon bundle and PWA manifest icons from an SVG
+    Icons(IconsArgs),
+
+    /// Launch an SVG as a PWA in the browser, or list launchable SVGs in a directory
+    Run(RunArgs),
+
+    /// Run a code snippet in one of the configured languages
+    Exec(ExecArgs),
+
+    /// Build and inspect the scan index used by list, search and view
+    Index(IndexArgs),
+
     /// Manage sView memory
     Memory(MemoryArgs),
 
@@ -45,12 +84,16 @@ enum Commands {
 
     /// System information and diagnostics
     System(SystemArgs),
+
+    /// Show, query and edit configuration
+    Config(ConfigArgs),
 }
 
 /// Arguments for the search command
 #[derive(Args, Debug)]
 struct SearchArgs {
-    /// Search query (filename or content)
+    /// Search query (filename or content); may be omitted with --title or --meta
+    #[arg(default_value = "", hide_default_value = true)]
     query: String,
 
     /// Directory to search in (default: current directory)
@@ -69,13 +112,43 @@ struct SearchArgs {
     #[arg(short = 'i', long)]
     ignore_case: bool,
 
-    /// Maximum depth to search
+    /// Treat the query as a regular expression instead of plain text
+    #[arg(short = 'E', long)]
+    regex: bool,
+
+    /// Only match paths (relative to the search directory) matching this glob, e.g. '**/icons/*.svg'
+    #[arg(short, long)]
+    glob: Option<String>,
+
+    /// Only match SVGs whose <title> (or dc:title) contains this text
+    #[arg(short = 't', long)]
+    title: Option<String>,
+
+    /// Only match SVGs whose metadata field contains a value, e.g. creator=Demo; repeatable
+    ///
+    /// Keys are title, description or any Dublin Core element (creator, date, subject, ...).
+    #[arg(short = 'm', long, value_name = "KEY=VALUE")]
+    meta: Vec<String>,
+
+    /// Maximum depth to search, 0 for unlimited [default: [general] scan_depth]
     #[arg(short = 'd', long)]
     max_depth: Option<usize>,
 
+    /// Scanner threads, 0 for one per core and 1 to search sequentially [default: [performance] max_threads]
+    #[arg(short = 'j', long)]
+    threads: Option<usize>,
+
     /// Show detailed information
     #[arg(short, long)]
     long: bool,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    /// Walk the filesystem even if the scan index covers the directory
+    #[arg(long)]
+    no_index: bool,
 }
 
 /// Arguments for listing files
@@ -85,25 +158,29 @@ struct ListArgs {
     #[arg(default_value_os_t = dirs::home_dir().unwrap_or_else(|| PathBuf::from(".")))]
     path: PathBuf,
 
-    /// File format to filter by (e.g., svg, png, jpg)
-    #[arg(short, long, default_value = "svg")]
-    format: String,
+    /// File format to filter by (e.g., svg, png, jpg) [default: [general] default_format]
+    #[arg(short, long)]
+    format: Option<String>,
 
     /// Show detailed information
     #[arg(short, long)]
     long: bool,
 
-    /// Sort by (name, size, modified)
-    #[arg(short, long, value_enum, default_value_t = SortBy::Name)]
-    sort: SortBy,
+    /// Sort by (name, size, modified); without it files are printed as they are found
+    #[arg(short, long, value_enum)]
+    sort: Option<SortBy>,
 
-    /// Reverse sort order
+    /// Reverse sort order (sorts by name unless --sort is given)
     #[arg(short, long)]
     reverse: bool,
 
-    /// Maximum depth to search (0 for unlimited)
-    #[arg(short = 'd', long, default_value_t = 6)]
-    max_depth: usize,
+    /// Maximum depth to search, 0 for unlimited [default: [general] scan_depth]
+    #[arg(short = 'd', long)]
+    max_depth: Option<usize>,
+
+    /// Scanner threads, 0 for one per core and 1 to scan sequentially [default: [performance] max_threads]
+    #[arg(short = 'j', long)]
+    threads: Option<usize>,
 
     /// Pattern to search for in filenames (regex)
     #[arg(default_value = ".*")]
@@ -116,6 +193,22 @@ struct ListArgs {
     /// Case-insensitive search
     #[arg(short = 'i', long)]
     ignore_case: bool,
+
+    /// Only list paths (relative to the directory) matching this glob, e.g. '**/icons/*.svg'
+    #[arg(short, long)]
+    glob: Option<String>,
+
+    /// Keep running and print files as they are added, removed or changed
+    #[arg(short, long)]
+    watch: bool,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    /// Walk the filesystem even if the scan index covers the directory
+    #[arg(long)]
+    no_index: bool,
 }
 
 /// Arguments for the view command
@@ -144,6 +237,46 @@ struct ViewArgs {
     /// Reverse sort order
     #[arg(short, long)]
     reverse: bool,
+
+    /// Directory layout [default: [ui] default_view]
+    #[arg(long, value_enum)]
+    layout: Option<ViewMode>,
+
+    /// Keep running and redraw the directory when SVGs are added, removed or changed
+    #[arg(short, long)]
+    watch: bool,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    /// Walk the filesystem even if the scan index covers the directory
+    #[arg(long)]
+    no_index: bool,
+
+    /// Terminal graphics protocol for single files
+    #[arg(long, value_enum, default_value_t = Protocol::Auto)]
+    protocol: Protocol,
+
+    /// Columns to draw a single file in [default: terminal width]
+    #[arg(long)]
+    width: Option<u32>,
+
+    /// Rows to draw a single file in [default: terminal height]
+    #[arg(long)]
+    height: Option<u32>,
+
+    /// How a single file is scaled into its area
+    #[arg(long, value_enum, default_value_t = Fit::Contain)]
+    fit: Fit,
+
+    /// Characters to draw a single file with
+    #[arg(long, value_enum, default_value_t = Symbols::Blocks)]
+    symbols: Symbols,
+
+    /// How shading is reduced to what the symbols can show
+    #[arg(long, value_enum, default_value_t = Dither::FloydSteinberg)]
+    dither: Dither,
 }
 
 /// Arguments for memory operations
@@ -183,215 +316,506 @@ enum MemoryCommands {
         /// Key to remove
         key: String,
     },
+
+    /// Encrypt the memory store with a passphrase
+    Lock,
+
+    /// Decrypt the memory store and keep it in plaintext
+    Unlock,
+
+    /// Rotate the encryption key (optionally changing the passphrase)
+    Rekey {
+        /// Only rotate if the key is older than the rotation interval
+        #[arg(long)]
+        if_due: bool,
+    },
 }
 
-/// System subcommands
+/// Arguments for configuration operations
 #[derive(Args, Debug)]
-struct SystemArgs {
+struct ConfigArgs {
     #[command(subcommand)]
-    command: SystemCommands,
+    command: ConfigCommands,
 }
 
-/// System subcommands
+/// Configuration subcommands
 #[derive(Subcommand, Debug)]
-enum SystemCommands {
-    /// Show system information
-    Info,
+enum ConfigCommands {
+    /// Show the merged configuration and where it came from
+    Show,
 
-    /// Check system requirements
-    Check,
+    /// Print the value of a key (e.g. general.scan_depth)
+    Get {
+        /// Dotted key
+        key: String,
+    },
+
+    /// Set a key in the user configuration file
+    Set {
+        /// Dotted key
+        key: String,
+
+        /// New value (TOML literal or plain string)
+        value: String,
+    },
 
-    /// Clean temporary files
-    Clean,
+    /// Report unknown or mistyped keys
+    Validate,
 }
 
-/// Sort criteria
-#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
-pub enum SortBy {
-    Name,
-    Size,
-    Modified,
+/// Arguments for the export command
+#[derive(Args, Debug)]
+struct ExportArgs {
+    /// SVG file, or directory whose SVGs are all exported
+    input: PathBuf,
+
+    /// Output file, or output directory for a batch [default: next to each SVG]
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Image format [default: the output extension, else [export] format]
+    #[arg(short, long, value_enum)]
+    format: Option<ExportFormat>,
+
+    /// Width in pixels (keeps the aspect ratio unless --height is given too)
+    #[arg(long)]
+    width: Option<u32>,
+
+    /// Height in pixels (with --width, the SVG is fitted inside both)
+    #[arg(long)]
+    height: Option<u32>,
+
+    /// Zoom factor
+    #[arg(long, default_value_t = 1.0, conflicts_with_all = ["width", "height"])]
+    scale: f64,
+
+    /// Resolution in dots per inch; 96 is one pixel per SVG unit
+    #[arg(long, default_value_t = export::CSS_DPI, conflicts_with_all = ["width", "height"])]
+    dpi: f64,
+
+    /// Background colour such as '#ffffff', or 'transparent' [default: [export] background]
+    #[arg(long)]
+    background: Option<String>,
+
+    /// JPEG quality, 1-100 [default: [export] quality]
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: Option<u8>,
+
+    /// Maximum depth when exporting a directory, 0 for unlimited [default: [general] scan_depth]
+    #[arg(short = 'd', long)]
+    max_depth: Option<usize>,
 }
 
-impl Default for SortBy {
-    fn default() -> Self {
-        SortBy::Name
-    }
+/// Arguments for the icons command
+#[derive(Args, Debug)]
+struct IconsArgs {
+    /// SVG master to generate icons from
+    svg: PathBuf,
+
+    /// Directory to write the icons to
+    #[arg(short, long, default_value = "icons")]
+    output: PathBuf,
+
+    /// Sizes of the PNG set, comma separated
+    #[arg(long, value_delimiter = ',', default_values_t = icons::PNG_SIZES)]
+    sizes: Vec<u32>,
+
+    /// Background colour such as '#ffffff' [default: transparent]
+    #[arg(long)]
+    background: Option<String>,
 }
 
-impl std::fmt::Display for SortBy {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            SortBy::Name => write!(f, "name"),
-            SortBy::Size => write!(f, "size"),
-            SortBy::Modified => write!(f, "modified"),
-        }
-    }
+/// Arguments for the run command
+#[derive(Args, Debug)]
+struct RunArgs {
+    /// SVG file to launch, or directory to list [default: current directory]
+    #[arg(default_value = ".")]
+    path: PathBuf,
+
+    /// Maximum depth when listing a directory, 0 for unlimited [default: [general] scan_depth]
+    #[arg(short = 'd', long)]
+    max_depth: Option<usize>,
+}
+
+/// Arguments for the exec command
+#[derive(Args, Debug)]
+struct ExecArgs {
+    /// Language to run, e.g. javascript, python or rust
+    #[arg(required_unless_present_any = ["from", "list"])]
+    language: Option<String>,
+
+    /// Source code to run
+    #[arg(required_unless_present_any = ["from", "list"])]
+    code: Option<String>,
+
+    /// List the known languages and which of them are enabled and installed
+    #[arg(long, conflicts_with_all = ["language", "code", "from"])]
+    list: bool,
+
+    /// Run the `<sview:code lang="...">` blocks of an SVG, with its data-store on stdin
+    #[arg(long, value_name = "SVG", conflicts_with_all = ["language", "code"])]
+    from: Option<PathBuf>,
+
+    /// With --from, store the results in the SVG as a new data-store version
+    #[arg(long, requires = "from")]
+    write: bool,
+
+    /// Print stdout, stderr, exit code and timing as JSON
+    #[arg(long)]
+    json: bool,
 }
 
-/// Memory types
-#[derive(ValueEnum, Clone, Debug)]
-enum MemoryType {
-    Factual,
-    Episodic,
-    Semantic,
-    Working,
+/// Arguments for scan index operations
+#[derive(Args, Debug)]
+struct IndexArgs {
+    #[command(subcommand)]
+    command: IndexCommands,
+}
+
+/// Scan index subcommands
+#[derive(Subcommand, Debug)]
+enum IndexCommands {
+    /// Index directories, or rebuild their index from scratch
+    Build {
+        /// Directories to index [default: [paths] scan_paths]
+        paths: Vec<PathBuf>,
+    },
+
+    /// Show indexed directories and how much changed since the last refresh
+    Status,
+
+    /// Forget an indexed directory, or delete the whole index
+    Drop {
+        /// Indexed directory to forget (default: all)
+        path: Option<PathBuf>,
+    },
+}
+
+/// System subcommands
+#[derive(Args, Debug)]
+struct SystemArgs {
+    #[command(subcommand)]
+    command: SystemCommands,
+}
+
+/// System subcommands
+#[derive(Subcommand, Debug)]
+enum SystemCommands {
+    /// Show version, features, configuration, cache, terminal and hardware details
+    Info {
+        /// Print the report as JSON, e.g. for bug reports
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Check the configuration, external tools and terminal capabilities
+    Check,
+
+    /// Remove cached thumbnails and temporary files
+    Clean {
+        /// List what would be removed without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
+    let layers = ConfigLayers::load(cli.config.as_deref())?;
+
+    // Config commands must work even when the configuration does not deserialize
+    if let Commands::Config(args) = &cli.command {
+        return handle_config(args, &layers, cli.config.as_deref());
+    }
+    if let Commands::System(args) = &cli.command {
+        return handle_system(args, &layers, cli.verbose);
+    }
+    let settings = layers.settings()?;
 
     match &cli.command {
-        Commands::List(args) => list_files(args, cli.verbose)?,
-        Commands::View(args) => view_file(args, cli.verbose)?,
-        Commands::Search(args) => search_files(args, cli.verbose)?,
-        Commands::Memory(args) => handle_memory(args, cli.verbose)?,
-        Commands::System(args) => handle_system(args, cli.verbose)?,
-        Commands::Shell => start_shell()?,
+        Commands::List(args) => list_files(args, &settings, cli.verbose)?,
+        Commands::View(args) => view_file(args, &settings, cli.verbose)?,
+        Commands::Search(args) => search_files(args, &settings, cli.verbose)?,
+        Commands::Export(args) => export_files(args, &settings, cli.verbose)?,
+        Commands::Icons(args) => generate_icons(args, cli.verbose)?,
+        Commands::Run(args) => run_svg(args, &settings, cli.verbose)?,
+        Commands::Exec(args) => exec_code(args, &settings)?,
+        Commands::Index(args) => handle_index(args, &settings)?,
+        Commands::Memory(args) => handle_memory(args, &settings, cli.verbose)?,
+        Commands::Shell => start_shell(&settings)?,
+        Commands::Config(_) | Commands::System(_) => unreachable!("handled above"),
     }
 
     Ok(())
 }
 
+/// Handle configuration operations
+fn handle_config(
+    args: &ConfigArgs,
+    layers: &ConfigLayers,
+    override_file: Option<&Path>,
+) -> anyhow::Result<()> {
+    match &args.command {
+        ConfigCommands::Show => {
+            for source in layers.sources() {
+                println!("# source: {}", source);
+            }
+            println!();
+            print!("{}", toml::to_string_pretty(layers.merged())?);
+        }
+        ConfigCommands::Get { key } => {
+            let value = layers
+                .get(key)
+                .ok_or_else(|| anyhow::anyhow!("Unknown configuration key: {}", key))?;
+            println!("{}", settings::display_value(value));
+        }
+        ConfigCommands::Set { key, value } => {
+            let path = match override_file {
+                Some(path) => path.to_path_buf(),
+                None => Settings::user_config_path().ok_or_else(|| {
+                    anyhow::anyhow!("Cannot determine the user configuration directory")
+                })?,
+            };
+            settings::set_in_file(&path, key, value)?;
+            println!("Set {} = {} in {}", key, value, path.display());
+        }
+        ConfigCommands::Validate => {
+            let issues = layers.validate();
+            for source in layers.sources() {
+                println!("Checked: {}", source);
+            }
+            if issues.is_empty() {
+                println!("Configuration is valid");
+            } else {
+                for issue in &issues {
+                    eprintln!("  {}", issue);
+                }
+                return Err(anyhow::anyhow!(
+                    "{} configuration issue(s) found",
+                    issues.len()
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Search for files matching the query
-fn search_files(args: &SearchArgs, verbose: bool) -> anyhow::Result<()> {
+fn search_files(args: &SearchArgs, settings: &Settings, verbose: bool) -> anyhow::Result<()> {
     use std::time::Instant;
 
     let start_time = Instant::now();
     let path = &args.path;
     let query = &args.query;
 
+    if query.is_empty() && args.title.is_none() && args.meta.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Nothing to search for; give a query, --title or --meta"
+        ));
+    }
     if !path.exists() {
         return Err(anyhow::anyhow!("Path does not exist: {}", path.display()));
     }
 
+    let defaults = scanner::ScannerConfig::from_settings(settings)?;
+    let max_depth = match args.max_depth {
+        Some(0) => None,
+        Some(depth) => Some(depth),
+        None => defaults.max_depth,
+    };
+
     if verbose {
-        println!(
+        eprintln!(
             "Searching for '{}' in {} (max depth: {})",
             query,
             path.display(),
-            args.max_depth
-                .map_or_else(|| "unlimited".to_string(), |d| format!("{}", d))
+            max_depth.map_or_else(|| "unlimited".to_string(), |d| format!("{}", d))
         );
     }
 
-    let scanner = scanner::FileScanner::new().with_config(scanner::ScannerConfig {
-        max_depth: args.max_depth,
-        extensions: args.format.as_ref().map(|f| vec![f.clone()]),
-        ..Default::default()
-    });
-
-    let mut found = 0;
-    let _ = scanner.search(path, query, args.content, args.ignore_case, |entry| {
-        found += 1;
-
-        if args.long {
-            let modified = entry
-                .modified
-                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                .map(|d| {
-                    chrono::DateTime::<chrono::Local>::from(std::time::UNIX_EPOCH + d)
-                        .format("%Y-%m-%d %H:%M:%S")
-                        .to_string()
-                })
-                .unwrap_or_else(|| "unknown".to_string());
-
-            println!(
-                "{:>10}  {}  {}",
-                humansize::format_size(entry.size, humansize::BINARY),
-                modified,
-                entry.path.display()
-            );
-        } else {
-            println!("{}", entry.path.display());
-        }
-
-        true // Continue searching
+    let index = lookup_index(path, &defaults, settings, args.no_index, verbose);
+    let scanner = scanner::FileScanner::new()
+        .with_config(scanner::ScannerConfig {
+            max_depth,
+            extensions: args.format.as_ref().map(|f| vec![f.clone()]),
+            ..defaults.with_threads(args.threads)
+        })
+        .with_index(index);
+
+    let mut search_query = if args.regex {
+        scanner::SearchQuery::regex(query, args.ignore_case)?
+    } else {
+        scanner::SearchQuery::literal(query, args.ignore_case)
+    }
+    .with_content(args.content);
+    if let Some(glob) = &args.glob {
+        search_query = search_query.with_glob(glob, args.ignore_case)?;
+    }
+    if let Some(title) = &args.title {
+        search_query = search_query.with_title(title, args.ignore_case);
+    }
+    for filter in &args.meta {
+        search_query = search_query.with_meta(filter, args.ignore_case)?;
+    }
+    let mut out = output::EntryWriter::new(io::stdout().lock(), args.output, args.long);
+    let mut written = Ok(());
+    let found = scanner.search(path, &search_query, |entry| {
+        written = out.write(entry);
+        written.is_ok() // Continue searching
     })?;
+    output::ignore_broken_pipe(written.and_then(|_| out.finish()))?;
 
     if verbose {
         let elapsed = start_time.elapsed();
-        println!("\nFound {} matches in {:.2?}", found, elapsed);
+        eprintln!("\nFound {} matches in {:.2?}", found, elapsed);
     }
 
     Ok(())
 }
 
 /// List files in a directory using search functionality
-fn list_files(args: &ListArgs, verbose: bool) -> anyhow::Result<()> {
+fn list_files(args: &ListArgs, settings: &Settings, verbose: bool) -> anyhow::Result<()> {
+    let defaults = scanner::ScannerConfig::from_settings(settings)?;
+    let max_depth = match args.max_depth {
+        Some(0) => None,
+        Some(depth) => Some(depth),
+        None => defaults.max_depth,
+    };
+    let extensions = match &args.format {
+        Some(format) if format.is_empty() => None,
+        Some(format) => Some(vec![format.clone()]),
+        None => defaults.extensions.clone(),
+    };
+
     if verbose {
-        println!(
+        eprintln!(
             "Searching for '{}' in {} (max depth: {})",
             args.pattern,
             args.path.display(),
-            args.max_depth
+            max_depth.map_or_else(|| "unlimited".to_string(), |d| format!("{}", d))
         );
     }
 
-    let scanner = scanner::FileScanner::new().with_config(scanner::ScannerConfig {
-        max_depth: if args.max_depth > 0 {
-            Some(args.max_depth)
-        } else {
-            None
-        },
-        extensions: if args.format.is_empty() {
-            None
-        } else {
-            Some(vec![args.format.clone()])
-        },
-        recursive: true,
-        ..Default::default()
-    });
+    let index = lookup_index(&args.path, &defaults, settings, args.no_index, verbose);
+    let scanner = scanner::FileScanner::new()
+        .with_config(scanner::ScannerConfig {
+            max_depth,
+            extensions,
+            recursive: true,
+            sort: args.sort.or(args.reverse.then_some(SortBy::Name)),
+            reverse: args.reverse,
+            ..defaults.with_threads(args.threads)
+        })
+        .with_index(index);
+
+    let mut query = scanner::SearchQuery::regex(&args.pattern, args.ignore_case)?
+        .with_content(args.search_content);
+    if let Some(glob) = &args.glob {
+        query = query.with_glob(glob, args.ignore_case)?;
+    }
+
+    if args.watch {
+        return watch_list(scanner, &args.path, query, args.output, args.long);
+    }
 
     // Use search to get incremental results
-    let mut count = 0;
-    let _result = scanner.search(
-        &args.path,
-        &args.pattern,
-        args.search_content,
-        args.ignore_case,
-        |entry| {
-            count += 1;
-
-            if args.long {
-                let modified = entry
-                    .modified
-                    .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
-                    .map(|d| {
-                        let dt = Local::now() - chrono::Duration::from_std(d).unwrap_or_default();
-                        dt.format("%Y-%m-%d %H:%M").to_string()
-                    })
-                    .unwrap_or_else(|| "unknown".to_string());
+    let mut out = output::EntryWriter::new(io::stdout().lock(), args.output, args.long);
+    let mut written = Ok(());
+    let count = scanner.search(&args.path, &query, |entry| {
+        written = out.write(entry);
+        written.is_ok() // Continue processing
+    })?;
+    output::ignore_broken_pipe(written.and_then(|_| out.finish()))?;
 
-                println!(
-                    "{:>10}  {}  {}",
-                    humansize::format_size(entry.size, humansize::BINARY),
-                    modified,
-                    entry.path.display()
-                );
-            } else {
-                println!("{}", entry.path.display());
+    if verbose {
+        eprintln!("\nFound {} matches", count);
+    }
+
+    Ok(())
+}
+
+/// List the current matches, then print changes to them until interrupted
+#[cfg(feature = "watch")]
+fn watch_list(
+    scanner: scanner::FileScanner,
+    path: &Path,
+    query: scanner::SearchQuery,
+    format: OutputFormat,
+    long: bool,
+) -> anyhow::Result<()> {
+    if !matches!(format, OutputFormat::Text | OutputFormat::Ndjson) {
+        return Err(anyhow::anyhow!(
+            "--watch streams changes; use --output text or ndjson"
+        ));
+    }
+
+    let (sort, reverse) = (scanner.config().sort, scanner.config().reverse);
+    let mut watcher = scanner.watch(path, query)?;
+
+    let mut entries: Vec<scanner::FileEntry> = watcher.entries().cloned().collect();
+    if let Some(sort) = sort {
+        scanner::sort_entries(&mut entries, sort, reverse);
+    }
+
+    let mut out = output::EntryWriter::new(io::stdout().lock(), format, long);
+    let written = (|| -> anyhow::Result<()> {
+        for entry in &entries {
+            out.write(entry)?;
+        }
+        out.flush()?;
+        loop {
+            for change in watcher.next_changes(None)? {
+                out.write_change(
+                    change_marker(change.kind),
+                    &change.kind.to_string(),
+                    &change.entry,
+                )?;
             }
+        }
+    })();
+    output::ignore_broken_pipe(written)
+}
 
-            true // Continue processing
-        },
-    )?;
+#[cfg(not(feature = "watch"))]
+fn watch_list(
+    _scanner: scanner::FileScanner,
+    _path: &Path,
+    _query: scanner::SearchQuery,
+    _format: OutputFormat,
+    _long: bool,
+) -> anyhow::Result<()> {
+    Err(watch_unavailable())
+}
 
-    if verbose {
-        println!("\nFound {} matches", count);
+/// Prefix for a change in `--watch` output
+#[cfg(feature = "watch")]
+fn change_marker(kind: watch::ChangeKind) -> char {
+    match kind {
+        watch::ChangeKind::Added => '+',
+        watch::ChangeKind::Removed => '-',
+        watch::ChangeKind::Modified => '~',
     }
+}
 
-    Ok(())
+/// Error returned for `--watch` without the feature
+#[cfg(not(feature = "watch"))]
+fn watch_unavailable() -> anyhow::Error {
+    anyhow::anyhow!("sview was built without the `watch` feature")
 }
 
 /// View an SVG file or directory with UTF-8 icons
-fn view_file(args: &ViewArgs, verbose: bool) -> anyhow::Result<()> {
+fn view_file(args: &ViewArgs, settings: &Settings, verbose: bool) -> anyhow::Result<()> {
     if !args.path.exists() {
         eprintln!("Error: Path not found: {}", args.path.display());
         process::exit(1);
     }
 
-    if args.path.is_file() {
+    if args.path.is_file() && !args.output.is_text() {
+        // Describe the file instead of drawing it
+        let entry = scanner::get_file_metadata(&args.path)?;
+        let mut out = output::EntryWriter::new(io::stdout().lock(), args.output, args.long);
+        output::ignore_broken_pipe(out.write(&entry).and_then(|_| out.finish()))?;
+    } else if args.path.is_file() {
         // Single file view
         if args.browser {
             if verbose {
@@ -401,12 +825,20 @@ fn view_file(args: &ViewArgs, verbose: bool) -> anyhow::Result<()> {
         } else {
             // Display file with UTF-8 rendering
             if let Some(ext) = args.path.extension() {
-                if ext.to_ascii_lowercase() == "svg" {
+                if ext.eq_ignore_ascii_case("svg") {
                     if verbose {
                         println!("Rendering SVG: {}", args.path.display());
                     }
                     // Render the SVG directly to the terminal
-                    svg2utf::render_svg_terminal(&args.path)?;
+                    let options = svg2utf::RenderOptions {
+                        width: args.width,
+                        height: args.height,
+                        fit: args.fit,
+                        protocol: args.protocol,
+                        symbols: args.symbols,
+                        dither: args.dither,
+                    };
+                    output::ignore_broken_pipe(svg2utf::render_svg_terminal(&args.path, &options))?;
                     return Ok(());
                 }
             }
@@ -415,7 +847,7 @@ fn view_file(args: &ViewArgs, verbose: bool) -> anyhow::Result<()> {
     } else {
         // Directory view - list SVGs with icons
         if verbose {
-            println!(
+            eprintln!(
                 "Viewing directory: {} (depth: {})",
                 args.path.display(),
                 args.depth
@@ -423,130 +855,249 @@ fn view_file(args: &ViewArgs, verbose: bool) -> anyhow::Result<()> {
         }
 
         // Configure scanner for SVG files
-        let scanner = scanner::FileScanner::new().with_config(scanner::ScannerConfig {
-            max_depth: if args.depth > 0 {
-                Some(args.depth as usize)
-            } else {
-                None
-            },
-            extensions: Some(vec!["svg".to_string()]),
-            recursive: args.depth > 1,
-            ..Default::default()
-        });
+        let defaults = scanner::ScannerConfig::from_settings(settings)?;
+        let index = lookup_index(&args.path, &defaults, settings, args.no_index, verbose);
+        let scanner = scanner::FileScanner::new()
+            .with_config(scanner::ScannerConfig {
+                max_depth: if args.depth > 0 {
+                    Some(args.depth as usize)
+                } else {
+                    None
+                },
+                extensions: Some(vec!["svg".to_string()]),
+                recursive: args.depth > 1,
+                sort: Some(args.sort),
+                reverse: args.reverse,
+                ..defaults
+            })
+            .with_index(index);
+
+        let layout = args.layout.unwrap_or(settings.ui.default_view);
+        if args.watch && args.output.is_text() {
+            return watch_view(scanner, &args.path, layout, settings);
+        } else if args.watch {
+            let query = scanner::SearchQuery::all();
+            return watch_list(scanner, &args.path, query, args.output, args.long);
+        }
 
         let mut entries: Vec<scanner::FileEntry> = Vec::new();
-        scanner.search(&args.path, ".*", false, false, |entry| {
-            if !entry.path.is_dir() {
-                // Only add files, not directories
-                entries.push(entry.clone());
-            }
+        scanner.search(&args.path, &scanner::SearchQuery::all(), |entry| {
+            entries.push(entry.clone());
             true // Continue processing
         })?;
 
-        // Sort entries based on the sort option
-        match args.sort {
-            SortBy::Name => {
-                entries.sort_by(|a, b| a.path.file_name().cmp(&b.path.file_name()));
-            }
-            SortBy::Size => {
-                entries.sort_by(|a, b| a.size.cmp(&b.size));
-            }
-            SortBy::Modified => {
-                entries.sort_by(|a, b| {
-                    a.modified
-                        .unwrap_or(SystemTime::UNIX_EPOCH)
-                        .cmp(&b.modified.unwrap_or(SystemTime::UNIX_EPOCH))
-                });
-            }
+        if args.output.is_text() {
+            print_svg_entries(&entries, layout, settings);
+        } else {
+            let mut out = output::EntryWriter::new(io::stdout().lock(), args.output, args.long);
+            let written = entries.iter().try_for_each(|entry| out.write(entry));
+            output::ignore_broken_pipe(written.and_then(|_| out.finish()))?;
         }
 
-        if args.reverse {
-            entries.reverse();
+        if verbose {
+            eprintln!("\nFound {} SVG files", entries.len());
         }
+    }
 
-        // Get terminal width with a minimum of 40 characters
-        let term_width = terminal_size::terminal_size()
-            .map(|(w, _)| w.0 as usize)
-            .unwrap_or(80)
-            .max(40);
-
-        // Calculate maximum filename length needed
-        let max_filename_width = entries
-            .iter()
-            .map(|e| {
-                e.path
-                    .file_name()
-                    .unwrap_or_default()
-                    .to_string_lossy()
-                    .len()
-            })
-            .max()
-            .unwrap_or(20)
-            .min(30); // Maximum filename width to prevent very wide columns
-
-        // Calculate layout parameters
-        let icon_width = 2; // Width of the icon plus space
-        let padding = 2; // Space between columns
-        let col_width = icon_width + max_filename_width + padding;
-        let num_cols = (term_width / col_width).max(1);
+    Ok(())
+}
 
+/// Print SVG files with their mini icons in the given layout
+fn print_svg_entries(entries: &[scanner::FileEntry], layout: ViewMode, settings: &Settings) {
+    let mut icons = MiniIcons::new(settings);
+    if layout == ViewMode::List {
         println!("Found {} SVG files:", entries.len());
-        println!("{}", "-".repeat(term_width));
-
-        // Display entries in a grid
-        for (i, entry) in entries.iter().enumerate() {
-            // Show mini icon
-            let icon = svg2utf::svg_to_mini_icon(&entry.path).unwrap_or(' ');
-            print!("{} ", icon);
+        for entry in entries {
+            let icon = icons.get(&entry.path);
+            println!(
+                "{} {:>10}  {}",
+                icon,
+                humansize::format_size(entry.size, humansize::BINARY),
+                entry.path.display()
+            );
+        }
+    } else {
+        print_svg_grid(entries, settings, &mut icons);
+    }
+    icons.save();
+}
 
-            // Show filename (without path)
-            let filename = entry
-                .path
+/// Print SVG files with their mini icons in columns fitted to the terminal
+fn print_svg_grid(entries: &[scanner::FileEntry], settings: &Settings, icons: &mut MiniIcons) {
+    // Get terminal width with a minimum of 40 characters
+    let detected_width = terminal_size::terminal_size().map(|(w, _)| w.0 as usize);
+    let term_width = detected_width.unwrap_or(80).max(40);
+
+    // Calculate maximum filename length needed
+    let max_filename_width = entries
+        .iter()
+        .map(|e| {
+            e.path
                 .file_name()
-                .and_then(|n| n.to_str())
+                .unwrap_or_default()
+                .to_string_lossy()
+                .len()
+        })
+        .max()
+        .unwrap_or(20)
+        .min(30); // Maximum filename width to prevent very wide columns
+
+    // Calculate layout parameters
+    let icon_width = 2; // Width of the icon plus space
+    let padding = 2; // Space between columns
+    let col_width = icon_width + max_filename_width + padding;
+    // Without a terminal, fall back to the configured column count
+    let num_cols = match detected_width {
+        Some(_) => term_width / col_width,
+        None => settings.ui.default_columns,
+    }
+    .max(1);
+
+    println!("Found {} SVG files:", entries.len());
+    println!("{}", "-".repeat(term_width));
+
+    // Display entries in a grid
+    for (i, entry) in entries.iter().enumerate() {
+        // Show mini icon
+        let icon = icons.get(&entry.path);
+        print!("{} ", icon);
+
+        // Show filename (without path)
+        let filename = entry
+            .path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("");
+
+        // Truncate filename if too long
+        let display_name = if filename.len() > max_filename_width {
+            let ext = std::path::Path::new(filename)
+                .extension()
+                .and_then(|e| e.to_str())
                 .unwrap_or("");
+            let name = &filename[..(max_filename_width.saturating_sub(ext.len() + 3))];
+            format!("{}...{}", name, ext)
+        } else {
+            format!("{:width$}", filename, width = max_filename_width)
+        };
 
-            // Truncate filename if too long
-            let display_name = if filename.len() > max_filename_width {
-                let ext = std::path::Path::new(filename)
-                    .extension()
-                    .and_then(|e| e.to_str())
-                    .unwrap_or("");
-                let name = &filename[..(max_filename_width.saturating_sub(ext.len() + 3))];
-                format!("{}...{}", name, ext)
-            } else {
-                format!("{:width$}", filename, width = max_filename_width)
-            };
-
-            print!("{}", display_name);
+        print!("{}", display_name);
 
-            // Add padding or newline
-            if (i + 1) % num_cols == 0 || i == entries.len() - 1 {
-                println!();
-            } else {
-                for _ in 0..padding {
-                    print!(" ");
-                }
+        // Add padding or newline
+        if (i + 1) % num_cols == 0 || i == entries.len() - 1 {
+            println!();
+        } else {
+            for _ in 0..padding {
+                print!(" ");
             }
         }
+    }
+}
 
-        if verbose {
-            println!("\nFound {} SVG files", entries.len());
+/// Draw the directory, then redraw it whenever its SVGs change until interrupted
+#[cfg(feature = "watch")]
+fn watch_view(
+    scanner: scanner::FileScanner,
+    path: &Path,
+    layout: ViewMode,
+    settings: &Settings,
+) -> anyhow::Result<()> {
+    use crossterm::{cursor, terminal};
+
+    let (sort, reverse) = (scanner.config().sort, scanner.config().reverse);
+    let mut watcher = scanner.watch(path, scanner::SearchQuery::all())?;
+    let mut changes: Vec<watch::ChangeEvent> = Vec::new();
+    loop {
+        let mut entries: Vec<scanner::FileEntry> = watcher.entries().cloned().collect();
+        if let Some(sort) = sort {
+            scanner::sort_entries(&mut entries, sort, reverse);
+        }
+
+        if crossterm::tty::IsTty::is_tty(&io::stdout()) {
+            crossterm::execute!(
+                io::stdout(),
+                terminal::Clear(terminal::ClearType::All),
+                cursor::MoveTo(0, 0)
+            )?;
         }
+        print_svg_entries(&entries, layout, settings);
+        for change in &changes {
+            println!(
+                "{} {}",
+                change_marker(change.kind),
+                change.entry.path.display()
+            );
+        }
+        io::stdout().flush()?;
+
+        changes = watcher.next_changes(None)?;
     }
+}
 
-    Ok(())
+#[cfg(not(feature = "watch"))]
+fn watch_view(
+    _scanner: scanner::FileScanner,
+    _path: &Path,
+    _layout: ViewMode,
+    _settings: &Settings,
+) -> anyhow::Result<()> {
+    Err(watch_unavailable())
 }
 
+/// Environment variable holding the memory store passphrase
+const PASSPHRASE_ENV: &str = "SVIEW_MEMORY_PASSPHRASE";
+
+/// Environment variable holding the new passphrase for `memory rekey`
+const NEW_PASSPHRASE_ENV: &str = "SVIEW_MEMORY_NEW_PASSPHRASE";
+
 /// Handle memory operations
-fn handle_memory(args: &MemoryArgs, verbose: bool) -> anyhow::Result<()> {
+fn handle_memory(args: &MemoryArgs, settings: &Settings, verbose: bool) -> anyhow::Result<()> {
+    use memory::crypto::KdfParams;
+
+    let kdf_params = KdfParams {
+        memory_kib: settings.security.argon2_memory,
+        iterations: settings.security.argon2_iterations,
+        parallelism: settings.security.argon2_parallelism,
+    };
+    let rotation_days = settings.security.key_rotation_days;
+
+    let path = memory::MemoryStore::default_path();
+    let mut passphrase = None;
+    let mut store = memory::MemoryStore::open_with_passphrase(&path, || {
+        let entered = read_passphrase("Memory passphrase: ", PASSPHRASE_ENV, false)?;
+        passphrase = Some(entered.clone());
+        Ok(entered)
+    })?;
+    if verbose {
+        println!(
+            "Using memory store: {}{}",
+            store.path().display(),
+            if store.is_encrypted() {
+                " (encrypted)"
+            } else {
+                ""
+            }
+        );
+    }
+    if store.rotation_due(rotation_days) && !matches!(args.command, MemoryCommands::Rekey { .. }) {
+        eprintln!(
+            "Warning: memory store key is older than {} days; run `sview memory rekey`",
+            rotation_days
+        );
+    }
+
     match &args.command {
         MemoryCommands::List => {
             if verbose {
                 println!("Listing all memory entries...");
             }
-            // TODO: Implement memory listing
-            println!("Memory listing not yet implemented");
+            let entries = store.entries();
+            if entries.is_empty() {
+                println!("No memory entries");
+            }
+            for entry in &entries {
+                print_memory_entry(entry);
+            }
         }
         MemoryCommands::Add {
             key,
@@ -561,54 +1112,834 @@ fn handle_memory(args: &MemoryArgs, verbose: bool) -> anyhow::Result<()> {
                     value
                 );
             }
-            // TODO: Implement memory add
-            println!("Memory add not yet implemented");
+            store.add(*memory_type, key, value);
+            store.save()?;
+            println!("Stored {} memory entry: {}", memory_type, key);
         }
         MemoryCommands::Get { key } => {
             if verbose {
                 println!("Getting memory entry: {}", key);
             }
-            // TODO: Implement memory get
-            println!("Memory get not yet implemented");
+            let entries = store.get(key);
+            if entries.is_empty() {
+                return Err(anyhow::anyhow!("No memory entry found for key: {}", key));
+            }
+            for entry in &entries {
+                print_memory_entry(entry);
+            }
         }
         MemoryCommands::Remove { key } => {
             if verbose {
                 println!("Removing memory entry: {}", key);
             }
-            // TODO: Implement memory remove
-            println!("Memory remove not yet implemented");
+            let removed = store.remove(key);
+            if removed == 0 {
+                return Err(anyhow::anyhow!("No memory entry found for key: {}", key));
+            }
+            store.save()?;
+            println!(
+                "Removed {} memory entr{} for key: {}",
+                removed,
+                if removed == 1 { "y" } else { "ies" },
+                key
+            );
+        }
+        MemoryCommands::Lock => {
+            if !settings.sview.memory_encryption {
+                return Err(anyhow::anyhow!(
+                    "Memory encryption is disabled ([sview] memory_encryption = false)"
+                ));
+            }
+            if store.is_encrypted() {
+                return Err(anyhow::anyhow!("Memory store is already encrypted"));
+            }
+            let passphrase = read_passphrase("New passphrase: ", PASSPHRASE_ENV, true)?;
+            store.lock(&passphrase, kdf_params)?;
+            store.save()?;
+            println!("Memory store encrypted: {}", store.path().display());
+        }
+        MemoryCommands::Unlock => {
+            store.unlock()?;
+            store.save()?;
+            println!("Memory store decrypted: {}", store.path().display());
+        }
+        MemoryCommands::Rekey { if_due } => {
+            if !store.is_encrypted() {
+                return Err(anyhow::anyhow!(
+                    "Memory store is not encrypted; use `sview memory lock` first"
+                ));
+            }
+            let created = store.key_created().unwrap_or_else(Local::now);
+            let age_days = (Local::now() - created).num_days();
+            if *if_due && !store.rotation_due(rotation_days) {
+                println!(
+                    "Key is {} days old; rotation not due until {} days",
+                    age_days, rotation_days
+                );
+                return Ok(());
+            }
+
+            let new_passphrase = read_passphrase(
+                "New passphrase (empty to keep the current one): ",
+                NEW_PASSPHRASE_ENV,
+                true,
+            )?;
+            let new_passphrase = if new_passphrase.is_empty() {
+                passphrase.ok_or_else(|| anyhow::anyhow!("Current passphrase unavailable"))?
+            } else {
+                new_passphrase
+            };
+            store.rekey(&new_passphrase, kdf_params)?;
+            store.save()?;
+            println!(
+                "Memory store key rotated (previous key was {} days old)",
+                age_days
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Read a passphrase from `env_var`, or prompt for it without echo
+fn read_passphrase(prompt: &str, env_var: &str, confirm: bool) -> anyhow::Result<String> {
+    use std::io::IsTerminal;
+
+    if let Ok(value) = std::env::var(env_var) {
+        return Ok(value);
+    }
+
+    if !io::stdin().is_terminal() {
+        let mut line = String::new();
+        io::stdin().read_line(&mut line)?;
+        return Ok(line.trim_end_matches(['\r', '\n']).to_string());
+    }
+
+    eprint!("{}", prompt);
+    let first = read_hidden_line()?;
+    if confirm && !first.is_empty() {
+        eprint!("Confirm passphrase: ");
+        if read_hidden_line()? != first {
+            return Err(anyhow::anyhow!("Passphrases do not match"));
+        }
+    }
+    Ok(first)
+}
+
+/// Read one line from the terminal without echoing it
+fn read_hidden_line() -> anyhow::Result<String> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use crossterm::terminal;
+
+    io::stderr().flush()?;
+    terminal::enable_raw_mode()?;
+    let mut line = String::new();
+    let result = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Backspace => {
+                    line.pop();
+                }
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    break Err(anyhow::anyhow!("Interrupted"));
+                }
+                KeyCode::Char(c) => line.push(c),
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(e) => break Err(e.into()),
+        }
+    };
+    terminal::disable_raw_mode()?;
+    eprintln!();
+    result.map(|_| line)
+}
+
+/// Print a single memory entry as `timestamp  type  key = value`
+fn print_memory_entry(entry: &memory::MemoryEntry) {
+    println!(
+        "{}  {:<8}  {} = {}",
+        entry.timestamp.format("%Y-%m-%d %H:%M:%S"),
+        entry.memory_type,
+        entry.key,
+        entry.value
+    );
+}
+
+/// Up-to-date scan index covering `path`, unless disabled or unusable
+///
+/// A broken index only costs speed, so problems are reported and the caller
+/// falls back to walking the filesystem.
+fn lookup_index(
+    path: &Path,
+    config: &scanner::ScannerConfig,
+    settings: &Settings,
+    disabled: bool,
+    verbose: bool,
+) -> Option<index::IndexedRoot> {
+    if disabled {
+        return None;
+    }
+    let found = index::ScanIndex::open(index::ScanIndex::path_in(&settings.paths.cache_dir()))
+        .and_then(|mut index| index.lookup(path, config));
+    match found {
+        Ok(Some(indexed)) => {
+            if verbose {
+                eprintln!("Using scan index of {}", indexed.root.display());
+            }
+            Some(indexed)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            eprintln!("Warning: ignoring scan index: {:#}", e);
+            None
+        }
+    }
+}
+
+/// Handle the export command
+fn export_files(args: &ExportArgs, settings: &Settings, verbose: bool) -> anyhow::Result<()> {
+    use rayon::prelude::*;
+
+    let input = &args.input;
+    if !input.exists() {
+        return Err(anyhow::anyhow!("Path does not exist: {}", input.display()));
+    }
+
+    let background = export::parse_color(
+        args.background
+            .as_deref()
+            .unwrap_or(&settings.export.background),
+    )?;
+    let default_format = ExportFormat::from_name(&settings.export.format).ok_or_else(|| {
+        anyhow::anyhow!("Unsupported [export] format '{}'", settings.export.format)
+    })?;
+    let options = |format| export::ExportOptions {
+        format,
+        width: args.width,
+        height: args.height,
+        scale: args.scale,
+        dpi: args.dpi,
+        background,
+        quality: args.quality.unwrap_or(settings.export.quality),
+    };
+
+    if input.is_file() {
+        let format = args
+            .format
+            .or_else(|| args.output.as_deref().and_then(ExportFormat::from_path))
+            .unwrap_or(default_format);
+        let output = args
+            .output
+            .clone()
+            .unwrap_or_else(|| input.with_extension(format.extension()));
+        let (width, height) = export::export(input, &output, &options(format))?;
+        println!(
+            "{} -> {} ({}x{})",
+            input.display(),
+            output.display(),
+            width,
+            height
+        );
+        return Ok(());
+    }
+
+    let format = args.format.unwrap_or(default_format);
+    let defaults = scanner::ScannerConfig::from_settings(settings)?;
+    let max_depth = match args.max_depth {
+        Some(0) => None,
+        Some(depth) => Some(depth),
+        None => defaults.max_depth,
+    };
+    let scanner = scanner::FileScanner::new().with_config(scanner::ScannerConfig {
+        max_depth,
+        extensions: Some(vec!["svg".to_string()]),
+        sort: Some(SortBy::Name),
+        ..defaults
+    });
+
+    let mut inputs = Vec::new();
+    scanner.search(input, &scanner::SearchQuery::all(), |entry| {
+        if !entry.is_dir {
+            inputs.push(entry.path.clone());
+        }
+        true
+    })?;
+    if verbose {
+        eprintln!(
+            "Exporting {} SVG files from {}",
+            inputs.len(),
+            input.display()
+        );
+    }
+
+    let options = options(format);
+    let results: Vec<_> = inputs
+        .par_iter()
+        .map(|svg| {
+            let relative = svg.strip_prefix(input).unwrap_or(svg);
+            let output = match &args.output {
+                Some(dir) => dir.join(relative),
+                None => svg.clone(),
+            }
+            .with_extension(format.extension());
+            let result = export::export(svg, &output, &options);
+            (svg, output, result)
+        })
+        .collect();
+
+    let mut failed = 0;
+    for (svg, output, result) in results {
+        match result {
+            Ok((width, height)) => println!(
+                "{} -> {} ({}x{})",
+                svg.display(),
+                output.display(),
+                width,
+                height
+            ),
+            Err(e) => {
+                eprintln!("Error: {:#}", e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "Exported {} of {} SVG files",
+        inputs.len() - failed,
+        inputs.len()
+    );
+    if failed > 0 {
+        return Err(anyhow::anyhow!(
+            "{} SVG files could not be exported",
+            failed
+        ));
+    }
+    Ok(())
+}
+
+/// Handle the icons command
+fn generate_icons(args: &IconsArgs, verbose: bool) -> anyhow::Result<()> {
+    if !args.svg.is_file() {
+        return Err(anyhow::anyhow!(
+            "File does not exist: {}",
+            args.svg.display()
+        ));
+    }
+    if let Some(size) = args.sizes.iter().find(|&&s| s == 0) {
+        return Err(anyhow::anyhow!("Invalid icon size: {}", size));
+    }
+    let background = match &args.background {
+        Some(color) => export::parse_color(color)?,
+        None => None,
+    };
+
+    let set = icons::generate(&args.svg, &args.output, &args.sizes, background)?;
+    if verbose {
+        for file in &set.files {
+            println!("{}", file.display());
+        }
+    }
+    println!(
+        "Wrote {} icon files to {}",
+        set.files.len(),
+        args.output.display()
+    );
+    println!("\nAdd to your HTML <head>:\n{}", icons::html_links());
+    Ok(())
+}
+
+/// Launch an SVG as a PWA, or list the SVGs a directory offers
+fn run_svg(args: &RunArgs, settings: &Settings, verbose: bool) -> anyhow::Result<()> {
+    let path = &args.path;
+    if !path.exists() {
+        return Err(anyhow::anyhow!("Path does not exist: {}", path.display()));
+    }
+
+    if path.is_file() {
+        if !path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+        {
+            return Err(anyhow::anyhow!("Not an SVG file: {}", path.display()));
+        }
+        let launcher = launcher::SvgLauncher::new(settings);
+        let session = launcher.launch_svg_as_pwa(path)?;
+        if verbose {
+            for skipped in session.skipped() {
+                eprintln!("Skipped browser {}", skipped);
+            }
+        }
+        println!(
+            "Serving {} at {} (opened in {})",
+            path.display(),
+            session.url(),
+            session.browser()
+        );
+        println!("Press Ctrl+C to stop");
+        return session.wait();
+    }
+
+    if verbose {
+        eprintln!("Scanning {} for SVG apps", path.display());
+    }
+    let scanner = launcher::SvgScanner::new(settings, args.max_depth)?;
+    let files = scanner.scan_svg_files(path)?;
+    let mut icons = MiniIcons::new(settings);
+    for file in &files {
+        println!(
+            "{} {} {:>10}  {}",
+            icons.get(&file.path),
+            if file.is_sview_enhanced { "🧠" } else { "  " },
+            humansize::format_size(file.size, humansize::BINARY),
+            file.path.display()
+        );
+    }
+    icons.save();
+
+    let enhanced = files.iter().filter(|f| f.is_sview_enhanced).count();
+    println!(
+        "\nFound {} SVG files ({} sView enhanced); launch one with `sview run <file.svg>`",
+        files.len(),
+        enhanced
+    );
+    Ok(())
+}
+
+/// Run a code snippet and exit with its status
+fn exec_code(args: &ExecArgs, settings: &Settings) -> anyhow::Result<()> {
+    if args.list {
+        return list_runtimes(args.json, settings);
+    }
+    let launcher = launcher::SvgLauncher::new(settings);
+    if let Some(path) = &args.from {
+        return exec_embedded(path, args, &launcher, settings);
+    }
+    let (Some(language), Some(code)) = (&args.language, &args.code) else {
+        unreachable!("clap requires language and code without --from");
+    };
+    let output = launcher.execute_language(language, code, b"")?;
+    if args.json {
+        let json = serde_json::to_string_pretty(&output)?;
+        output::ignore_broken_pipe(writeln!(io::stdout(), "{}", json))?;
+    } else {
+        output::ignore_broken_pipe(io::stdout().write_all(output.stdout.as_bytes()))?;
+        eprint!("{}", output.stderr);
+        if output.truncated {
+            eprintln!("Warning: output was cut off at 1 MiB per stream");
+        }
+    }
+    if output.timed_out {
+        return Err(anyhow::anyhow!(
+            "Timed out after {}s ([languages] execution_timeout)",
+            settings.languages.execution_timeout
+        ));
+    }
+    if !output.success() {
+        process::exit(output.exit_code.unwrap_or(1));
+    }
+    Ok(())
+}
+
+/// Show every known runtime with its aliases, whether it is enabled and what runs it
+fn list_runtimes(json: bool, settings: &Settings) -> anyhow::Result<()> {
+    let statuses: Vec<_> = runtime::RUNTIMES
+        .iter()
+        .map(|runtime| runtime.status(&settings.languages.supported))
+        .collect();
+    if json {
+        let json = serde_json::to_string_pretty(&statuses)?;
+        return output::ignore_broken_pipe(writeln!(io::stdout(), "{}", json));
+    }
+
+    for status in &statuses {
+        let program = match &status.program {
+            Some(path) => path.display().to_string(),
+            None => "not installed".to_string(),
+        };
+        let enabled = if status.enabled {
+            ""
+        } else {
+            "  (disabled in [languages] supported)"
+        };
+        output::ignore_broken_pipe(writeln!(
+            io::stdout(),
+            "{:<12} {:<16} {}{}",
+            status.name,
+            status.aliases.join(", "),
+            program,
+            enabled
+        ))?;
+    }
+    Ok(())
+}
+
+/// Run the code blocks of an sView document, optionally storing their results in it
+fn exec_embedded(
+    path: &Path,
+    args: &ExecArgs,
+    launcher: &launcher::SvgLauncher,
+    settings: &Settings,
+) -> anyhow::Result<()> {
+    let mut doc = embedded::Document::read(path)?;
+    let blocks = doc.code_blocks();
+    if blocks.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No <sview:code lang=\"...\"> blocks in {}",
+            path.display()
+        ));
+    }
+    let store = doc.data_store();
+    let input = store.as_ref().map_or("", |store| store.content.as_str());
+
+    let mut outputs = Vec::new();
+    for block in &blocks {
+        if !args.json {
+            eprintln!("==> {} ({})", block.id, block.language);
+        }
+        let output = launcher.execute_language(&block.language, &block.code, input.as_bytes())?;
+        if !args.json {
+            output::ignore_broken_pipe(io::stdout().write_all(output.stdout.as_bytes()))?;
+            eprint!("{}", output.stderr);
+            if output.timed_out {
+                eprintln!(
+                    "Timed out after {}s ([languages] execution_timeout)",
+                    settings.languages.execution_timeout
+                );
+            }
+        }
+        outputs.push(output);
+    }
+
+    if args.json {
+        let results: Vec<_> = blocks
+            .iter()
+            .zip(&outputs)
+            .map(|(block, output)| {
+                let mut result = serde_json::to_value(output).unwrap_or_default();
+                result["id"] = block.id.clone().into();
+                result["language"] = block.language.clone().into();
+                result
+            })
+            .collect();
+        let json = serde_json::to_string_pretty(&results)?;
+        output::ignore_broken_pipe(writeln!(io::stdout(), "{}", json))?;
+    }
+
+    if args.write {
+        let mut results = serde_json::Map::new();
+        for (block, output) in blocks.iter().zip(&outputs) {
+            // Blocks that print JSON contribute data, anything else is kept as text
+            let value: serde_json::Value = serde_json::from_str(&output.stdout)
+                .unwrap_or_else(|_| output.stdout.trim_end().into());
+            let result = serde_json::json!({
+                "language": block.language,
+                "exit_code": output.exit_code,
+                "output": value,
+            });
+            results.insert(block.id.clone(), result);
+        }
+        let data = doc.data_with_results(results)?;
+        doc.push_data_store(&serde_json::to_string_pretty(&data)?);
+        doc.save(path)?;
+        let version = doc.data_store().map_or(1, |store| store.version);
+        eprintln!(
+            "Stored results in {} as data-store version {}",
+            path.display(),
+            version
+        );
+    }
+
+    let failed = outputs.iter().filter(|output| !output.success()).count();
+    if failed > 0 {
+        return Err(anyhow::anyhow!(
+            "{} of {} code blocks failed",
+            failed,
+            outputs.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Handle scan index operations
+fn handle_index(args: &IndexArgs, settings: &Settings) -> anyhow::Result<()> {
+    use std::time::Instant;
+
+    let mut index = index::ScanIndex::open(index::ScanIndex::path_in(&settings.paths.cache_dir()))?;
+    let config = scanner::ScannerConfig::from_settings(settings)?;
+
+    match &args.command {
+        IndexCommands::Build { paths } => {
+            let paths = if paths.is_empty() {
+                settings
+                    .paths
+                    .scan_paths()
+                    .into_iter()
+                    .filter(|p| p.is_dir())
+                    .collect()
+            } else {
+                paths.clone()
+            };
+            if paths.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "No directories to index; pass paths or set [paths] scan_paths"
+                ));
+            }
+
+            for path in &paths {
+                let start_time = Instant::now();
+                let (indexed, stats) = index::IndexedRoot::build(path, &config)?;
+                println!(
+                    "Indexed {}: {} files in {} directories ({:.2?})",
+                    indexed.root.display(),
+                    stats.files,
+                    stats.dirs,
+                    start_time.elapsed()
+                );
+                index.insert(indexed);
+            }
+            index.save()?;
+        }
+        IndexCommands::Status => {
+            println!("Index: {}", index.path().display());
+            if let Ok(metadata) = std::fs::metadata(index.path()) {
+                println!(
+                    "Size: {}",
+                    humansize::format_size(metadata.len(), humansize::BINARY)
+                );
+            }
+            if index.roots().is_empty() {
+                println!("No directories indexed; run `sview index build <dir>`");
+            }
+
+            let filter = index::IndexFilter::from_config(&config);
+            for indexed in index.roots() {
+                println!();
+                println!("{}", indexed.root.display());
+                println!(
+                    "  {} files in {} directories",
+                    indexed.file_count(),
+                    indexed.dir_count()
+                );
+                println!(
+                    "  built {}, refreshed {}",
+                    indexed.built_at.format("%Y-%m-%d %H:%M:%S"),
+                    indexed.refreshed_at.format("%Y-%m-%d %H:%M:%S")
+                );
+                if !indexed.root.is_dir() {
+                    println!("  directory no longer exists");
+                } else if indexed.filter != filter {
+                    println!("  unused: built with different exclude settings; rebuild it");
+                } else {
+                    match indexed.stale_dirs() {
+                        0 => println!("  up to date"),
+                        n => println!("  {} directories changed since the last refresh", n),
+                    }
+                }
+            }
+        }
+        IndexCommands::Drop { path: Some(path) } => {
+            let root = path.canonicalize().unwrap_or_else(|_| path.clone());
+            if !index.remove(&root) {
+                return Err(anyhow::anyhow!("Not indexed: {}", root.display()));
+            }
+            index.save()?;
+            println!("Dropped index of {}", root.display());
+        }
+        IndexCommands::Drop { path: None } => {
+            let path = index.path().to_path_buf();
+            index.drop_all()?;
+            println!("Deleted {}", path.display());
         }
     }
     Ok(())
 }
 
 /// Handle system operations
-fn handle_system(args: &SystemArgs, verbose: bool) -> anyhow::Result<()> {
+fn handle_system(args: &SystemArgs, layers: &ConfigLayers, verbose: bool) -> anyhow::Result<()> {
     match &args.command {
-        SystemCommands::Info => {
-            println!("SView v{}", env!("CARGO_PKG_VERSION"));
-            println!("Platform: {}", std::env::consts::OS);
-            println!("Architecture: {}", std::env::consts::ARCH);
-            println!("Home directory: {:?}", dirs::home_dir().unwrap_or_default());
+        SystemCommands::Info { json } => {
+            let info = system::collect_info(layers);
+            if *json {
+                let json = serde_json::to_string_pretty(&info)?;
+                output::ignore_broken_pipe(writeln!(io::stdout(), "{}", json))?;
+            } else {
+                print_system_info(&info);
+            }
         }
         SystemCommands::Check => {
-            println!("Checking system requirements...");
-            // TODO: Implement system check
-            println!("All requirements met!");
+            let checks = system::run_checks(layers);
+            let width = checks.iter().map(|c| c.name.len()).max().unwrap_or(0);
+            for check in &checks {
+                println!(
+                    "[{}] {:width$}  {}",
+                    check.status.label(),
+                    check.name,
+                    check.detail,
+                    width = width
+                );
+            }
+
+            let count = |status| checks.iter().filter(|c| c.status == status).count();
+            let (warnings, failures) = (count(system::Status::Warn), count(system::Status::Fail));
+            println!();
+            if failures > 0 {
+                return Err(anyhow::anyhow!(
+                    "{} check(s) failed, {} warning(s)",
+                    failures,
+                    warnings
+                ));
+            }
+            println!("All checks passed ({} warning(s))", warnings);
         }
-        SystemCommands::Clean => {
-            if verbose {
-                println!("Cleaning temporary files...");
+        SystemCommands::Clean { dry_run } => {
+            let settings = layers.settings()?;
+            let targets = system::clean_targets(&settings.paths.cache_dir())?;
+            let total: u64 = targets.iter().map(|t| t.size).sum();
+            if *dry_run || verbose {
+                for target in &targets {
+                    println!(
+                        "{:>10}  {}",
+                        humansize::format_size(target.size, humansize::BINARY),
+                        target.path.display()
+                    );
+                }
+            }
+
+            if *dry_run {
+                println!(
+                    "Would remove {} files, freeing {}",
+                    targets.len(),
+                    humansize::format_size(total, humansize::BINARY)
+                );
+            } else {
+                let freed = system::remove(&targets)?;
+                println!(
+                    "Removed {} files, freed {}",
+                    targets.len(),
+                    humansize::format_size(freed, humansize::BINARY)
+                );
             }
-            // TODO: Implement clean
-            println!("Clean not yet implemented");
         }
     }
     Ok(())
 }
 
+/// Print the `system info` report as text
+fn print_system_info(info: &system::SystemInfo) {
+    let size = |bytes: u64| humansize::format_size(bytes, humansize::BINARY);
+    let list_or_none = |features: &[&str]| match features.join(", ") {
+        list if list.is_empty() => "none".to_string(),
+        list => list,
+    };
+
+    println!("SView v{}", info.version);
+    println!("Platform: {}", info.os);
+    println!("Architecture: {}", info.arch);
+    println!(
+        "Home directory: {}",
+        info.home_dir
+            .as_deref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| "unknown".to_string())
+    );
+    println!("Features: {}", list_or_none(&info.features));
+
+    println!("\nConfiguration:");
+    for source in &info.config.sources {
+        println!("  Source: {}", source);
+    }
+    if let Some(path) = &info.config.user_file {
+        println!("  User file: {}", path.display());
+    }
+    println!(
+        "  Valid: {}",
+        if info.config.valid {
+            "yes"
+        } else {
+            "no (run `sview config validate`)"
+        }
+    );
+
+    println!(
+        "\nCache: {} ({})",
+        info.cache.dir.display(),
+        size(info.cache.total_bytes)
+    );
+    println!(
+        "  Thumbnails: {} ({})",
+        info.cache.thumbnails,
+        size(info.cache.thumbnail_bytes)
+    );
+    match info.cache.index_bytes {
+        Some(bytes) => println!("  Scan index: {}", size(bytes)),
+        None => println!("  Scan index: not built"),
+    }
+
+    println!("\nMemory store: {}", info.memory.path.display());
+    match (info.memory.bytes, &info.memory.entries) {
+        (None, _) => println!("  Not created yet"),
+        (Some(bytes), Some(entries)) => {
+            let counts: Vec<String> = entries
+                .iter()
+                .map(|(kind, count)| format!("{} {}", count, kind))
+                .collect();
+            println!(
+                "  {} entries ({}), {}",
+                entries.values().sum::<usize>(),
+                if counts.is_empty() {
+                    "empty".to_string()
+                } else {
+                    counts.join(", ")
+                },
+                size(bytes)
+            );
+        }
+        (Some(bytes), None) if info.memory.encrypted => {
+            println!("  Encrypted, {}", size(bytes))
+        }
+        (Some(bytes), None) => println!("  Unreadable, {}", size(bytes)),
+    }
+
+    let terminal = &info.terminal;
+    println!("\nTerminal:");
+    println!(
+        "  TERM: {}{}",
+        terminal.term.as_deref().unwrap_or("unset"),
+        terminal
+            .term_program
+            .as_deref()
+            .map(|p| format!(" ({})", p))
+            .unwrap_or_default()
+    );
+    match terminal.size {
+        Some((columns, rows)) => println!("  Size: {}x{}", columns, rows),
+        None if !terminal.is_terminal => println!("  Size: not a terminal"),
+        None => println!("  Size: unknown"),
+    }
+    println!("  Graphics: {}", terminal.graphics);
+    println!("  Colour: {}", terminal.colour.unwrap_or("none"));
+
+    let hardware = &info.hardware;
+    println!("\nHardware:");
+    let cores = hardware
+        .physical_cores
+        .map(|n| format!(", {} physical cores", n))
+        .unwrap_or_default();
+    println!(
+        "  CPU: {} ({} threads{})",
+        hardware.cpu, hardware.cpus, cores
+    );
+    println!(
+        "  Memory: {} total, {} available",
+        size(hardware.total_memory),
+        size(hardware.available_memory)
+    );
+}
+
 /// Start interactive shell
-fn start_shell() -> anyhow::Result<()> {
+fn start_shell(settings: &Settings) -> anyhow::Result<()> {
     println!("SView Interactive Shell");
     println!("Type 'help' for available commands, 'exit' to quit");
 
@@ -632,7 +1963,7 @@ fn start_shell() -> anyhow::Result<()> {
             _ => {
                 // Try to parse as a command
                 let args = input.split_whitespace().collect::<Vec<_>>();
-                if let Err(e) = parse_shell_command(&args) {
+                if let Err(e) = parse_shell_command(&args, settings) {
                     eprintln!("Error: {}", e);
                 }
             }
@@ -643,7 +1974,7 @@ fn start_shell() -> anyhow::Result<()> {
 }
 
 /// Parse and execute shell command
-fn parse_shell_command(args: &[&str]) -> anyhow::Result<()> {
+fn parse_shell_command(args: &[&str], settings: &Settings) -> anyhow::Result<()> {
     if args.is_empty() {
         return Ok(());
     }
@@ -653,16 +1984,21 @@ fn parse_shell_command(args: &[&str]) -> anyhow::Result<()> {
             let path = args.get(1).unwrap_or(&".");
             let list_args = ListArgs {
                 path: PathBuf::from(path),
-                format: "svg".to_string(),
+                format: None,
                 long: args.contains(&"-l") || args.contains(&"--long"),
-                sort: SortBy::Name,
+                sort: None,
                 reverse: args.contains(&"-r") || args.contains(&"--reverse"),
-                max_depth: 6,
+                max_depth: None,
+                threads: None,
                 pattern: ".*".to_string(),
                 search_content: false,
                 ignore_case: false,
+                glob: None,
+                watch: false,
+                output: OutputFormat::Text,
+                no_index: false,
             };
-            list_files(&list_args, false)
+            list_files(&list_args, settings, false)
         }
         "view" | "open" => {
             if let Some(file) = args.get(1) {
@@ -688,8 +2024,18 @@ fn parse_shell_command(args: &[&str]) -> anyhow::Result<()> {
                         SortBy::Name
                     },
                     reverse: args.contains(&"-r") || args.contains(&"--reverse"),
+                    layout: None,
+                    watch: false,
+                    output: OutputFormat::Text,
+                    no_index: false,
+                    protocol: Protocol::Auto,
+                    width: None,
+                    height: None,
+                    fit: Fit::Contain,
+                    symbols: Symbols::Blocks,
+                    dither: Dither::FloydSteinberg,
                 };
-                view_file(&view_args, false)?;
+                view_file(&view_args, settings, false)?;
                 Ok(())
             } else {
                 Err(anyhow::anyhow!("No file specified"))
@@ -714,26 +2060,3 @@ fn print_help() {
     println!("  help             Show this help");
     println!("  exit | quit | q  Exit the shell");
 }
-
-// Implement Display for enums
-impl std::fmt::Display for MemoryType {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            MemoryType::Factual => write!(f, "factual"),
-            MemoryType::Episodic => write!(f, "episodic"),
-            MemoryType::Semantic => write!(f, "semantic"),
-            MemoryType::Working => write!(f, "working"),
-        }
-    }
-}
-
-impl AsRef<str> for MemoryType {
-    fn as_ref(&self) -> &str {
-        match self {
-            MemoryType::Factual => "factual",
-            MemoryType::Episodic => "episodic",
-            MemoryType::Semantic => "semantic",
-            MemoryType::Working => "working",
-        }
-    }
-}
@@ -0,0 +1,253 @@
+//! At-rest encryption for the memory store
+//!
+//! The store is serialized as usual, then sealed with AES-256-GCM under a key
+//! derived from a passphrase with Argon2id. The salt, KDF parameters and key
+//! creation time travel in the clear next to the ciphertext so the key can be
+//! re-derived and its age checked against `key_rotation_days`.
+
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Argon2id cost parameters (`[security] argon2_*`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Memory cost in KiB
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            memory_kib: 65536,
+            iterations: 3,
+            parallelism: 4,
+        }
+    }
+}
+
+/// On-disk form of an encrypted store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedFile {
+    pub version: u32,
+    pub algorithm: String,
+    pub key_derivation: String,
+    pub kdf_params: KdfParams,
+    pub key_created: DateTime<Local>,
+    /// Base64-encoded Argon2 salt
+    pub salt: String,
+    /// Base64-encoded AES-GCM nonce
+    pub nonce: String,
+    /// Base64-encoded ciphertext (including the GCM tag)
+    pub ciphertext: String,
+}
+
+impl EncryptedFile {
+    /// Whether `data` looks like an encrypted store rather than a plaintext one
+    pub fn detect(data: &[u8]) -> bool {
+        serde_json::from_slice::<serde_json::Value>(data)
+            .ok()
+            .is_some_and(|v| v.get("ciphertext").is_some())
+    }
+}
+
+/// Error returned when an encryption operation is requested without the feature
+#[cfg(not(feature = "encryption"))]
+pub fn unavailable() -> anyhow::Error {
+    anyhow::anyhow!("sview was built without the `encryption` feature")
+}
+
+#[cfg(feature = "encryption")]
+pub use sealed::StoreKey;
+
+#[cfg(feature = "encryption")]
+mod sealed {
+    use super::*;
+    use aes_gcm::aead::rand_core::RngCore;
+    use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+    use aes_gcm::{Aes256Gcm, Key, Nonce};
+    use anyhow::{Context, Result};
+    use argon2::{Algorithm, Argon2, Params, Version};
+    use base64::{engine::general_purpose, Engine as _};
+
+    /// Envelope format version
+    const ENVELOPE_VERSION: u32 = 1;
+
+    const ALGORITHM: &str = "AES-256-GCM";
+    const KEY_DERIVATION: &str = "Argon2id";
+
+    const SALT_LEN: usize = 16;
+    const KEY_LEN: usize = 32;
+
+    /// Authenticated alongside the ciphertext so envelopes from other tools are rejected
+    const ASSOCIATED_DATA: &[u8] = b"sview-memory-store";
+
+    /// A derived store key together with what is needed to re-derive it
+    pub struct StoreKey {
+        key: [u8; KEY_LEN],
+        salt: Vec<u8>,
+        params: KdfParams,
+        created: DateTime<Local>,
+    }
+
+    impl StoreKey {
+        /// Derive a fresh key from `passphrase` with a new random salt
+        pub fn derive(passphrase: &str, params: KdfParams) -> Result<Self> {
+            let mut salt = vec![0u8; SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+            Self::derive_with(passphrase, salt, params, Local::now())
+        }
+
+        fn derive_with(
+            passphrase: &str,
+            salt: Vec<u8>,
+            params: KdfParams,
+            created: DateTime<Local>,
+        ) -> Result<Self> {
+            let argon_params = Params::new(
+                params.memory_kib,
+                params.iterations,
+                params.parallelism,
+                Some(KEY_LEN),
+            )
+            .map_err(|e| anyhow::anyhow!("Invalid Argon2 parameters: {}", e))?;
+            let argon = Argon2::new(Algorithm::Argon2id, Version::V0x13, argon_params);
+
+            let mut key = [0u8; KEY_LEN];
+            argon
+                .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+                .map_err(|e| anyhow::anyhow!("Key derivation failed: {}", e))?;
+
+            Ok(Self {
+                key,
+                salt,
+                params,
+                created,
+            })
+        }
+
+        /// When this key was first derived
+        pub fn created(&self) -> DateTime<Local> {
+            self.created
+        }
+
+        /// Whether the key is older than `rotation_days` (0 disables rotation)
+        pub fn rotation_due(&self, rotation_days: i64) -> bool {
+            rotation_days > 0 && (Local::now() - self.created).num_days() >= rotation_days
+        }
+
+        /// Seal `plaintext` into an envelope
+        pub fn encrypt(&self, plaintext: &[u8]) -> Result<EncryptedFile> {
+            let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.key));
+            let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+            let ciphertext = cipher
+                .encrypt(
+                    &nonce,
+                    Payload {
+                        msg: plaintext,
+                        aad: ASSOCIATED_DATA,
+                    },
+                )
+                .map_err(|_| anyhow::anyhow!("Failed to encrypt memory store"))?;
+
+            Ok(EncryptedFile {
+                version: ENVELOPE_VERSION,
+                algorithm: ALGORITHM.to_string(),
+                key_derivation: KEY_DERIVATION.to_string(),
+                kdf_params: self.params,
+                key_created: self.created,
+                salt: general_purpose::STANDARD.encode(&self.salt),
+                nonce: general_purpose::STANDARD.encode(nonce),
+                ciphertext: general_purpose::STANDARD.encode(ciphertext),
+            })
+        }
+
+        /// Re-derive the key for `file` from `passphrase` and open it
+        pub fn decrypt(file: &EncryptedFile, passphrase: &str) -> Result<(Self, Vec<u8>)> {
+            if file.version > ENVELOPE_VERSION {
+                return Err(anyhow::anyhow!(
+                    "Unsupported encrypted store version {}",
+                    file.version
+                ));
+            }
+            if file.algorithm != ALGORITHM || file.key_derivation != KEY_DERIVATION {
+                return Err(anyhow::anyhow!(
+                    "Unsupported encryption scheme: {} / {}",
+                    file.algorithm,
+                    file.key_derivation
+                ));
+            }
+
+            let salt = general_purpose::STANDARD
+                .decode(&file.salt)
+                .context("Corrupt salt in encrypted store")?;
+            let nonce = general_purpose::STANDARD
+                .decode(&file.nonce)
+                .context("Corrupt nonce in encrypted store")?;
+            let ciphertext = general_purpose::STANDARD
+                .decode(&file.ciphertext)
+                .context("Corrupt ciphertext in encrypted store")?;
+            if nonce.len() != 12 {
+                return Err(anyhow::anyhow!("Corrupt nonce in encrypted store"));
+            }
+
+            let key = Self::derive_with(passphrase, salt, file.kdf_params, file.key_created)?;
+            let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key.key));
+            let plaintext = cipher
+                .decrypt(
+                    Nonce::from_slice(&nonce),
+                    Payload {
+                        msg: &ciphertext,
+                        aad: ASSOCIATED_DATA,
+                    },
+                )
+                .map_err(|_| anyhow::anyhow!("Wrong passphrase or corrupted memory store"))?;
+
+            Ok((key, plaintext))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "encryption"))]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    const ROTATION_DAYS: i64 = 90;
+
+    /// Cheap parameters so tests stay fast in debug builds
+    fn test_params() -> KdfParams {
+        KdfParams {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let key = StoreKey::derive("correct horse", test_params())?;
+        let file = key.encrypt(b"secret entries")?;
+        assert!(EncryptedFile::detect(&serde_json::to_vec(&file)?));
+        assert!(!file.ciphertext.contains("secret"));
+
+        let (_, plaintext) = StoreKey::decrypt(&file, "correct horse")?;
+        assert_eq!(plaintext, b"secret entries");
+        assert!(StoreKey::decrypt(&file, "wrong").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rotation_due() -> Result<()> {
+        let key = StoreKey::derive("pw", test_params())?;
+        assert!(!key.rotation_due(ROTATION_DAYS));
+        assert!(!key.rotation_due(0));
+
+        let mut file = key.encrypt(b"x")?;
+        file.key_created = Local::now() - chrono::Duration::days(ROTATION_DAYS);
+        let (old, _) = StoreKey::decrypt(&file, "pw")?;
+        assert!(old.rotation_due(ROTATION_DAYS));
+        Ok(())
+    }
+}
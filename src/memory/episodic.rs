@@ -1,8 +1,16 @@
-use std::collections::VecDeque;
 use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Episode {
+    pub timestamp: DateTime<Local>,
+    pub key: String,
+    pub event: String,
+}
 
 pub struct EpisodicMemory {
-    events: VecDeque<(DateTime<Local>, String)>,
+    events: VecDeque<Episode>,
     max_size: usize,
 }
 
@@ -14,29 +22,32 @@ impl EpisodicMemory {
         }
     }
 
-    pub fn record_event(&mut self, event: String) {
-        let now = Local::now();
-        self.events.push_back((now, event));
-        
+    /// Append an episode with a known timestamp (used when loading from disk)
+    pub fn restore(&mut self, episode: Episode) {
+        self.events.push_back(episode);
+
         if self.events.len() > self.max_size {
             self.events.pop_front();
         }
     }
 
-    pub fn get_recent_events(&self, count: usize) -> Vec<(DateTime<Local>, String)> {
+    /// All episodes recorded under `key`, oldest first
+    pub fn events_for(&self, key: &str) -> Vec<Episode> {
         self.events
             .iter()
-            .rev()
-            .take(count)
-            .map(|&(time, ref event)| (time, event.clone()))
+            .filter(|e| e.key == key)
+            .cloned()
             .collect()
     }
 
-    pub fn get_events_between(&self, start: DateTime<Local>, end: DateTime<Local>) -> Vec<(DateTime<Local>, String)> {
-        self.events
-            .iter()
-            .filter(|&&(time, _)| time >= start && time <= end)
-            .map(|&(time, ref event)| (time, event.clone()))
-            .collect()
+    /// Remove every episode recorded under `key`, returning how many were dropped
+    pub fn forget(&mut self, key: &str) -> usize {
+        let before = self.events.len();
+        self.events.retain(|e| e.key != key);
+        before - self.events.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Episode> {
+        self.events.iter()
     }
 }
@@ -1,29 +1,39 @@
-use std::collections::HashMap;
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fact {
+    pub value: String,
+    pub stored_at: DateTime<Local>,
+}
+
+#[derive(Default)]
 pub struct FactualMemory {
-    facts: HashMap<String, String>,
+    facts: BTreeMap<String, Fact>,
 }
 
 impl FactualMemory {
     pub fn new() -> Self {
         FactualMemory {
-            facts: HashMap::new(),
+            facts: BTreeMap::new(),
         }
     }
 
-    pub fn store(&mut self, key: String, value: String) {
-        self.facts.insert(key, value);
+    /// Insert a fact with a known timestamp (used when loading from disk)
+    pub fn restore(&mut self, key: String, value: String, stored_at: DateTime<Local>) {
+        self.facts.insert(key, Fact { value, stored_at });
     }
 
-    pub fn retrieve(&self, key: &str) -> Option<&String> {
+    pub fn retrieve(&self, key: &str) -> Option<&Fact> {
         self.facts.get(key)
     }
 
-    pub fn remove(&mut self, key: &str) -> Option<String> {
+    pub fn remove(&mut self, key: &str) -> Option<Fact> {
         self.facts.remove(key)
     }
 
-    pub fn clear(&mut self) {
-        self.facts.clear();
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Fact)> {
+        self.facts.iter()
     }
 }
@@ -1,20 +1,54 @@
-// Memory module placeholder for future use
-// This module will be used for implementing memory-related functionality
+//! sView memory system
+//!
+//! The four memory types hold entries at runtime; [`MemoryStore`] loads
+//! them from and saves them to a single file under `~/.sview/`.
+//!
+//! - factual.rs: key/value facts
+//! - episodic.rs: time-ordered events
+//! - semantic.rs: concepts and their relationships
+//! - working.rs: short-lived items that expire after a timeout
+//!
+//! With the `encryption` feature the store can be sealed at rest (crypto.rs).
 
-// Example of how to structure memory components:
-// - factual.rs: For factual memory storage
-// - episodic.rs: For event-based memory
-// - semantic.rs: For concept and relationship storage
-// - working.rs: For short-term/working memory
+pub mod crypto;
+pub mod episodic;
+pub mod factual;
+pub mod semantic;
+pub mod store;
+pub mod working;
 
-// To use these modules in the future, uncomment the relevant lines below:
-// pub mod factual;
-// pub mod episodic;
-// pub mod semantic;
-// pub mod working;
+pub use episodic::EpisodicMemory;
+pub use factual::FactualMemory;
+pub use semantic::SemanticMemory;
+pub use store::{MemoryEntry, MemoryStore};
+pub use working::WorkingMemory;
 
-// And export the types you need:
-// pub use factual::FactualMemory;
-// pub use episodic::EpisodicMemory;
-// pub use semantic::SemanticMemory;
-// pub use working::WorkingMemory;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// Memory types
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryType {
+    Factual,
+    Episodic,
+    Semantic,
+    Working,
+}
+
+impl std::fmt::Display for MemoryType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.as_ref())
+    }
+}
+
+impl AsRef<str> for MemoryType {
+    fn as_ref(&self) -> &str {
+        match self {
+            MemoryType::Factual => "factual",
+            MemoryType::Episodic => "episodic",
+            MemoryType::Semantic => "semantic",
+            MemoryType::Working => "working",
+        }
+    }
+}
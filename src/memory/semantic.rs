@@ -1,3 +1,5 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Mutex;
 
@@ -5,11 +7,23 @@ pub struct SemanticMemory {
     concepts: Mutex<HashMap<String, Concept>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Concept {
     pub name: String,
     pub attributes: HashMap<String, String>,
     pub relationships: HashMap<String, String>,
+    pub updated_at: DateTime<Local>,
+}
+
+impl Concept {
+    pub fn new(name: String) -> Self {
+        Concept {
+            name,
+            attributes: HashMap::new(),
+            relationships: HashMap::new(),
+            updated_at: Local::now(),
+        }
+    }
 }
 
 impl SemanticMemory {
@@ -29,28 +43,22 @@ impl SemanticMemory {
         concepts.get(name).cloned()
     }
 
-    pub fn add_attribute(&self, concept_name: &str, attribute: String, value: String) {
-        if let Some(concept) = self.get_concept(concept_name) {
-            let mut updated_concept = concept;
-            updated_concept.attributes.insert(attribute, value);
-            self.add_concept(updated_concept);
-        }
+    pub fn remove_concept(&self, name: &str) -> Option<Concept> {
+        let mut concepts = self.concepts.lock().unwrap();
+        concepts.remove(name)
     }
 
-    pub fn add_relationship(&self, concept_name: &str, related_concept: String, relation_type: String) {
-        if let Some(concept) = self.get_concept(concept_name) {
-            let mut updated_concept = concept;
-            updated_concept.relationships.insert(related_concept, relation_type);
-            self.add_concept(updated_concept);
-        }
+    /// Snapshot of all concepts, sorted by name
+    pub fn concepts(&self) -> Vec<Concept> {
+        let concepts = self.concepts.lock().unwrap();
+        let mut all: Vec<Concept> = concepts.values().cloned().collect();
+        all.sort_by(|a, b| a.name.cmp(&b.name));
+        all
     }
+}
 
-    pub fn query_concepts(&self, attribute: &str, value: &str) -> Vec<Concept> {
-        let concepts = self.concepts.lock().unwrap();
-        concepts
-            .values()
-            .filter(|c| c.attributes.get(attribute).map_or(false, |v| v == value))
-            .cloned()
-            .collect()
+impl Default for SemanticMemory {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -0,0 +1,465 @@
+use super::crypto::{EncryptedFile, KdfParams};
+use super::episodic::Episode;
+use super::semantic::Concept;
+use super::{EpisodicMemory, FactualMemory, MemoryType, SemanticMemory, WorkingMemory};
+use anyhow::{Context, Result};
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Current on-disk format version
+const STORE_VERSION: u32 = 1;
+
+/// Attribute under which semantic entries keep the value given on the CLI
+const SEMANTIC_VALUE_ATTRIBUTE: &str = "value";
+
+// Default capacities for the bounded memory types
+const EPISODIC_MAX_EVENTS: usize = 1000;
+const WORKING_MAX_ITEMS: usize = 100;
+const WORKING_TIMEOUT_SECS: u64 = 24 * 60 * 60;
+
+/// A single memory entry as listed by `sview memory list`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryEntry {
+    pub key: String,
+    pub value: String,
+    pub memory_type: MemoryType,
+    pub timestamp: DateTime<Local>,
+}
+
+/// Serialized form of the whole store
+#[derive(Debug, Serialize, Deserialize)]
+struct StoreFile {
+    version: u32,
+    entries: Vec<MemoryEntry>,
+}
+
+/// Durable store backing the four memory types
+pub struct MemoryStore {
+    path: PathBuf,
+    factual: FactualMemory,
+    episodic: EpisodicMemory,
+    semantic: SemanticMemory,
+    working: WorkingMemory,
+    /// Key the store is sealed with, if it is encrypted at rest
+    #[cfg(feature = "encryption")]
+    key: Option<super::crypto::StoreKey>,
+}
+
+impl MemoryStore {
+    /// Default location of the store file (`~/.sview/memory.json`)
+    pub fn default_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(".sview")
+            .join("memory.json")
+    }
+
+    /// Open the store at `path`, starting empty if the file does not exist yet
+    ///
+    /// Fails if the store is encrypted; use [`MemoryStore::open_with_passphrase`] for those.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_with_passphrase(path, || {
+            Err(anyhow::anyhow!(
+                "Memory store is locked; a passphrase is required"
+            ))
+        })
+    }
+
+    /// Open the store at `path`, asking `passphrase` for the key only if it is encrypted
+    pub fn open_with_passphrase<P, F>(path: P, passphrase: F) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        F: FnOnce() -> Result<String>,
+    {
+        let path = path.as_ref();
+        let mut store = Self {
+            path: path.to_path_buf(),
+            factual: FactualMemory::new(),
+            episodic: EpisodicMemory::new(EPISODIC_MAX_EVENTS),
+            semantic: SemanticMemory::new(),
+            working: WorkingMemory::new(WORKING_MAX_ITEMS, WORKING_TIMEOUT_SECS),
+            #[cfg(feature = "encryption")]
+            key: None,
+        };
+
+        if path.exists() {
+            let mut data = fs::read(path)
+                .with_context(|| format!("Failed to read memory store: {}", path.display()))?;
+            if EncryptedFile::detect(&data) {
+                data = store.unseal(&data, passphrase)?;
+            }
+            let file: StoreFile = serde_json::from_slice(&data)
+                .with_context(|| format!("Failed to parse memory store: {}", path.display()))?;
+            if file.version > STORE_VERSION {
+                return Err(anyhow::anyhow!(
+                    "Memory store {} has unsupported version {}",
+                    path.display(),
+                    file.version
+                ));
+            }
+            for entry in file.entries {
+                store.restore(entry);
+            }
+            store.working.expire();
+        }
+
+        Ok(store)
+    }
+
+    /// Path of the backing file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the store is encrypted at rest
+    pub fn is_encrypted(&self) -> bool {
+        #[cfg(feature = "encryption")]
+        return self.key.is_some();
+        #[cfg(not(feature = "encryption"))]
+        return false;
+    }
+
+    /// Encrypt the store with a key derived from `passphrase` on the next save
+    pub fn lock(&mut self, passphrase: &str, params: KdfParams) -> Result<()> {
+        if self.is_encrypted() {
+            return Err(anyhow::anyhow!("Memory store is already encrypted"));
+        }
+        self.set_key(passphrase, params)
+    }
+
+    /// Store the entries in plaintext on the next save
+    pub fn unlock(&mut self) -> Result<()> {
+        if !self.is_encrypted() {
+            return Err(anyhow::anyhow!("Memory store is not encrypted"));
+        }
+        #[cfg(feature = "encryption")]
+        {
+            self.key = None;
+        }
+        Ok(())
+    }
+
+    /// Replace the key with a new one derived from `passphrase` and a fresh salt
+    pub fn rekey(&mut self, passphrase: &str, params: KdfParams) -> Result<()> {
+        if !self.is_encrypted() {
+            return Err(anyhow::anyhow!(
+                "Memory store is not encrypted; use `sview memory lock` first"
+            ));
+        }
+        self.set_key(passphrase, params)
+    }
+
+    /// When the current key was derived, if the store is encrypted
+    pub fn key_created(&self) -> Option<DateTime<Local>> {
+        #[cfg(feature = "encryption")]
+        return self.key.as_ref().map(|k| k.created());
+        #[cfg(not(feature = "encryption"))]
+        return None;
+    }
+
+    /// Whether the key is older than `rotation_days`
+    pub fn rotation_due(&self, rotation_days: i64) -> bool {
+        #[cfg(feature = "encryption")]
+        return self
+            .key
+            .as_ref()
+            .is_some_and(|k| k.rotation_due(rotation_days));
+        #[cfg(not(feature = "encryption"))]
+        {
+            let _ = rotation_days;
+            false
+        }
+    }
+
+    #[cfg(feature = "encryption")]
+    fn set_key(&mut self, passphrase: &str, params: KdfParams) -> Result<()> {
+        if passphrase.is_empty() {
+            return Err(anyhow::anyhow!("Passphrase must not be empty"));
+        }
+        self.key = Some(super::crypto::StoreKey::derive(passphrase, params)?);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn set_key(&mut self, _passphrase: &str, _params: KdfParams) -> Result<()> {
+        Err(super::crypto::unavailable())
+    }
+
+    #[cfg(feature = "encryption")]
+    fn unseal<F>(&mut self, data: &[u8], passphrase: F) -> Result<Vec<u8>>
+    where
+        F: FnOnce() -> Result<String>,
+    {
+        let file: EncryptedFile = serde_json::from_slice(data)
+            .with_context(|| format!("Failed to parse encrypted store: {}", self.path.display()))?;
+        let (key, plaintext) = super::crypto::StoreKey::decrypt(&file, &passphrase()?)?;
+        self.key = Some(key);
+        Ok(plaintext)
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn unseal<F>(&mut self, _data: &[u8], _passphrase: F) -> Result<Vec<u8>>
+    where
+        F: FnOnce() -> Result<String>,
+    {
+        Err(super::crypto::unavailable()
+            .context(format!("Memory store {} is encrypted", self.path.display())))
+    }
+
+    /// Add an entry of the given type
+    pub fn add(&mut self, memory_type: MemoryType, key: &str, value: &str) {
+        self.restore(MemoryEntry {
+            key: key.to_string(),
+            value: value.to_string(),
+            memory_type,
+            timestamp: Local::now(),
+        });
+    }
+
+    /// Look up `key` in every memory type
+    pub fn get(&self, key: &str) -> Vec<MemoryEntry> {
+        let mut found = Vec::new();
+
+        if let Some(fact) = self.factual.retrieve(key) {
+            found.push(MemoryEntry {
+                key: key.to_string(),
+                value: fact.value.clone(),
+                memory_type: MemoryType::Factual,
+                timestamp: fact.stored_at,
+            });
+        }
+        found.extend(
+            self.episodic
+                .events_for(key)
+                .into_iter()
+                .map(Self::episode_entry),
+        );
+        if let Some(concept) = self.semantic.get_concept(key) {
+            found.push(Self::concept_entry(concept));
+        }
+        if let Some((_, item)) = self.working.items().into_iter().find(|(k, _)| k == key) {
+            found.push(MemoryEntry {
+                key: key.to_string(),
+                value: item.data,
+                memory_type: MemoryType::Working,
+                timestamp: item.last_accessed,
+            });
+        }
+
+        found
+    }
+
+    /// Remove `key` from every memory type, returning how many entries were dropped
+    pub fn remove(&mut self, key: &str) -> usize {
+        let mut removed = 0;
+        if self.factual.remove(key).is_some() {
+            removed += 1;
+        }
+        removed += self.episodic.forget(key);
+        if self.semantic.remove_concept(key).is_some() {
+            removed += 1;
+        }
+        if self.working.remove(key) {
+            removed += 1;
+        }
+        removed
+    }
+
+    /// All entries, ordered by timestamp
+    pub fn entries(&self) -> Vec<MemoryEntry> {
+        let mut entries: Vec<MemoryEntry> = self
+            .factual
+            .iter()
+            .map(|(key, fact)| MemoryEntry {
+                key: key.clone(),
+                value: fact.value.clone(),
+                memory_type: MemoryType::Factual,
+                timestamp: fact.stored_at,
+            })
+            .collect();
+        entries.extend(self.episodic.iter().cloned().map(Self::episode_entry));
+        entries.extend(
+            self.semantic
+                .concepts()
+                .into_iter()
+                .map(Self::concept_entry),
+        );
+        entries.extend(
+            self.working
+                .items()
+                .into_iter()
+                .map(|(key, item)| MemoryEntry {
+                    key,
+                    value: item.data,
+                    memory_type: MemoryType::Working,
+                    timestamp: item.last_accessed,
+                }),
+        );
+        entries.sort_by_key(|e| e.timestamp);
+        entries
+    }
+
+    /// Write the store back to disk
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+
+        let file = StoreFile {
+            version: STORE_VERSION,
+            entries: self.entries(),
+        };
+        let data = serde_json::to_vec_pretty(&file)?;
+        #[cfg(feature = "encryption")]
+        let data = match &self.key {
+            Some(key) => serde_json::to_vec_pretty(&key.encrypt(&data)?)?,
+            None => data,
+        };
+
+        // Write to a sibling file first so a crash never leaves a truncated store
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, data)
+            .with_context(|| format!("Failed to write memory store: {}", tmp.display()))?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Failed to replace memory store: {}", self.path.display()))?;
+        Ok(())
+    }
+
+    fn restore(&mut self, entry: MemoryEntry) {
+        match entry.memory_type {
+            MemoryType::Factual => self
+                .factual
+                .restore(entry.key, entry.value, entry.timestamp),
+            MemoryType::Episodic => self.episodic.restore(Episode {
+                timestamp: entry.timestamp,
+                key: entry.key,
+                event: entry.value,
+            }),
+            MemoryType::Semantic => {
+                let mut concept = self
+                    .semantic
+                    .get_concept(&entry.key)
+                    .unwrap_or_else(|| Concept::new(entry.key.clone()));
+                concept
+                    .attributes
+                    .insert(SEMANTIC_VALUE_ATTRIBUTE.to_string(), entry.value);
+                concept.updated_at = entry.timestamp;
+                self.semantic.add_concept(concept);
+            }
+            MemoryType::Working => self
+                .working
+                .restore(entry.key, entry.value, entry.timestamp),
+        }
+    }
+
+    fn episode_entry(episode: Episode) -> MemoryEntry {
+        MemoryEntry {
+            key: episode.key,
+            value: episode.event,
+            memory_type: MemoryType::Episodic,
+            timestamp: episode.timestamp,
+        }
+    }
+
+    fn concept_entry(concept: Concept) -> MemoryEntry {
+        MemoryEntry {
+            value: concept
+                .attributes
+                .get(SEMANTIC_VALUE_ATTRIBUTE)
+                .cloned()
+                .unwrap_or_default(),
+            key: concept.name,
+            memory_type: MemoryType::Semantic,
+            timestamp: concept.updated_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_entries_survive_reopen() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("memory.json");
+
+        let mut store = MemoryStore::open(&path)?;
+        store.add(MemoryType::Factual, "colour", "blue");
+        store.add(MemoryType::Episodic, "launch", "opened dashboard.svg");
+        store.add(MemoryType::Episodic, "launch", "opened pong-game.svg");
+        store.add(MemoryType::Semantic, "svg", "vector format");
+        store.add(MemoryType::Working, "task", "review icons");
+        store.save()?;
+
+        let store = MemoryStore::open(&path)?;
+        let entries = store.entries();
+        assert_eq!(entries.len(), 5);
+
+        let launches = store.get("launch");
+        assert_eq!(launches.len(), 2);
+        assert!(launches
+            .iter()
+            .all(|e| e.memory_type == MemoryType::Episodic));
+        assert_eq!(launches[1].value, "opened pong-game.svg");
+
+        assert_eq!(store.get("svg")[0].value, "vector format");
+        assert_eq!(store.get("task")[0].memory_type, MemoryType::Working);
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_drops_key_from_all_types() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("nested").join("memory.json");
+
+        let mut store = MemoryStore::open(&path)?;
+        store.add(MemoryType::Factual, "k", "fact");
+        store.add(MemoryType::Episodic, "k", "event");
+        store.add(MemoryType::Factual, "other", "kept");
+        assert_eq!(store.remove("k"), 2);
+        store.save()?;
+
+        let store = MemoryStore::open(&path)?;
+        assert!(store.get("k").is_empty());
+        assert_eq!(store.entries().len(), 1);
+        Ok(())
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_lock_unlock_and_rekey() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("memory.json");
+        let params = KdfParams {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        };
+
+        let mut store = MemoryStore::open(&path)?;
+        store.add(MemoryType::Factual, "token", "hunter2");
+        store.lock("first", params)?;
+        store.save()?;
+        assert!(!fs::read_to_string(&path)?.contains("hunter2"));
+        assert!(MemoryStore::open(&path).is_err());
+
+        let mut store = MemoryStore::open_with_passphrase(&path, || Ok("first".into()))?;
+        assert!(store.is_encrypted());
+        assert_eq!(store.get("token")[0].value, "hunter2");
+        store.rekey("second", params)?;
+        store.save()?;
+        assert!(MemoryStore::open_with_passphrase(&path, || Ok("first".into())).is_err());
+
+        let mut store = MemoryStore::open_with_passphrase(&path, || Ok("second".into()))?;
+        store.unlock()?;
+        store.save()?;
+        assert!(fs::read_to_string(&path)?.contains("hunter2"));
+        assert!(!MemoryStore::open(&path)?.is_encrypted());
+        Ok(())
+    }
+}
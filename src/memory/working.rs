@@ -1,7 +1,7 @@
+use chrono::prelude::*;
 use std::collections::HashMap;
-use std::time::Duration;
 use std::sync::{Arc, Mutex};
-use std::thread;
+use std::time::Duration;
 
 #[derive(Clone)]
 pub struct WorkingMemory {
@@ -11,9 +11,17 @@ pub struct WorkingMemory {
 }
 
 #[derive(Debug, Clone)]
-struct MemoryItem {
-    data: String,
-    last_accessed: std::time::Instant,
+pub struct MemoryItem {
+    pub data: String,
+    pub last_accessed: DateTime<Local>,
+}
+
+impl MemoryItem {
+    fn is_expired(&self, now: DateTime<Local>, timeout: Duration) -> bool {
+        (now - self.last_accessed)
+            .to_std()
+            .is_ok_and(|age| age >= timeout)
+    }
 }
 
 impl WorkingMemory {
@@ -25,72 +33,52 @@ impl WorkingMemory {
         }
     }
 
-    pub fn add(&self, key: String, data: String) {
+    /// Insert an item with a known access time (used when loading from disk)
+    pub fn restore(&self, key: String, data: String, last_accessed: DateTime<Local>) {
         let mut buffer = self.buffer.lock().unwrap();
-        
+
         // Remove oldest item if buffer is full
-        if buffer.len() >= self.max_items {
+        if buffer.len() >= self.max_items && !buffer.contains_key(&key) {
             let oldest_key = buffer
                 .iter()
                 .min_by_key(|&(_, item)| item.last_accessed)
                 .map(|(k, _)| k.clone());
-            
+
             if let Some(oldest_key) = oldest_key {
                 buffer.remove(&oldest_key);
             }
         }
 
-        buffer.insert(key, MemoryItem {
-            data,
-            last_accessed: std::time::Instant::now(),
-        });
+        buffer.insert(
+            key,
+            MemoryItem {
+                data,
+                last_accessed,
+            },
+        );
     }
 
-    pub fn get(&self, key: &str) -> Option<String> {
+    pub fn remove(&self, key: &str) -> bool {
         let mut buffer = self.buffer.lock().unwrap();
-        
-        if let Some(item) = buffer.get_mut(key) {
-            item.last_accessed = std::time::Instant::now();
-            Some(item.data.clone())
-        } else {
-            None
-        }
+        buffer.remove(key).is_some()
     }
 
-    pub fn remove(&self, key: &str) {
+    /// Drop every item that has not been accessed within the timeout
+    pub fn expire(&self) {
         let mut buffer = self.buffer.lock().unwrap();
-        buffer.remove(key);
-    }
-
-    pub fn clear(&self) {
-        let mut buffer = self.buffer.lock().unwrap();
-        buffer.clear();
-    }
-
-    pub fn start_cleanup_thread(&self) -> std::thread::JoinHandle<()> {
-        // Clone the Arc that contains the Mutex
-        let buffer = Arc::clone(&self.buffer);
+        let now = Local::now();
         let timeout = self.timeout;
+        buffer.retain(|_, item| !item.is_expired(now, timeout));
+    }
 
-        thread::spawn(move || {
-            loop {
-                thread::sleep(timeout);
-                
-                // Lock the mutex and clean up old entries
-                let mut buffer_guard = match buffer.lock() {
-                    Ok(guard) => guard,
-                    Err(poisoned) => {
-                        // Try to recover from a poisoned lock
-                        println!("Warning: Mutex was poisoned, attempting to recover...");
-                        poisoned.into_inner()
-                    }
-                };
-                
-                let now = std::time::Instant::now();
-                buffer_guard.retain(|_, item| {
-                    now.duration_since(item.last_accessed) < timeout
-                });
-            }
-        })
+    /// Snapshot of all items, sorted by key
+    pub fn items(&self) -> Vec<(String, MemoryItem)> {
+        let buffer = self.buffer.lock().unwrap();
+        let mut items: Vec<_> = buffer
+            .iter()
+            .map(|(k, item)| (k.clone(), item.clone()))
+            .collect();
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+        items
     }
 }
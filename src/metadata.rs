@@ -0,0 +1,336 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// The sView namespace; documents bind it, usually as `xmlns:sview`
+pub const SVIEW_NAMESPACE: &str = "http://sview.veridock.com/schema/v1";
+
+/// The Dublin Core elements namespace
+const DUBLIN_CORE: &str = "http://purl.org/dc/elements/1.1/";
+
+/// Parse SVG markup as XML
+///
+/// DTDs are allowed because editors such as Illustrator declare entities in
+/// an internal subset; roxmltree still guards against entity expansion attacks.
+pub fn parse_xml(text: &str) -> Result<roxmltree::Document<'_>, roxmltree::Error> {
+    roxmltree::Document::parse_with_options(
+        text,
+        roxmltree::ParsingOptions {
+            allow_dtd: true,
+            ..Default::default()
+        },
+    )
+}
+
+/// Facts about an SVG document worth keeping alongside its [`FileEntry`](crate::scanner::FileEntry)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SvgInfo {
+    /// Width in user units, from `width` or else the `viewBox`
+    pub width: Option<f64>,
+    /// Height in user units, from `height` or else the `viewBox`
+    pub height: Option<f64>,
+    /// What the document declares in the sView namespace, if it binds it
+    pub sview: Option<SviewMetadata>,
+    /// The root `<title>`
+    pub title: Option<String>,
+    /// The root `<desc>`
+    pub description: Option<String>,
+    /// Dublin Core fields (`dc:creator`, `dc:date`, ...) by element name, first one wins
+    pub dublin_core: BTreeMap<String, String>,
+}
+
+/// The `sview:*` attributes of an SVG's root element
+///
+/// ```xml
+/// <svg xmlns:sview="http://sview.veridock.com/schema/v1" sview:version="1.0" sview:memory="true" sview:pwa="true">
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SviewMetadata {
+    /// `sview:version`, as written
+    pub version: Option<String>,
+    /// Binding the namespace is enough unless `sview:enhanced="false"`
+    pub enhanced: bool,
+    /// `sview:memory`: the document uses the memory store
+    pub memory: bool,
+    /// `sview:pwa`: the document can be installed as a web app
+    pub pwa: bool,
+    /// `sview:interactive`: the document responds to input
+    pub interactive: bool,
+}
+
+impl SviewMetadata {
+    /// Read the sView attributes of `root`, under whatever prefix it binds
+    /// [`SVIEW_NAMESPACE`] to; `None` if it does not bind it
+    fn parse(root: roxmltree::Node) -> Option<Self> {
+        if !root.namespaces().any(|ns| ns.uri() == SVIEW_NAMESPACE) {
+            return None;
+        }
+        let attribute = |name: &str| root.attribute((SVIEW_NAMESPACE, name));
+        let flag = |name: &str| attribute(name).is_some_and(parse_bool);
+        Some(Self {
+            version: attribute("version").map(|v| v.trim().to_string()),
+            enhanced: attribute("enhanced").map_or(true, parse_bool),
+            memory: flag("memory"),
+            pwa: flag("pwa"),
+            interactive: flag("interactive"),
+        })
+    }
+
+    /// The declared capabilities, e.g. `["memory", "pwa"]`
+    pub fn capabilities(&self) -> Vec<&'static str> {
+        [
+            (self.memory, "memory"),
+            (self.pwa, "pwa"),
+            (self.interactive, "interactive"),
+        ]
+        .into_iter()
+        .filter_map(|(set, name)| set.then_some(name))
+        .collect()
+    }
+}
+
+/// An XML boolean, also accepting `yes` and `on`
+fn parse_bool(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "true" | "1" | "yes" | "on"
+    )
+}
+
+impl SvgInfo {
+    /// Read the root `<svg>` element of the file at `path`
+    ///
+    /// Returns `None` if the file cannot be read or is not an SVG document.
+    pub fn read(path: &Path) -> Option<Self> {
+        let text = fs::read_to_string(path).ok()?;
+        Self::parse(&text)
+    }
+
+    /// Parse the root `<svg>` element of `text`
+    pub fn parse(text: &str) -> Option<Self> {
+        let doc = parse_xml(text).ok()?;
+        let root = doc.root_element();
+        if root.tag_name().name() != "svg" {
+            return None;
+        }
+
+        let view_box: Vec<f64> = root
+            .attribute("viewBox")
+            .map(|v| {
+                v.split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|s| !s.is_empty())
+                    .filter_map(|s| s.parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let from_view_box = |i: usize| (view_box.len() == 4).then(|| view_box[i]);
+
+        Some(Self {
+            width: root
+                .attribute("width")
+                .and_then(parse_length)
+                .or_else(|| from_view_box(2)),
+            height: root
+                .attribute("height")
+                .and_then(parse_length)
+                .or_else(|| from_view_box(3)),
+            sview: SviewMetadata::parse(root),
+            title: child_text(root, "title"),
+            description: child_text(root, "desc"),
+            dublin_core: dublin_core(root),
+        })
+    }
+
+    /// `<title>`, or else `dc:title`
+    pub fn title(&self) -> Option<&str> {
+        self.title
+            .as_deref()
+            .or_else(|| self.dublin_core.get("title").map(String::as_str))
+    }
+
+    /// `<desc>`, or else `dc:description`
+    pub fn description(&self) -> Option<&str> {
+        self.description
+            .as_deref()
+            .or_else(|| self.dublin_core.get("description").map(String::as_str))
+    }
+
+    /// A metadata field by name: `title`, `description` (or `desc`) or any
+    /// Dublin Core element such as `creator` or `date`
+    pub fn field(&self, key: &str) -> Option<&str> {
+        match key.to_lowercase().as_str() {
+            "title" => self.title(),
+            "description" | "desc" => self.description(),
+            key => self.dublin_core.get(key).map(String::as_str),
+        }
+    }
+}
+
+/// The text of the first `name` child element of `node`, with whitespace collapsed
+fn child_text(node: roxmltree::Node, name: &str) -> Option<String> {
+    node.children()
+        .find(|child| child.tag_name().name() == name)
+        .and_then(|child| collapse(&text_content(child)))
+}
+
+/// Dublin Core elements anywhere in the document, plus RDF's attribute form
+/// (`<rdf:Description dc:creator="..."/>`)
+fn dublin_core(root: roxmltree::Node) -> BTreeMap<String, String> {
+    let mut fields = BTreeMap::new();
+    for node in root.descendants().filter(|n| n.is_element()) {
+        if node.tag_name().namespace() == Some(DUBLIN_CORE) {
+            if let Some(text) = collapse(&text_content(node)) {
+                fields
+                    .entry(node.tag_name().name().to_string())
+                    .or_insert(text);
+            }
+        }
+        for attribute in node.attributes() {
+            if attribute.namespace() == Some(DUBLIN_CORE) {
+                if let Some(text) = collapse(attribute.value()) {
+                    fields.entry(attribute.name().to_string()).or_insert(text);
+                }
+            }
+        }
+    }
+    fields
+}
+
+/// All text inside `node`, e.g. an `rdf:Bag` of creators
+fn text_content(node: roxmltree::Node) -> String {
+    node.descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `text` on one line with single spaces; `None` if it is blank
+fn collapse(text: &str) -> Option<String> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+/// Parse an absolute SVG length such as `600`, `600px` or `12.5pt` into user units
+///
+/// Percentages are relative to a viewport we do not know, so they yield `None`.
+fn parse_length(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+' || c == 'e'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let scale = match unit.trim() {
+        "" | "px" => 1.0,
+        "pt" => 4.0 / 3.0,
+        "pc" => 16.0,
+        "in" => 96.0,
+        "cm" => 96.0 / 2.54,
+        "mm" => 96.0 / 25.4,
+        _ => return None,
+    };
+    Some(number * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dimensions_and_sview_flag() {
+        let info = SvgInfo::parse(
+            r#"<svg width="600" height="10cm" viewBox="0 0 300 200" xmlns="http://www.w3.org/2000/svg"/>"#,
+        )
+        .unwrap();
+        assert_eq!(info.width, Some(600.0));
+        assert!((info.height.unwrap() - 377.95).abs() < 0.01);
+        assert_eq!(info.sview, None);
+
+        let info = SvgInfo::parse(
+            r#"<svg viewBox="0,0,48,32" xmlns="http://www.w3.org/2000/svg" xmlns:sview="http://sview.veridock.com/schema/v1"/>"#,
+        )
+        .unwrap();
+        assert_eq!((info.width, info.height), (Some(48.0), Some(32.0)));
+        assert_eq!(
+            info.sview,
+            Some(SviewMetadata {
+                enhanced: true,
+                ..Default::default()
+            })
+        );
+
+        assert!(SvgInfo::parse("<html/>").is_none());
+        assert!(SvgInfo::parse("not xml").is_none());
+    }
+
+    #[test]
+    fn test_parse_with_internal_dtd_subset() {
+        // As exported by Illustrator
+        let info = SvgInfo::parse(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+            <!DOCTYPE svg PUBLIC "-//W3C//DTD SVG 1.1//EN" "http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd" [
+              <!ENTITY ns_ai "http://ns.adobe.com/AdobeIllustrator/10.0/">
+              <!ENTITY name "Logo">
+            ]>
+            <svg xmlns="http://www.w3.org/2000/svg" xmlns:i="&ns_ai;"
+                 xmlns:sview="http://sview.veridock.com/schema/v1" width="40" height="30">
+              <title>&name;</title>
+            </svg>"#,
+        )
+        .unwrap();
+        assert_eq!((info.width, info.height), (Some(40.0), Some(30.0)));
+        assert!(info.sview.as_ref().is_some_and(|m| m.enhanced));
+        assert_eq!(info.title(), Some("Logo"));
+    }
+
+    #[test]
+    fn test_parse_title_and_dublin_core() {
+        let info = SvgInfo::parse(
+            r#"<svg xmlns="http://www.w3.org/2000/svg">
+              <title>  Sales
+                Q1 </title>
+              <metadata>
+                <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"
+                         xmlns:d="http://purl.org/dc/elements/1.1/">
+                  <rdf:Description d:date="2025-06-28">
+                    <d:title>Quarterly sales</d:title>
+                    <d:creator><rdf:Bag><rdf:li>Ann</rdf:li><rdf:li>Bob</rdf:li></rdf:Bag></d:creator>
+                    <d:description>Bars by region</d:description>
+                    <d:creator>Ignored</d:creator>
+                  </rdf:Description>
+                </rdf:RDF>
+              </metadata>
+              <g><title>Not the document title</title></g>
+            </svg>"#,
+        )
+        .unwrap();
+        assert_eq!(info.title(), Some("Sales Q1"));
+        assert_eq!(info.dublin_core["title"], "Quarterly sales");
+        assert_eq!(info.description(), Some("Bars by region"));
+        assert_eq!(info.field("Creator"), Some("Ann Bob"));
+        assert_eq!(info.field("date"), Some("2025-06-28"));
+        assert_eq!(info.field("subject"), None);
+    }
+
+    #[test]
+    fn test_parse_sview_metadata() {
+        let info = SvgInfo::parse(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:s="http://sview.veridock.com/schema/v1"
+                 xmlns:sview="urn:other" s:version=" 2.1 " s:memory="true" s:pwa="yes"
+                 interactive="true" sview:interactive="true" s:enhanced="false" onclick="go()">
+              <!-- manifest, memory:system, interactive= -->
+            </svg>"#,
+        )
+        .unwrap();
+        let sview = info.sview.unwrap();
+        assert_eq!(sview.version.as_deref(), Some("2.1"));
+        assert!(!sview.enhanced);
+        // Matched by namespace URI, whatever the prefix
+        assert!(sview.memory && sview.pwa);
+        // Attributes outside the namespace do not count, even with the `sview` prefix
+        assert!(!sview.interactive);
+        assert_eq!(sview.capabilities(), ["memory", "pwa"]);
+    }
+}
@@ -0,0 +1,379 @@
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::{self, Write};
+
+use crate::metadata::{SvgInfo, SviewMetadata};
+use crate::scanner::FileEntry;
+
+/// How commands print the files they find
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// A single JSON array
+    Json,
+    /// One JSON object per line
+    Ndjson,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+impl OutputFormat {
+    pub fn is_text(self) -> bool {
+        self == OutputFormat::Text
+    }
+}
+
+const CSV_HEADER: &str = "path,is_dir,size,modified,file_type,width,height,sview,title,description";
+
+/// A [`FileEntry`] as written by the machine-readable formats
+#[derive(Serialize)]
+struct Record<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    change: Option<&'a str>,
+    path: String,
+    is_dir: bool,
+    size: u64,
+    /// RFC 3339 modification time
+    modified: Option<String>,
+    file_type: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    svg: Option<SvgInfo>,
+}
+
+impl<'a> Record<'a> {
+    fn new(entry: &'a FileEntry, change: Option<&'a str>) -> Self {
+        Self {
+            change,
+            path: entry.path.display().to_string(),
+            is_dir: entry.is_dir,
+            size: entry.size,
+            modified: entry
+                .modified
+                .map(|t| DateTime::<Local>::from(t).to_rfc3339()),
+            file_type: entry.file_type.as_deref(),
+            svg: svg_info(entry),
+        }
+    }
+
+    fn csv_row(&self) -> String {
+        let svg = self.svg.as_ref();
+        let number = |n: Option<f64>| n.map(|n| n.to_string()).unwrap_or_default();
+        [
+            csv_field(&self.path),
+            self.is_dir.to_string(),
+            self.size.to_string(),
+            self.modified.clone().unwrap_or_default(),
+            csv_field(self.file_type.unwrap_or_default()),
+            number(svg.and_then(|s| s.width)),
+            number(svg.and_then(|s| s.height)),
+            svg.map(|s| s.sview.as_ref().is_some_and(|m| m.enhanced).to_string())
+                .unwrap_or_default(),
+            csv_field(svg.and_then(SvgInfo::title).unwrap_or_default()),
+            csv_field(svg.and_then(SvgInfo::description).unwrap_or_default()),
+        ]
+        .join(",")
+    }
+}
+
+/// SVG details of `entry`; walked entries carry none, so they are read from the file
+fn svg_info(entry: &FileEntry) -> Option<SvgInfo> {
+    entry.svg.clone().or_else(|| {
+        entry
+            .file_type
+            .as_deref()
+            .filter(|ext| ext.eq_ignore_ascii_case("svg"))
+            .and_then(|_| SvgInfo::read(&entry.path))
+    })
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Streams file entries to `out` in one of the [`OutputFormat`]s
+pub struct EntryWriter<W: Write> {
+    out: W,
+    format: OutputFormat,
+    /// Include size and modification time in text output
+    long: bool,
+    written: usize,
+}
+
+impl<W: Write> EntryWriter<W> {
+    pub fn new(out: W, format: OutputFormat, long: bool) -> Self {
+        Self {
+            out,
+            format,
+            long,
+            written: 0,
+        }
+    }
+
+    /// Write one entry
+    pub fn write(&mut self, entry: &FileEntry) -> io::Result<()> {
+        self.write_record(entry, None)
+    }
+
+    /// Write a change reported by `--watch`, tagged with `change` (added, removed, ...)
+    ///
+    /// Text output prefixes the entry with `marker`.
+    #[cfg(feature = "watch")]
+    pub fn write_change(
+        &mut self,
+        marker: char,
+        change: &str,
+        entry: &FileEntry,
+    ) -> io::Result<()> {
+        if self.format.is_text() {
+            write!(self.out, "{} ", marker)?;
+        }
+        self.write_record(entry, Some(change))?;
+        self.out.flush()
+    }
+
+    fn write_record(&mut self, entry: &FileEntry, change: Option<&str>) -> io::Result<()> {
+        match self.format {
+            OutputFormat::Text => write_text(&mut self.out, entry, self.long)?,
+            OutputFormat::Json => {
+                self.out.write_all(if self.written == 0 {
+                    b"[\n  "
+                } else {
+                    b",\n  "
+                })?;
+                serde_json::to_writer(&mut self.out, &Record::new(entry, change))?;
+            }
+            OutputFormat::Ndjson => {
+                serde_json::to_writer(&mut self.out, &Record::new(entry, change))?;
+                writeln!(self.out)?;
+            }
+            OutputFormat::Csv => {
+                if self.written == 0 {
+                    writeln!(self.out, "{}", CSV_HEADER)?;
+                }
+                writeln!(self.out, "{}", Record::new(entry, change).csv_row())?;
+            }
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    #[cfg(feature = "watch")]
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    /// Close the output; JSON and CSV are complete documents even when empty
+    pub fn finish(mut self) -> io::Result<()> {
+        match self.format {
+            OutputFormat::Json if self.written == 0 => writeln!(self.out, "[]")?,
+            OutputFormat::Json => writeln!(self.out, "\n]")?,
+            OutputFormat::Csv if self.written == 0 => writeln!(self.out, "{}", CSV_HEADER)?,
+            _ => {}
+        }
+        self.out.flush()
+    }
+}
+
+fn write_text<W: Write>(out: &mut W, entry: &FileEntry, long: bool) -> io::Result<()> {
+    if !long {
+        return writeln!(out, "{}", entry.path.display());
+    }
+
+    let modified = entry
+        .modified
+        .map(|t| {
+            DateTime::<Local>::from(t)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+    write!(
+        out,
+        "{:>10}  {}  {}",
+        humansize::format_size(entry.size, humansize::BINARY),
+        modified,
+        entry.path.display()
+    )?;
+    let svg = svg_info(entry);
+    match svg
+        .as_ref()
+        .and_then(|svg| svg.sview.as_ref())
+        .filter(|m| m.enhanced)
+    {
+        Some(sview) => writeln!(out, "  {}", sview_label(sview))?,
+        None => writeln!(out)?,
+    }
+    // Title, description and Dublin Core fields go on lines of their own,
+    // lined up under the date
+    for (key, value) in svg.as_ref().map(metadata_fields).unwrap_or_default() {
+        writeln!(out, "{:>10}  {}: {}", "", key, value)?;
+    }
+    Ok(())
+}
+
+/// The title, the description, then the other Dublin Core fields by name
+fn metadata_fields(svg: &SvgInfo) -> Vec<(&str, &str)> {
+    let mut fields: Vec<(&str, &str)> =
+        [("title", svg.title()), ("description", svg.description())]
+            .into_iter()
+            .filter_map(|(key, value)| Some((key, value?)))
+            .collect();
+    fields.extend(
+        svg.dublin_core
+            .iter()
+            .filter(|(key, _)| !matches!(key.as_str(), "title" | "description"))
+            .map(|(key, value)| (key.as_str(), value.as_str())),
+    );
+    fields
+}
+
+/// `[sview 1.0: memory, pwa]`, leaving out what the document does not declare
+fn sview_label(sview: &SviewMetadata) -> String {
+    let mut label = "[sview".to_string();
+    if let Some(version) = &sview.version {
+        label.push(' ');
+        label.push_str(version);
+    }
+    let capabilities = sview.capabilities();
+    if !capabilities.is_empty() {
+        label.push_str(": ");
+        label.push_str(&capabilities.join(", "));
+    }
+    label.push(']');
+    label
+}
+
+/// Treat a closed pipe (`sview list | head`) as a normal end of output
+pub fn ignore_broken_pipe<E: Into<anyhow::Error>>(result: Result<(), E>) -> anyhow::Result<()> {
+    let error = match result {
+        Ok(()) => return Ok(()),
+        Err(e) => e.into(),
+    };
+    match error.downcast_ref::<io::Error>() {
+        Some(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        _ => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
+
+    fn entry(path: &str) -> FileEntry {
+        FileEntry {
+            path: PathBuf::from(path),
+            is_dir: false,
+            size: 42,
+            modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            file_type: Some("svg".to_string()),
+            svg: Some(SvgInfo {
+                width: Some(16.0),
+                height: Some(8.5),
+                sview: Some(SviewMetadata {
+                    version: Some("1.0".to_string()),
+                    enhanced: true,
+                    pwa: true,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn render(format: OutputFormat, entries: &[FileEntry]) -> String {
+        let mut out = Vec::new();
+        let mut writer = EntryWriter::new(&mut out, format, false);
+        for e in entries {
+            writer.write(e).unwrap();
+        }
+        writer.finish().unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_json_and_ndjson() {
+        let entries = [entry("a.svg"), entry("b.svg")];
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render(OutputFormat::Json, &entries)).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 2);
+        assert_eq!(json[1]["path"], "b.svg");
+        assert_eq!(json[0]["size"], 42);
+        assert_eq!(json[0]["svg"]["height"], 8.5);
+        assert_eq!(json[0]["svg"]["sview"]["version"], "1.0");
+        assert_eq!(json[0]["svg"]["sview"]["pwa"], true);
+        let modified = json[0]["modified"].as_str().unwrap();
+        assert!(DateTime::parse_from_rfc3339(modified).is_ok());
+
+        assert_eq!(render(OutputFormat::Json, &[]).trim(), "[]");
+
+        let ndjson = render(OutputFormat::Ndjson, &entries);
+        let lines: Vec<serde_json::Value> = ndjson
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["file_type"], "svg");
+        assert!(lines[0].get("change").is_none());
+    }
+
+    #[test]
+    fn test_long_text_shows_svg_metadata() {
+        let mut out = Vec::new();
+        let mut writer = EntryWriter::new(&mut out, OutputFormat::Text, true);
+        writer.write(&entry("a.svg")).unwrap();
+        let mut plain = entry("b.svg");
+        let svg = plain.svg.as_mut().unwrap();
+        svg.sview = None;
+        svg.title = Some("Sales".to_string());
+        svg.dublin_core = [("creator", "Demo"), ("title", "Sales Q1")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        writer.write(&plain).unwrap();
+        writer.finish().unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0].ends_with("  a.svg  [sview 1.0: pwa]"));
+        assert!(lines[1].ends_with("  b.svg"));
+        assert_eq!(
+            lines[2..],
+            ["            title: Sales", "            creator: Demo"]
+        );
+    }
+
+    #[test]
+    fn test_csv_quotes_fields() {
+        let csv = render(OutputFormat::Csv, &[entry("dir, with \"quotes\"/a.svg")]);
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some(CSV_HEADER));
+        let row = lines.next().unwrap();
+        assert!(row.starts_with("\"dir, with \"\"quotes\"\"/a.svg\",false,42,"));
+        assert!(row.ends_with(",svg,16,8.5,true,,"));
+
+        assert_eq!(render(OutputFormat::Csv, &[]).trim(), CSV_HEADER);
+    }
+
+    #[test]
+    fn test_csv_svg_metadata() {
+        let mut titled = entry("a.svg");
+        let svg = titled.svg.as_mut().unwrap();
+        svg.title = Some("Sales, \"Q1\"".to_string());
+        svg.dublin_core = [("description".to_string(), "Revenue".to_string())].into();
+
+        let csv = render(OutputFormat::Csv, &[titled]);
+        let row = csv.lines().nth(1).unwrap();
+        assert!(row.ends_with(",true,\"Sales, \"\"Q1\"\"\",Revenue"));
+    }
+}
@@ -0,0 +1,229 @@
+//! Interpreters and compilers that `exec` can run code with
+//!
+//! Each [`Runtime`] says which programs provide it, what file the snippet is
+//! written to and how to build and run that file. Adding a language means
+//! adding an entry to [`RUNTIMES`].
+
+use serde::Serialize;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::system::find_executable;
+
+/// How one language is run
+#[derive(Debug)]
+pub struct Runtime {
+    /// Canonical name, as used in `[languages] supported` and `lang="..."`
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    /// Extension of the source file the snippet is saved as
+    pub extension: &'static str,
+    /// Executables providing the runtime, most preferred first
+    pub programs: &'static [&'static str],
+    /// Arguments that compile `{source}` into `{binary}`; `None` for interpreters
+    pub build: Option<&'static [&'static str]>,
+    /// Arguments that run `{source}` with the program, or for compiled
+    /// languages the arguments passed to the binary
+    pub run: &'static [&'static str],
+    /// A first line the source needs, added when the snippet lacks it
+    pub header: Option<&'static str>,
+    /// Finds the real program behind a wrapper that needs the environment
+    /// the sandbox clears
+    pub resolve: Option<fn(&Path) -> Option<PathBuf>>,
+}
+
+/// Every language `exec` knows, in the order `exec --list` shows them
+pub const RUNTIMES: [Runtime; 8] = [
+    Runtime {
+        name: "javascript",
+        aliases: &["js", "node"],
+        extension: "js",
+        programs: &["node"],
+        build: None,
+        run: &["{source}"],
+        header: None,
+        resolve: None,
+    },
+    Runtime {
+        name: "python",
+        aliases: &["py", "python3"],
+        extension: "py",
+        programs: &["python3", "python"],
+        build: None,
+        run: &["{source}"],
+        header: None,
+        resolve: None,
+    },
+    Runtime {
+        name: "rust",
+        aliases: &["rs"],
+        extension: "rs",
+        programs: &["rustc"],
+        build: Some(&["{source}", "-o", "{binary}"]),
+        run: &[],
+        header: None,
+        resolve: Some(rustup_toolchain),
+    },
+    Runtime {
+        name: "go",
+        aliases: &["golang"],
+        extension: "go",
+        programs: &["go"],
+        build: Some(&["build", "-o", "{binary}", "{source}"]),
+        run: &[],
+        header: None,
+        resolve: None,
+    },
+    Runtime {
+        name: "ruby",
+        aliases: &["rb"],
+        extension: "rb",
+        programs: &["ruby"],
+        build: None,
+        run: &["{source}"],
+        header: None,
+        resolve: None,
+    },
+    Runtime {
+        name: "php",
+        aliases: &[],
+        extension: "php",
+        programs: &["php"],
+        build: None,
+        run: &["{source}"],
+        header: Some("<?php"),
+        resolve: None,
+    },
+    Runtime {
+        name: "bash",
+        aliases: &["sh", "shell"],
+        extension: "sh",
+        programs: &["bash"],
+        build: None,
+        run: &["{source}"],
+        header: None,
+        resolve: None,
+    },
+    Runtime {
+        name: "powershell",
+        aliases: &["pwsh", "ps1"],
+        extension: "ps1",
+        programs: &["pwsh", "powershell"],
+        build: None,
+        run: &["-NoProfile", "-NonInteractive", "-File", "{source}"],
+        header: None,
+        resolve: None,
+    },
+];
+
+/// Whether a runtime is enabled and installed, for `exec --list`
+#[derive(Debug, Serialize)]
+pub struct RuntimeStatus {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    /// Listed in `[languages] supported`
+    pub enabled: bool,
+    /// The program that would run it, if one is installed
+    pub program: Option<PathBuf>,
+}
+
+impl Runtime {
+    /// Look a runtime up by name or alias, ignoring case
+    pub fn find(language: &str) -> Option<&'static Runtime> {
+        let wanted = language.to_lowercase();
+        RUNTIMES
+            .iter()
+            .find(|runtime| runtime.name == wanted || runtime.aliases.contains(&wanted.as_str()))
+    }
+
+    /// The installed program for this runtime, if any
+    pub fn detect(&self) -> Option<PathBuf> {
+        let program = self
+            .programs
+            .iter()
+            .find_map(|name| find_executable(name))?;
+        match self.resolve {
+            Some(resolve) => Some(resolve(&program).unwrap_or(program)),
+            None => Some(program),
+        }
+    }
+
+    pub fn status(&self, supported: &[String]) -> RuntimeStatus {
+        RuntimeStatus {
+            name: self.name,
+            aliases: self.aliases,
+            enabled: supported.iter().any(|l| l == self.name),
+            program: self.detect(),
+        }
+    }
+
+    /// The source file contents for `code`
+    pub fn source(&self, code: &str) -> String {
+        match self.header {
+            Some(header) if !code.trim_start().starts_with(header) => {
+                format!("{}\n{}", header, code)
+            }
+            _ => code.to_string(),
+        }
+    }
+
+    /// `args` with `{source}` and `{binary}` filled in
+    pub fn expand(args: &[&str], source: &Path, binary: &Path) -> Vec<OsString> {
+        args.iter()
+            .map(|arg| match *arg {
+                "{source}" => source.as_os_str().to_owned(),
+                "{binary}" => binary.as_os_str().to_owned(),
+                arg => OsString::from(arg),
+            })
+            .collect()
+    }
+}
+
+/// The real `rustc` of the active toolchain
+///
+/// rustup's proxy needs the `HOME` the sandbox replaces, so ask it for the
+/// toolchain's sysroot while we still can.
+fn rustup_toolchain(proxy: &Path) -> Option<PathBuf> {
+    let output = Command::new(proxy)
+        .args(["--print", "sysroot"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let sysroot = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let rustc = Path::new(&sysroot)
+        .join("bin")
+        .join("rustc")
+        .with_extension(std::env::consts::EXE_EXTENSION);
+    rustc.is_file().then_some(rustc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        assert_eq!(Runtime::find("JS").unwrap().name, "javascript");
+        assert_eq!(Runtime::find("golang").unwrap().name, "go");
+        assert!(Runtime::find("cobol").is_none());
+        for runtime in &RUNTIMES {
+            assert_eq!(Runtime::find(runtime.name).unwrap().name, runtime.name);
+        }
+    }
+
+    #[test]
+    fn test_source_and_args() {
+        let php = Runtime::find("php").unwrap();
+        assert_eq!(php.source("echo 1;"), "<?php\necho 1;");
+        assert_eq!(php.source("<?php echo 1;"), "<?php echo 1;");
+
+        let go = Runtime::find("go").unwrap();
+        let args = Runtime::expand(
+            go.build.unwrap(),
+            Path::new("/w/main.go"),
+            Path::new("/w/main"),
+        );
+        assert_eq!(args, ["build", "-o", "/w/main", "/w/main.go"]);
+    }
+}
@@ -0,0 +1,284 @@
+//! Running untrusted code with limits
+//!
+//! With `[languages] enable_sandbox` a program runs in its own process group
+//! with a cleared environment, a private working directory as `HOME` and
+//! `TMPDIR`, resource limits (`execution_memory_limit` caps its data segment)
+//! and, if `sandbox_isolate_network` is set, in a new network namespace with
+//! no interfaces. The whole group is killed after `execution_timeout` seconds
+//! either way. Resource limits and namespaces need Unix and Linux respectively.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::settings::LanguageSettings;
+
+/// Output kept per stream; the rest is read and dropped so the program never blocks
+const MAX_OUTPUT: usize = 1024 * 1024;
+
+/// Largest file a sandboxed program may write
+const MAX_FILE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Open files a sandboxed program may hold
+const MAX_OPEN_FILES: u64 = 256;
+
+/// How often a running program is checked for exit or timeout
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Search path inside the sandbox when the caller has none
+const DEFAULT_PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+
+/// What a finished program printed and how it ended
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// `None` if the program was killed by a signal, including on timeout
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    /// Whether stdout or stderr went over the capture limit
+    pub truncated: bool,
+    pub duration_ms: u64,
+}
+
+impl ExecOutput {
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+/// Limits and isolation applied to every program it runs
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    enabled: bool,
+    timeout: Duration,
+    /// In bytes; `None` for no limit
+    memory_limit: Option<u64>,
+    isolate_network: bool,
+}
+
+impl Sandbox {
+    pub fn from_settings(settings: &LanguageSettings) -> Self {
+        Self {
+            enabled: settings.enable_sandbox,
+            timeout: Duration::from_secs(settings.execution_timeout),
+            memory_limit: (settings.execution_memory_limit > 0)
+                .then(|| settings.execution_memory_limit * 1024 * 1024),
+            isolate_network: settings.sandbox_isolate_network,
+        }
+    }
+
+    /// The same sandbox without the memory limit, for compilers whose own
+    /// footprint has nothing to do with the snippet being built
+    pub fn without_memory_limit(&self) -> Self {
+        Self {
+            memory_limit: None,
+            ..self.clone()
+        }
+    }
+
+    /// Run `command` in `work_dir` with `input` on stdin and collect its output
+    ///
+    /// Without the sandbox the command keeps the caller's environment and only
+    /// the timeout applies.
+    pub fn run(&self, mut command: Command, work_dir: &Path, input: &[u8]) -> Result<ExecOutput> {
+        command
+            .current_dir(work_dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if self.enabled {
+            self.confine(&mut command, work_dir)?;
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            // A group of its own, so a timeout also kills whatever it started
+            command.process_group(0);
+        }
+
+        let program = command.get_program().to_string_lossy().into_owned();
+        let started = Instant::now();
+        let mut child = command
+            .spawn()
+            .with_context(|| format!("Failed to run {}", program))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            let input = input.to_vec();
+            // A program that never reads its input must not stall us
+            thread::spawn(move || stdin.write_all(&input));
+        }
+        let stdout = capture(child.stdout.take());
+        let stderr = capture(child.stderr.take());
+
+        let mut timed_out = false;
+        let status = loop {
+            if let Some(status) = child
+                .try_wait()
+                .with_context(|| format!("Failed to wait for {}", program))?
+            {
+                break status;
+            }
+            if started.elapsed() >= self.timeout {
+                timed_out = true;
+                kill(&mut child);
+                break child
+                    .wait()
+                    .with_context(|| format!("Failed to wait for {}", program))?;
+            }
+            thread::sleep(POLL_INTERVAL);
+        };
+        // Background processes it left behind would keep the pipes open
+        kill(&mut child);
+
+        let (stdout, stdout_truncated) = stdout.join().unwrap_or_default();
+        let (stderr, stderr_truncated) = stderr.join().unwrap_or_default();
+        Ok(ExecOutput {
+            stdout,
+            stderr,
+            exit_code: status.code(),
+            timed_out,
+            truncated: stdout_truncated || stderr_truncated,
+            duration_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+
+    #[cfg(unix)]
+    fn confine(&self, command: &mut Command, work_dir: &Path) -> Result<()> {
+        use std::os::unix::process::CommandExt;
+
+        let path = std::env::var("PATH").unwrap_or_else(|_| DEFAULT_PATH.to_string());
+        command
+            .env_clear()
+            .env("PATH", path)
+            .env("HOME", work_dir)
+            .env("TMPDIR", work_dir)
+            .env("LANG", "C.UTF-8");
+
+        let memory_limit = self.memory_limit;
+        let isolate_network = self.isolate_network;
+        if isolate_network && !cfg!(target_os = "linux") {
+            anyhow::bail!(
+                "Network isolation needs Linux; set [languages] sandbox_isolate_network = false"
+            );
+        }
+        // SAFETY: the closure runs between fork and exec and only makes
+        // async-signal-safe system calls
+        unsafe {
+            command.pre_exec(move || {
+                set_limit(libc::RLIMIT_CORE, 0)?;
+                set_limit(libc::RLIMIT_FSIZE, MAX_FILE_SIZE)?;
+                set_limit(libc::RLIMIT_NOFILE, MAX_OPEN_FILES)?;
+                if let Some(bytes) = memory_limit {
+                    set_limit(libc::RLIMIT_DATA, bytes)?;
+                }
+                #[cfg(target_os = "linux")]
+                if isolate_network && libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn confine(&self, _command: &mut Command, _work_dir: &Path) -> Result<()> {
+        anyhow::bail!("Sandboxing needs Unix; set [languages] enable_sandbox = false")
+    }
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(all(unix, not(all(target_os = "linux", target_env = "gnu"))))]
+type Resource = libc::c_int;
+
+#[cfg(unix)]
+fn set_limit(resource: Resource, value: u64) -> std::io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: value as libc::rlim_t,
+        rlim_max: value as libc::rlim_t,
+    };
+    // SAFETY: setrlimit only reads the struct we pass
+    if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Kill the program and everything in its process group
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    // SAFETY: signalling a process group we created; a missing group is harmless
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    #[cfg(not(unix))]
+    let _ = child.kill();
+}
+
+/// Read a stream to the end on its own thread, keeping up to [`MAX_OUTPUT`] bytes
+fn capture<R: Read + Send + 'static>(stream: Option<R>) -> thread::JoinHandle<(String, bool)> {
+    thread::spawn(move || {
+        let mut kept = Vec::new();
+        let mut truncated = false;
+        if let Some(mut stream) = stream {
+            let mut buf = [0u8; 8192];
+            while let Ok(n) = stream.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+                let room = MAX_OUTPUT - kept.len();
+                kept.extend_from_slice(&buf[..n.min(room)]);
+                truncated |= n > room;
+            }
+        }
+        (String::from_utf8_lossy(&kept).into_owned(), truncated)
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn sandbox(timeout: u64) -> Sandbox {
+        Sandbox {
+            enabled: true,
+            timeout: Duration::from_secs(timeout),
+            memory_limit: Some(64 * 1024 * 1024),
+            isolate_network: false,
+        }
+    }
+
+    fn sh(script: &str) -> Command {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script);
+        command
+    }
+
+    #[test]
+    fn test_sandbox_run() -> Result<()> {
+        std::env::set_var("SVIEW_SANDBOX_SECRET", "leaked");
+        let dir = tempfile::tempdir()?;
+        let output = sandbox(10).run(
+            sh("pwd; echo \"[$SVIEW_SANDBOX_SECRET]\"; cat >&2; exit 3"),
+            dir.path(),
+            b"oops\n",
+        )?;
+        assert_eq!(output.exit_code, Some(3));
+        assert!(!output.success() && !output.timed_out);
+        let pwd = dir.path().canonicalize()?;
+        assert_eq!(output.stdout, format!("{}\n[]\n", pwd.display()));
+        assert_eq!(output.stderr, "oops\n");
+
+        let started = Instant::now();
+        let output = sandbox(1).run(sh("sleep 30 & wait"), dir.path(), b"")?;
+        assert!(output.timed_out);
+        assert_eq!(output.exit_code, None);
+        assert!(started.elapsed() < Duration::from_secs(10));
+        Ok(())
+    }
+}
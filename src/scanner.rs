@@ -1,11 +1,20 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex};
 use std::time::SystemTime;
 use walkdir::WalkDir;
 
+use crate::index::IndexedRoot;
+use crate::metadata::SvgInfo;
+use crate::settings::Settings;
+
 /// Represents a file system entry (file or directory)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileEntry {
@@ -14,6 +23,28 @@ pub struct FileEntry {
     pub size: u64,
     pub modified: Option<SystemTime>,
     pub file_type: Option<String>,
+    /// Dimensions and sView flags, for SVGs read through the scan index
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub svg: Option<SvgInfo>,
+}
+
+/// Sort criteria
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortBy {
+    #[default]
+    Name,
+    Size,
+    Modified,
+}
+
+impl std::fmt::Display for SortBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SortBy::Name => write!(f, "name"),
+            SortBy::Size => write!(f, "size"),
+            SortBy::Modified => write!(f, "modified"),
+        }
+    }
 }
 
 /// Configuration for the file scanner
@@ -25,6 +56,20 @@ pub struct ScannerConfig {
     pub min_size: Option<u64>,
     pub max_size: Option<u64>,
     pub exclude_dirs: Vec<String>,
+    /// File names matching any of these are skipped (`[paths] exclude_patterns`)
+    pub exclude_patterns: Vec<Regex>,
+    /// Include files and directories whose name starts with a dot
+    pub include_hidden: bool,
+    /// Files larger than this are not read when searching contents
+    pub content_size_limit: u64,
+    /// Walk directories and match contents on a thread pool
+    pub parallel: bool,
+    /// Size of the thread pool, 0 lets rayon pick one thread per core
+    pub threads: usize,
+    /// Collect all matches and report them in this order instead of as found
+    pub sort: Option<SortBy>,
+    /// Reverse the `sort` order
+    pub reverse: bool,
 }
 
 impl Default for ScannerConfig {
@@ -36,13 +81,229 @@ impl Default for ScannerConfig {
             min_size: None,
             max_size: None,
             exclude_dirs: vec![".git".to_string(), "node_modules".to_string()],
+            exclude_patterns: Vec::new(),
+            include_hidden: true,
+            content_size_limit: 10_000_000,
+            parallel: false,
+            threads: 0,
+            sort: None,
+            reverse: false,
         }
     }
 }
 
+impl ScannerConfig {
+    /// Build a scanner configuration from the `[general]`, `[performance]`, `[ui]` and `[paths]` settings
+    pub fn from_settings(settings: &Settings) -> Result<Self> {
+        let exclude_patterns = settings
+            .paths
+            .exclude_patterns
+            .iter()
+            .map(|p| {
+                Regex::new(p)
+                    .with_context(|| format!("Invalid [paths] exclude_patterns entry: {}", p))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            max_depth: match settings.general.scan_depth {
+                0 => None,
+                depth => Some(depth),
+            },
+            extensions: if settings.general.default_format.is_empty() {
+                None
+            } else {
+                Some(vec![settings.general.default_format.clone()])
+            },
+            exclude_dirs: settings.paths.exclude_paths.clone(),
+            exclude_patterns,
+            include_hidden: settings.ui.show_hidden_files,
+            content_size_limit: settings.paths.max_file_size.saturating_mul(1024 * 1024),
+            parallel: settings.performance.parallel_scan,
+            threads: settings.performance.max_threads,
+            ..Default::default()
+        })
+    }
+
+    /// Override the configured thread count; any count other than 1 scans in parallel
+    pub fn with_threads(mut self, threads: Option<usize>) -> Self {
+        if let Some(threads) = threads {
+            self.parallel = threads != 1;
+            self.threads = threads;
+        }
+        self
+    }
+}
+
+/// Whether an entry named `name` is pruned from the walk under `config`
+///
+/// Pruning here keeps excluded directories from being walked at all.
+pub(crate) fn is_excluded(name: &str, is_dir: bool, config: &ScannerConfig) -> bool {
+    if !config.include_hidden && name.starts_with('.') {
+        return true;
+    }
+    if is_dir {
+        return config
+            .exclude_dirs
+            .iter()
+            .any(|dir| name.eq_ignore_ascii_case(dir));
+    }
+    config.exclude_patterns.iter().any(|re| re.is_match(name))
+}
+
+/// Order `entries` by `sort`, ties broken by path so the result is deterministic
+pub fn sort_entries(entries: &mut [FileEntry], sort: SortBy, reverse: bool) {
+    match sort {
+        SortBy::Name => entries.sort_by(|a, b| {
+            a.path
+                .file_name()
+                .cmp(&b.path.file_name())
+                .then_with(|| a.path.cmp(&b.path))
+        }),
+        SortBy::Size => {
+            entries.sort_by(|a, b| a.size.cmp(&b.size).then_with(|| a.path.cmp(&b.path)))
+        }
+        SortBy::Modified => entries.sort_by(|a, b| {
+            a.modified
+                .unwrap_or(SystemTime::UNIX_EPOCH)
+                .cmp(&b.modified.unwrap_or(SystemTime::UNIX_EPOCH))
+                .then_with(|| a.path.cmp(&b.path))
+        }),
+    }
+
+    if reverse {
+        entries.reverse();
+    }
+}
+
+/// What [`FileScanner::search`] looks for
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    /// Matched against file names (and contents when `search_content` is set);
+    /// `None` matches every file
+    pattern: Option<Regex>,
+    /// Shell-style glob matched against the path relative to the search root
+    glob: Option<GlobMatcher>,
+    search_content: bool,
+    /// Metadata fields (see [`SvgInfo::field`]) and what they must contain
+    fields: Vec<(String, Regex)>,
+}
+
+impl SearchQuery {
+    /// Match every file
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Match file names against a regular expression
+    pub fn regex(pattern: &str, ignore_case: bool) -> Result<Self> {
+        // `.*` is the documented "everything" pattern; skip the regex entirely
+        if pattern.is_empty() || pattern == ".*" {
+            return Ok(Self::all());
+        }
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .with_context(|| format!("Invalid regular expression: {}", pattern))?;
+        Ok(Self {
+            pattern: Some(regex),
+            ..Self::default()
+        })
+    }
+
+    /// Match file names containing `text`
+    pub fn literal(text: &str, ignore_case: bool) -> Self {
+        let regex = RegexBuilder::new(&regex::escape(text))
+            .case_insensitive(ignore_case)
+            .build()
+            .expect("escaped pattern is a valid regex");
+        Self {
+            pattern: Some(regex),
+            ..Self::default()
+        }
+    }
+
+    /// Additionally require the path relative to the search root to match `glob`
+    pub fn with_glob(mut self, glob: &str, ignore_case: bool) -> Result<Self> {
+        let matcher = GlobBuilder::new(glob)
+            .literal_separator(true)
+            .case_insensitive(ignore_case)
+            .build()
+            .with_context(|| format!("Invalid glob pattern: {}", glob))?
+            .compile_matcher();
+        self.glob = Some(matcher);
+        Ok(self)
+    }
+
+    /// Also match files whose contents match the pattern
+    pub fn with_content(mut self, search_content: bool) -> Self {
+        self.search_content = search_content;
+        self
+    }
+
+    /// Additionally require the SVG's title to contain `text`
+    pub fn with_title(self, text: &str, ignore_case: bool) -> Self {
+        self.with_field("title", text, ignore_case)
+    }
+
+    /// Additionally require a metadata field to contain a value, given as
+    /// `key=value`, e.g. `creator=Demo`
+    pub fn with_meta(self, filter: &str, ignore_case: bool) -> Result<Self> {
+        match filter.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => {
+                Ok(self.with_field(key.trim(), value, ignore_case))
+            }
+            _ => Err(anyhow::anyhow!(
+                "Invalid metadata filter: {} (expected KEY=VALUE, e.g. creator=Demo)",
+                filter
+            )),
+        }
+    }
+
+    fn with_field(mut self, key: &str, text: &str, ignore_case: bool) -> Self {
+        let regex = RegexBuilder::new(&regex::escape(text))
+            .case_insensitive(ignore_case)
+            .build()
+            .expect("escaped pattern is a valid regex");
+        self.fields.push((key.to_lowercase(), regex));
+        self
+    }
+
+    /// Whether matching needs the SVG details of each file
+    fn needs_svg_info(&self) -> bool {
+        !self.fields.is_empty()
+    }
+
+    fn matches_fields(&self, svg: Option<&SvgInfo>) -> bool {
+        self.fields.iter().all(|(key, regex)| {
+            svg.and_then(|svg| svg.field(key))
+                .is_some_and(|value| regex.is_match(value))
+        })
+    }
+
+    fn matches_glob(&self, root: &Path, path: &Path) -> bool {
+        match &self.glob {
+            Some(glob) => glob.is_match(path.strip_prefix(root).unwrap_or(path)),
+            None => true,
+        }
+    }
+
+    fn matches_name(&self, name: &str) -> bool {
+        self.pattern.as_ref().map_or(true, |re| re.is_match(name))
+    }
+
+    fn matches_content(&self, contents: &str) -> bool {
+        self.pattern
+            .as_ref()
+            .is_some_and(|re| re.is_match(contents))
+    }
+}
+
 /// Main scanner implementation
 pub struct FileScanner {
     config: ScannerConfig,
+    /// Answer searches from this index instead of walking the filesystem
+    index: Option<IndexedRoot>,
 }
 
 impl FileScanner {
@@ -50,82 +311,72 @@ impl FileScanner {
     pub fn new() -> Self {
         Self {
             config: ScannerConfig::default(),
+            index: None,
         }
     }
 
     /// Scan a directory and return all files matching the configuration
     pub fn scan<P: AsRef<Path>>(&self, path: P) -> Result<Vec<FileEntry>> {
         let path = path.as_ref();
-        let mut entries = Vec::new();
-
-        let walker = WalkDir::new(path).max_depth(if self.config.recursive {
-            self.config.max_depth.unwrap_or(usize::MAX)
-        } else {
-            1
-        });
-
-        for entry in walker.into_iter().filter_map(Result::ok) {
-            // Skip directories in exclude list
-            if entry.file_type().is_dir() {
-                if self.config.exclude_dirs.iter().any(|dir| {
-                    entry.path().file_name().map_or(false, |name| {
-                        name.to_string_lossy().eq_ignore_ascii_case(dir)
-                    })
-                }) {
-                    continue;
-                }
-            }
+        let entries = Mutex::new(Vec::new());
+        let stop = AtomicBool::new(false);
 
-            if let Ok(metadata) = entry.metadata() {
-                let file_type = if entry.file_type().is_dir() {
-                    Some("directory".to_string())
-                } else if let Some(ext) = entry.path().extension() {
-                    Some(ext.to_string_lossy().to_string())
-                } else {
-                    None
-                };
+        self.walk(path, &stop, &|entry_path: &Path, is_dir: bool| {
+            let metadata = match fs::metadata(entry_path) {
+                Ok(m) => m,
+                Err(_) => return,
+            };
+            let file_type = if is_dir {
+                Some("directory".to_string())
+            } else {
+                entry_path
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().to_string())
+            };
 
-                let entry = FileEntry {
-                    path: entry.path().to_path_buf(),
-                    is_dir: entry.file_type().is_dir(),
-                    size: metadata.len(),
-                    modified: metadata.modified().ok(),
-                    file_type,
-                };
+            let entry = FileEntry {
+                path: entry_path.to_path_buf(),
+                is_dir,
+                size: metadata.len(),
+                modified: metadata.modified().ok(),
+                file_type,
+                svg: None,
+            };
 
-                // Check size filters
-                let size_ok = self.config.min_size.map_or(true, |min| entry.size >= min)
-                    && self.config.max_size.map_or(true, |max| entry.size <= max);
+            // Check size filters
+            let size_ok = self.config.min_size.map_or(true, |min| entry.size >= min)
+                && self.config.max_size.map_or(true, |max| entry.size <= max);
 
-                // Check extension filter
-                let ext_ok = if let Some(exts) = &self.config.extensions {
-                    entry
-                        .path
-                        .extension()
-                        .and_then(|e| e.to_str())
-                        .map_or(false, |e| exts.iter().any(|x| x.eq_ignore_ascii_case(e)))
-                } else {
-                    true
-                };
+            // Check extension filter
+            let ext_ok = if let Some(exts) = &self.config.extensions {
+                entry
+                    .path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| exts.iter().any(|x| x.eq_ignore_ascii_case(e)))
+            } else {
+                true
+            };
 
-                if size_ok && ext_ok {
-                    entries.push(entry);
-                }
+            if size_ok && ext_ok {
+                entries.lock().unwrap().push(entry);
             }
-        }
+        })?;
 
+        let mut entries = entries.into_inner().unwrap();
+        if let Some(sort) = self.config.sort {
+            sort_entries(&mut entries, sort, self.config.reverse);
+        }
         Ok(entries)
     }
 
     /// Search for files matching a query with incremental results
-    pub fn search<P, F>(
-        &self,
-        path: P,
-        query: &str,
-        search_content: bool,
-        ignore_case: bool,
-        mut callback: F,
-    ) -> Result<usize>
+    ///
+    /// `callback` is invoked on the calling thread for each match; returning
+    /// `false` stops the search. Matches arrive as they are found unless
+    /// `sort` is configured, in which case they are collected and reported in
+    /// that order once the walk completes.
+    pub fn search<P, F>(&self, path: P, query: &SearchQuery, callback: F) -> Result<usize>
     where
         P: AsRef<Path>,
         F: FnMut(&FileEntry) -> bool,
@@ -135,125 +386,334 @@ impl FileScanner {
             return Err(anyhow::anyhow!("Path does not exist: {}", path.display()));
         }
 
-        let query = if ignore_case {
-            query.to_lowercase()
-        } else {
-            query.to_string()
-        };
-
-        let mut count = 0;
-        let mut walker = WalkDir::new(path);
-
-        // Apply configuration to walker
-        if let Some(depth) = self.config.max_depth {
-            walker = walker.max_depth(depth);
-        }
+        self.search_from(path, path, query, callback)
+    }
 
-        if !self.config.recursive {
-            walker = walker.max_depth(1);
-        }
+    /// Search `dir`, which lies at or below `root`, matching globs relative to `root`
+    fn search_from<F>(
+        &self,
+        root: &Path,
+        dir: &Path,
+        query: &SearchQuery,
+        mut callback: F,
+    ) -> Result<usize>
+    where
+        F: FnMut(&FileEntry) -> bool,
+    {
+        let stop = AtomicBool::new(false);
+        let (tx, rx) = mpsc::sync_channel::<FileEntry>(256);
 
-        for entry in walker.into_iter().filter_map(Result::ok) {
-            // Skip directories in exclude list
-            if entry.file_type().is_dir() {
-                if self.config.exclude_dirs.iter().any(|dir| {
-                    entry.path().file_name().map_or(false, |name| {
-                        name.to_string_lossy().eq_ignore_ascii_case(dir)
-                    })
-                }) {
-                    continue;
+        std::thread::scope(|scope| {
+            let walker = scope.spawn(|| {
+                let tx = tx;
+                let report = |entry: FileEntry| {
+                    // The receiver is gone once the callback asked to stop
+                    if tx.send(entry).is_err() {
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                };
+                match &self.index {
+                    Some(indexed) if dir.is_dir() => {
+                        self.walk_index(indexed, dir, &stop, &|entry: FileEntry| {
+                            if self.matches_path(root, &entry.path, query)
+                                && self.matches(&entry, query)
+                            {
+                                report(entry);
+                            }
+                        })
+                    }
+                    _ => self.walk(dir, &stop, &|entry_path: &Path, is_dir: bool| {
+                        if is_dir {
+                            return;
+                        }
+                        if let Some(entry) = self.match_file(root, entry_path, query) {
+                            report(entry);
+                        }
+                    }),
                 }
-                // Don't continue here - we want to process files in this directory
-                continue;
-            }
+            });
 
-            // Check file extension if specified
-            if let Some(extensions) = &self.config.extensions {
-                if let Some(ext) = entry.path().extension() {
-                    let ext_str = ext.to_string_lossy().to_string();
-                    if !extensions.iter().any(|e| e.eq_ignore_ascii_case(&ext_str)) {
-                        continue;
+            let mut count = 0;
+            if let Some(sort) = self.config.sort {
+                let mut entries: Vec<FileEntry> = rx.iter().collect();
+                sort_entries(&mut entries, sort, self.config.reverse);
+                for entry in &entries {
+                    count += 1;
+                    if !callback(entry) {
+                        break;
+                    }
+                }
+            } else {
+                for entry in rx.iter() {
+                    count += 1;
+                    if !callback(&entry) {
+                        stop.store(true, Ordering::Relaxed);
+                        break;
                     }
-                } else {
-                    continue;
                 }
             }
+            drop(rx);
 
-            // Get file metadata
-            let metadata = match fs::metadata(entry.path()) {
-                Ok(m) => m,
-                Err(_) => continue,
-            };
+            walker
+                .join()
+                .map_err(|_| anyhow::anyhow!("Scanner thread panicked"))??;
+            Ok(count)
+        })
+    }
 
-            // Check size filters
-            let size = metadata.len();
-            if let Some(min) = self.config.min_size {
-                if size < min {
-                    continue;
-                }
-            }
-            if let Some(max) = self.config.max_size {
-                if size > max {
-                    continue;
-                }
+    /// Stat the file at `path` and return it if it passes the filters and matches `query`
+    fn match_file(&self, root: &Path, path: &Path, query: &SearchQuery) -> Option<FileEntry> {
+        if !self.matches_path(root, path, query) {
+            return None;
+        }
+        let metadata = fs::metadata(path).ok()?;
+        let mut entry = FileEntry {
+            path: path.to_path_buf(),
+            is_dir: false,
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            file_type: path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map(|s| s.to_string()),
+            svg: None,
+        };
+        if query.needs_svg_info()
+            && entry
+                .file_type
+                .as_deref()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+        {
+            entry.svg = SvgInfo::read(path);
+        }
+        self.matches(&entry, query).then_some(entry)
+    }
+
+    /// Depth at which the walk from `root` reaches `path`, or `None` if the walk
+    /// never gets there because of the depth limit or an excluded component
+    #[cfg(feature = "watch")]
+    fn depth_below(&self, root: &Path, path: &Path, is_dir: bool) -> Option<usize> {
+        let relative = path.strip_prefix(root).ok()?;
+        let names: Vec<_> = relative.components().collect();
+        for (i, name) in names.iter().enumerate() {
+            let name = name.as_os_str().to_string_lossy();
+            if is_excluded(&name, is_dir || i + 1 < names.len(), &self.config) {
+                return None;
             }
+        }
+        (names.len() <= self.max_depth()).then_some(names.len())
+    }
 
-            // Create file entry
-            let file_entry = FileEntry {
-                path: entry.path().to_path_buf(),
-                is_dir: false,
-                size,
-                modified: metadata.modified().ok(),
-                file_type: entry
-                    .path()
-                    .extension()
-                    .and_then(|ext| ext.to_str())
-                    .map(|s| s.to_string()),
+    /// The file at `path` below `root` if a search of `root` would report it
+    #[cfg(feature = "watch")]
+    pub(crate) fn match_one(
+        &self,
+        root: &Path,
+        path: &Path,
+        query: &SearchQuery,
+    ) -> Option<FileEntry> {
+        self.depth_below(root, path, false)?;
+        self.match_file(root, path, query)
+    }
+
+    /// Report what a search of `root` would find below its subdirectory `dir`
+    #[cfg(feature = "watch")]
+    pub(crate) fn search_below<F>(
+        &self,
+        root: &Path,
+        dir: &Path,
+        query: &SearchQuery,
+        callback: F,
+    ) -> Result<usize>
+    where
+        F: FnMut(&FileEntry) -> bool,
+    {
+        let depth = match self.depth_below(root, dir, true) {
+            Some(depth) if depth < self.max_depth() => depth,
+            _ => return Ok(0),
+        };
+        let scanner = FileScanner::new().with_config(ScannerConfig {
+            recursive: true,
+            max_depth: Some(self.max_depth() - depth),
+            sort: None,
+            ..self.config.clone()
+        });
+        scanner.search_from(root, dir, query, callback)
+    }
+
+    /// How deep below the search root the walk goes
+    fn max_depth(&self) -> usize {
+        if self.config.recursive {
+            self.config.max_depth.unwrap_or(usize::MAX)
+        } else {
+            1
+        }
+    }
+
+    /// Whether the file at `path` passes the extension filter and the glob of `query`
+    fn matches_path(&self, root: &Path, path: &Path, query: &SearchQuery) -> bool {
+        // Check file extension if specified
+        if let Some(extensions) = &self.config.extensions {
+            let ext = match path.extension() {
+                Some(ext) => ext.to_string_lossy(),
+                None => return false,
             };
+            if !extensions.iter().any(|e| e.eq_ignore_ascii_case(&ext)) {
+                return false;
+            }
+        }
 
-            // Check if filename matches
-            let file_name = entry.file_name().to_string_lossy();
-            let matches = if query == ".*" {
-                // Special case: .* matches all files
-                true
-            } else if ignore_case {
-                file_name.to_lowercase().contains(&query)
-            } else {
-                file_name.contains(&query)
+        query.matches_glob(root, path)
+    }
+
+    /// Whether `entry` passes the size and metadata filters and its name or
+    /// contents match `query`
+    fn matches(&self, entry: &FileEntry, query: &SearchQuery) -> bool {
+        let size = entry.size;
+        if self.config.min_size.is_some_and(|min| size < min)
+            || self.config.max_size.is_some_and(|max| size > max)
+            || !query.matches_fields(entry.svg.as_ref())
+        {
+            return false;
+        }
+
+        let name = entry
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy())
+            .unwrap_or_default();
+        query.matches_name(&name)
+            || (query.search_content
+                && size < self.config.content_size_limit
+                && fs::File::open(&entry.path)
+                    .and_then(|mut file| {
+                        let mut contents = String::new();
+                        file.read_to_string(&mut contents).map(|_| contents)
+                    })
+                    .is_ok_and(|contents| query.matches_content(&contents)))
+    }
+
+    /// Call `visit` with every indexed file below `root`, rebased onto `root`
+    /// as given and limited to the configured depth
+    fn walk_index<F>(
+        &self,
+        indexed: &IndexedRoot,
+        root: &Path,
+        stop: &AtomicBool,
+        visit: &F,
+    ) -> Result<()>
+    where
+        F: Fn(FileEntry) + Sync,
+    {
+        let canonical = root
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve {}", root.display()))?;
+        let max_depth = self.max_depth();
+
+        let each = |entry: &&FileEntry| {
+            if stop.load(Ordering::Relaxed) {
+                return;
+            }
+            let relative = match entry.path.strip_prefix(&canonical) {
+                Ok(relative) => relative,
+                Err(_) => return,
             };
+            if relative.components().count() > max_depth {
+                return;
+            }
+            visit(FileEntry {
+                path: root.join(relative),
+                ..(*entry).clone()
+            });
+        };
 
-            if matches {
-                count += 1;
-                if !callback(&file_entry) {
+        let files: Vec<&FileEntry> = indexed.files_under(&canonical).collect();
+        if !self.config.parallel || self.config.threads == 1 {
+            files.iter().for_each(each);
+        } else {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(self.config.threads)
+                .build()
+                .context("Failed to start scanner thread pool")?
+                .install(|| files.par_iter().for_each(each));
+        }
+        Ok(())
+    }
+
+    /// Call `visit` for every entry under `root` that is not excluded, the root
+    /// itself included
+    ///
+    /// Runs on a rayon pool of `threads` workers when `parallel` is set, in
+    /// which case `visit` is called concurrently and in no particular order.
+    /// Stops early once `stop` is set.
+    fn walk<F>(&self, root: &Path, stop: &AtomicBool, visit: &F) -> Result<()>
+    where
+        F: Fn(&Path, bool) + Sync,
+    {
+        let max_depth = self.max_depth();
+
+        if !self.config.parallel || self.config.threads == 1 {
+            for entry in WalkDir::new(root)
+                .max_depth(max_depth)
+                .into_iter()
+                .filter_entry(|e| {
+                    e.depth() == 0
+                        || !is_excluded(
+                            &e.file_name().to_string_lossy(),
+                            e.file_type().is_dir(),
+                            &self.config,
+                        )
+                })
+                .filter_map(Result::ok)
+            {
+                if stop.load(Ordering::Relaxed) {
                     break;
                 }
-                continue;
+                visit(entry.path(), entry.file_type().is_dir());
             }
+            return Ok(());
+        }
 
-            // If we should search in file contents
-            if search_content && size < 10_000_000 {
-                // Skip large files
-                if let Ok(mut file) = fs::File::open(entry.path()) {
-                    let mut contents = String::new();
-                    if file.read_to_string(&mut contents).is_ok() {
-                        let search_text = if ignore_case {
-                            contents.to_lowercase()
-                        } else {
-                            contents
-                        };
-
-                        if search_text.contains(&query) {
-                            count += 1;
-                            if !callback(&file_entry) {
-                                break;
-                            }
-                        }
-                    }
-                }
-            }
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.config.threads)
+            .build()
+            .context("Failed to start scanner thread pool")?;
+
+        let is_dir = fs::metadata(root).map(|m| m.is_dir()).unwrap_or(false);
+        visit(root, is_dir);
+        if is_dir && max_depth > 0 {
+            pool.install(|| self.walk_dir(root, 1, max_depth, stop, visit));
         }
+        Ok(())
+    }
+
+    /// Visit the children of `dir`, which sit at `depth`, and descend into
+    /// subdirectories in parallel
+    fn walk_dir<F>(&self, dir: &Path, depth: usize, max_depth: usize, stop: &AtomicBool, visit: &F)
+    where
+        F: Fn(&Path, bool) + Sync,
+    {
+        let children: Vec<fs::DirEntry> = match fs::read_dir(dir) {
+            Ok(read_dir) => read_dir.filter_map(Result::ok).collect(),
+            Err(_) => return,
+        };
+
+        children.par_iter().for_each(|child| {
+            if stop.load(Ordering::Relaxed) {
+                return;
+            }
+            // Like walkdir, symlinks are reported but never followed
+            let is_dir = child.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            if is_excluded(&child.file_name().to_string_lossy(), is_dir, &self.config) {
+                return;
+            }
 
-        Ok(count)
+            let path = child.path();
+            visit(&path, is_dir);
+            if is_dir && depth < max_depth {
+                self.walk_dir(&path, depth + 1, max_depth, stop, visit);
+            }
+        });
     }
 
     /// Configure the scanner with custom settings
@@ -261,6 +721,32 @@ impl FileScanner {
         self.config = config;
         self
     }
+
+    #[cfg(feature = "watch")]
+    pub fn config(&self) -> &ScannerConfig {
+        &self.config
+    }
+
+    /// Watch `path` for files matching `query` being added, removed or modified
+    ///
+    /// The returned watcher starts out knowing the current matches; call
+    /// [`ScanWatcher::next_changes`](crate::watch::ScanWatcher::next_changes)
+    /// to wait for changes to them.
+    #[cfg(feature = "watch")]
+    pub fn watch<P: AsRef<Path>>(
+        self,
+        path: P,
+        query: SearchQuery,
+    ) -> Result<crate::watch::ScanWatcher> {
+        crate::watch::ScanWatcher::new(self, path.as_ref(), query)
+    }
+
+    /// Search an up-to-date index (see [`ScanIndex::lookup`](crate::index::ScanIndex::lookup))
+    /// instead of walking directories it covers
+    pub fn with_index(mut self, index: Option<IndexedRoot>) -> Self {
+        self.index = index;
+        self
+    }
 }
 
 impl Default for FileScanner {
@@ -269,7 +755,6 @@ impl Default for FileScanner {
     }
 }
 
-#[cfg(test)]
 /// Get metadata for a file
 pub fn get_file_metadata<P: AsRef<Path>>(path: P) -> Result<FileEntry> {
     let path = path.as_ref();
@@ -289,6 +774,7 @@ pub fn get_file_metadata<P: AsRef<Path>>(path: P) -> Result<FileEntry> {
         size: metadata.len(),
         modified: metadata.modified().ok(),
         file_type,
+        svg: None,
     })
 }
 
@@ -297,7 +783,7 @@ mod tests {
     use super::*;
     use std::fs::File;
     use std::io::Write;
-    use std::path::Path;
+
     use tempfile::tempdir;
 
     #[test]
@@ -337,7 +823,7 @@ mod tests {
                     && f.path
                         .file_name()
                         .and_then(|n| n.to_str())
-                        .map_or(false, |n| !n.starts_with('.'))
+                        .is_some_and(|n| !n.starts_with('.'))
             })
             .collect();
 
@@ -378,4 +864,208 @@ mod tests {
         temp_dir.close()?;
         Ok(())
     }
+
+    /// Collect the file names `search` reports, sorted
+    fn search_names(scanner: &FileScanner, root: &Path, query: &SearchQuery) -> Vec<String> {
+        let mut names = Vec::new();
+        scanner
+            .search(root, query, |entry| {
+                names.push(
+                    entry
+                        .path
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string(),
+                );
+                true
+            })
+            .unwrap();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_search_regex_and_glob() -> Result<()> {
+        let dir = tempdir()?;
+        let icons = dir.path().join("assets").join("icons");
+        std::fs::create_dir_all(&icons)?;
+        for name in ["logo-1.svg", "logo-22.svg", "logo-x.svg", "Logo-3.svg"] {
+            File::create(dir.path().join(name))?;
+        }
+        File::create(icons.join("home.svg"))?;
+        File::create(icons.join("readme.txt"))?;
+
+        let scanner = FileScanner::new();
+        let root = dir.path();
+
+        let query = SearchQuery::regex(r"^logo-\d+", false)?;
+        assert_eq!(
+            search_names(&scanner, root, &query),
+            ["logo-1.svg", "logo-22.svg"]
+        );
+
+        let query = SearchQuery::regex(r"^logo-\d+", true)?;
+        assert_eq!(search_names(&scanner, root, &query).len(), 3);
+
+        let query = SearchQuery::all().with_glob("**/icons/*.svg", false)?;
+        assert_eq!(search_names(&scanner, root, &query), ["home.svg"]);
+
+        // `*` does not cross directory separators
+        let query = SearchQuery::all().with_glob("*.svg", false)?;
+        assert_eq!(search_names(&scanner, root, &query).len(), 4);
+
+        assert!(SearchQuery::regex("(unclosed", false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_content_uses_pattern() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("a.svg"), "<svg><circle r=\"4\"/></svg>")?;
+        std::fs::write(dir.path().join("b.svg"), "<svg><rect/></svg>")?;
+
+        let scanner = FileScanner::new();
+        let query = SearchQuery::literal("circle", false).with_content(true);
+        assert_eq!(search_names(&scanner, dir.path(), &query), ["a.svg"]);
+
+        let query = SearchQuery::literal("circle", false);
+        assert!(search_names(&scanner, dir.path(), &query).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_excludes_are_pruned_in_walker() -> Result<()> {
+        let dir = tempdir()?;
+        let nested = dir.path().join("node_modules").join("pkg");
+        std::fs::create_dir_all(&nested)?;
+        File::create(nested.join("inside.svg"))?;
+        File::create(dir.path().join("keep.svg"))?;
+        File::create(dir.path().join("draft.svg.bak"))?;
+        File::create(dir.path().join(".hidden.svg"))?;
+
+        let config = ScannerConfig {
+            exclude_patterns: vec![Regex::new(r".*\.bak$")?],
+            include_hidden: false,
+            ..Default::default()
+        };
+        let scanner = FileScanner::new().with_config(config.clone());
+        assert_eq!(
+            search_names(&scanner, dir.path(), &SearchQuery::all()),
+            ["keep.svg"]
+        );
+
+        let scanned = scanner.scan(dir.path())?;
+        assert!(scanned
+            .iter()
+            .all(|e| !e.path.starts_with(dir.path().join("node_modules"))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parallel_search_matches_sequential() -> Result<()> {
+        let dir = tempdir()?;
+        for d in 0..4 {
+            let sub = dir.path().join(format!("dir{}", d)).join("nested");
+            std::fs::create_dir_all(&sub)?;
+            for f in 0..5 {
+                std::fs::write(sub.join(format!("f{}.svg", f)), "x".repeat(f + d))?;
+            }
+        }
+        std::fs::create_dir_all(dir.path().join(".git"))?;
+        File::create(dir.path().join(".git").join("hidden.svg"))?;
+
+        let sequential = FileScanner::new();
+        let parallel = FileScanner::new().with_config(ScannerConfig {
+            parallel: true,
+            threads: 4,
+            ..Default::default()
+        });
+        let query = SearchQuery::regex(r"^f[0-3]", false)?;
+        let expected = search_names(&sequential, dir.path(), &query);
+        assert_eq!(expected.len(), 16);
+        assert_eq!(search_names(&parallel, dir.path(), &query), expected);
+
+        // Depth limits apply the same way
+        let shallow = |parallel| {
+            FileScanner::new().with_config(ScannerConfig {
+                max_depth: Some(2),
+                parallel,
+                ..Default::default()
+            })
+        };
+        assert!(search_names(&shallow(true), dir.path(), &SearchQuery::all()).is_empty());
+        assert_eq!(
+            shallow(false).scan(dir.path())?.len(),
+            shallow(true).scan(dir.path())?.len()
+        );
+
+        // With a sort the callback sees a deterministic order
+        let sorted = FileScanner::new().with_config(ScannerConfig {
+            parallel: true,
+            sort: Some(SortBy::Size),
+            reverse: true,
+            ..Default::default()
+        });
+        let mut sizes = Vec::new();
+        sorted.search(dir.path(), &SearchQuery::all(), |entry| {
+            sizes.push(entry.size);
+            true
+        })?;
+        assert_eq!(sizes.len(), 20);
+        assert!(sizes.windows(2).all(|w| w[0] >= w[1]));
+
+        // Returning false stops the walk
+        let mut seen = 0;
+        let count = parallel.search(dir.path(), &SearchQuery::all(), |_| {
+            seen += 1;
+            seen < 3
+        })?;
+        assert_eq!((seen, count), (3, 3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_from_index() -> Result<()> {
+        let dir = tempdir()?;
+        let nested = dir.path().join("a").join("b");
+        std::fs::create_dir_all(&nested)?;
+        File::create(dir.path().join("top.svg"))?;
+        File::create(dir.path().join("a").join("mid.svg"))?;
+        File::create(nested.join("deep.svg"))?;
+        File::create(nested.join("notes.txt"))?;
+
+        let config = ScannerConfig {
+            extensions: Some(vec!["svg".to_string()]),
+            max_depth: Some(2),
+            ..Default::default()
+        };
+        let (indexed, _) = IndexedRoot::build(dir.path(), &config)?;
+        let walked = FileScanner::new().with_config(config.clone());
+        let indexed = FileScanner::new()
+            .with_config(config)
+            .with_index(Some(indexed));
+
+        let query = SearchQuery::all();
+        assert_eq!(
+            search_names(&indexed, dir.path(), &query),
+            ["mid.svg", "top.svg"]
+        );
+        assert_eq!(
+            search_names(&indexed, dir.path(), &query),
+            search_names(&walked, dir.path(), &query)
+        );
+
+        // Results are rebased onto the path as given
+        let sub = dir.path().join("a");
+        indexed.search(&sub, &query, |entry| {
+            assert!(entry.path.starts_with(&sub));
+            true
+        })?;
+        assert_eq!(
+            search_names(&indexed, &sub, &query),
+            ["deep.svg", "mid.svg"]
+        );
+        Ok(())
+    }
 }
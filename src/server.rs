@@ -0,0 +1,223 @@
+//! Minimal HTTP server for in-memory files
+//!
+//! Just enough HTTP/1.1 to host a launched SVG app on localhost: `GET` and
+//! `HEAD` of a fixed set of paths, one request per connection.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+
+/// Largest request head we read before giving up on a client
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+
+/// How long a client has to send its request head before it is dropped
+const REQUEST_HEAD_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A file served from memory
+#[derive(Debug, Clone)]
+pub struct Asset {
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Asset {
+    pub fn new(content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            content_type,
+            body: body.into(),
+        }
+    }
+}
+
+/// Serves a fixed set of [`Asset`]s by path; `/` is `/index.html`
+pub struct StaticServer {
+    listener: TcpListener,
+    assets: Arc<HashMap<String, Asset>>,
+    requests: Arc<watch::Sender<u64>>,
+}
+
+impl StaticServer {
+    /// Listen on `address` and `port` (0 picks a free port)
+    pub async fn bind(address: &str, port: u16, assets: HashMap<String, Asset>) -> Result<Self> {
+        let listener = TcpListener::bind((address, port))
+            .await
+            .with_context(|| format!("Failed to listen on {}:{}", address, port))?;
+        Ok(Self {
+            listener,
+            assets: Arc::new(assets),
+            requests: Arc::new(watch::channel(0).0),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener
+            .local_addr()
+            .context("Failed to read the server address")
+    }
+
+    /// Number of requests answered so far, updated as they are served
+    pub fn requests(&self) -> watch::Receiver<u64> {
+        self.requests.subscribe()
+    }
+
+    /// Answer requests until the task is dropped
+    pub async fn run(self) -> Result<()> {
+        loop {
+            let (stream, _) = self
+                .listener
+                .accept()
+                .await
+                .context("Failed to accept a connection")?;
+            let assets = Arc::clone(&self.assets);
+            let requests = Arc::clone(&self.requests);
+            tokio::spawn(async move {
+                // A client hanging up early is not our problem
+                if handle(stream, &assets).await.unwrap_or(false) {
+                    requests.send_modify(|count| *count += 1);
+                }
+            });
+        }
+    }
+}
+
+/// Answer one request; `false` if the client went away without asking anything
+async fn handle(mut stream: TcpStream, assets: &HashMap<String, Asset>) -> std::io::Result<bool> {
+    let Some(head) = read_head(&mut stream, REQUEST_HEAD_TIMEOUT).await? else {
+        return Ok(false);
+    };
+
+    let head = String::from_utf8_lossy(&head);
+    let request_line = head.lines().next().unwrap_or_default();
+    stream.write_all(&respond(request_line, assets)).await?;
+    stream.shutdown().await?;
+    Ok(true)
+}
+
+/// Read up to the blank line ending the request head
+///
+/// `None` if the client hangs up, sends too much, or takes longer than `limit`.
+async fn read_head<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    limit: Duration,
+) -> std::io::Result<Option<Vec<u8>>> {
+    let read = async {
+        let mut head = Vec::new();
+        let mut buf = [0u8; 1024];
+        while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = stream.read(&mut buf).await?;
+            if n == 0 || head.len() + n > MAX_REQUEST_HEAD {
+                return Ok(None);
+            }
+            head.extend_from_slice(&buf[..n]);
+        }
+        Ok(Some(head))
+    };
+    tokio::time::timeout(limit, read).await.unwrap_or(Ok(None))
+}
+
+/// The full response to a request line such as `GET /app.svg HTTP/1.1`
+fn respond(request_line: &str, assets: &HashMap<String, Asset>) -> Vec<u8> {
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or_default();
+    let path = match target.split(['?', '#']).next().unwrap_or_default() {
+        "/" => "/index.html",
+        path => path,
+    };
+
+    let (status, asset) = match (method, assets.get(path)) {
+        ("GET" | "HEAD", Some(asset)) => ("200 OK", Some(asset)),
+        ("GET" | "HEAD", None) => ("404 Not Found", None),
+        _ => ("405 Method Not Allowed", None),
+    };
+    let (content_type, body) = match asset {
+        Some(asset) => (asset.content_type, asset.body.as_slice()),
+        None => ("text/plain; charset=utf-8", status.as_bytes()),
+    };
+
+    let mut response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nX-Content-Type-Options: nosniff\r\nConnection: close\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    if status.starts_with("405") {
+        response.push_str("Allow: GET, HEAD\r\n");
+    }
+    response.push_str("\r\n");
+
+    let mut response = response.into_bytes();
+    if method != "HEAD" {
+        response.extend_from_slice(body);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn request(addr: SocketAddr, request: &str) -> Result<String> {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.write_all(request.as_bytes()).await?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+        Ok(response)
+    }
+
+    #[tokio::test]
+    async fn test_static_server() -> Result<()> {
+        let assets = HashMap::from([
+            (
+                "/index.html".to_string(),
+                Asset::new("text/html; charset=utf-8", "<h1>hi</h1>"),
+            ),
+            (
+                "/app.svg".to_string(),
+                Asset::new("image/svg+xml", "<svg/>"),
+            ),
+        ]);
+        let server = StaticServer::bind("127.0.0.1", 0, assets).await?;
+        let addr = server.local_addr()?;
+        let mut requests = server.requests();
+        tokio::spawn(server.run());
+
+        let response = request(addr, "GET /?source=pwa HTTP/1.1\r\nHost: x\r\n\r\n").await?;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: text/html; charset=utf-8\r\n"));
+        assert!(response.ends_with("\r\n\r\n<h1>hi</h1>"));
+        requests.changed().await?;
+        assert_eq!(*requests.borrow(), 1);
+
+        let response = request(addr, "HEAD /app.svg HTTP/1.1\r\n\r\n").await?;
+        assert!(response.contains("Content-Type: image/svg+xml\r\n"));
+        assert!(response.contains("Content-Length: 6\r\n"));
+        assert!(response.ends_with("\r\n\r\n"));
+
+        let response = request(addr, "GET /../etc/passwd HTTP/1.1\r\n\r\n").await?;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+        let response = request(addr, "POST / HTTP/1.1\r\n\r\n").await?;
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed"));
+        assert!(response.contains("Allow: GET, HEAD\r\n"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_read_head() -> Result<()> {
+        let (mut client, mut server) = tokio::io::duplex(64);
+        client.write_all(b"GET / HTTP/1.1\r\n\r\n").await?;
+        let head = read_head(&mut server, Duration::from_secs(5)).await?;
+        assert_eq!(head.as_deref(), Some(&b"GET / HTTP/1.1\r\n\r\n"[..]));
+
+        // A client that never finishes its head is dropped
+        client.write_all(b"GET / HTTP/1.1\r\n").await?;
+        let head = read_head(&mut server, Duration::from_millis(50)).await?;
+        assert_eq!(head, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,599 @@
+//! Layered configuration
+//!
+//! Settings are resolved from, in increasing priority:
+//! 1. the built-in defaults (`config/default.toml`, embedded at compile time)
+//! 2. the user file (`~/.config/sview/config.toml`, or `--config <file>`)
+//! 3. `SVIEW_<SECTION>__<KEY>` environment variables
+//! 4. command-line flags, applied by each command on top of [`Settings`]
+//!
+//! The embedded defaults double as the schema: `sview config validate`
+//! reports keys that do not exist there and values whose type differs.
+
+use anyhow::{Context, Result};
+use config::{Config, Environment, File, FileFormat};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Built-in defaults
+pub const DEFAULTS: &str = include_str!("../config/default.toml");
+
+/// Prefix of configuration environment variables
+const ENV_PREFIX: &str = "SVIEW";
+
+/// Separates section and key in environment variable names
+const ENV_SEPARATOR: &str = "__";
+
+/// Keys whose environment variables hold a comma-separated list
+const ENV_LIST_KEYS: [&str; 6] = [
+    "browser.alternatives",
+    "browser.flags",
+    "languages.supported",
+    "paths.scan_paths",
+    "paths.exclude_paths",
+    "paths.exclude_patterns",
+];
+
+/// Fully resolved, typed settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Settings {
+    pub general: GeneralSettings,
+    pub browser: BrowserSettings,
+    pub performance: PerformanceSettings,
+    pub sview: SviewSettings,
+    pub security: SecuritySettings,
+    pub languages: LanguageSettings,
+    pub ui: UiSettings,
+    pub export: ExportSettings,
+    pub paths: PathSettings,
+    pub networking: NetworkingSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneralSettings {
+    pub scan_depth: usize,
+    pub default_format: String,
+    pub cache_thumbnails: bool,
+    pub max_scan_time: u64,
+    pub log_level: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrowserSettings {
+    pub command: String,
+    pub alternatives: Vec<String>,
+    pub flags: Vec<String>,
+    pub local_server_port: u16,
+    pub launch_timeout: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerformanceSettings {
+    pub parallel_scan: bool,
+    pub max_threads: usize,
+    pub cache_size: u64,
+    pub batch_size: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SviewSettings {
+    pub enabled: bool,
+    pub memory_encryption: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecuritySettings {
+    pub encryption_algorithm: String,
+    pub key_derivation: String,
+    pub argon2_memory: u32,
+    pub argon2_iterations: u32,
+    pub argon2_parallelism: u32,
+    pub key_rotation_days: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanguageSettings {
+    pub supported: Vec<String>,
+    pub execution_timeout: u64,
+    pub execution_memory_limit: u64,
+    pub enable_sandbox: bool,
+    pub sandbox_isolate_network: bool,
+}
+
+/// How `view` lays out a directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ViewMode {
+    Grid,
+    List,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UiSettings {
+    pub default_columns: usize,
+    pub default_view: ViewMode,
+    pub thumbnail_size: u32,
+    pub show_hidden_files: bool,
+    pub max_search_results: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSettings {
+    pub format: String,
+    /// JPEG quality, 1-100
+    pub quality: u8,
+    /// `#rrggbb` or `transparent`
+    pub background: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathSettings {
+    pub scan_paths: Vec<String>,
+    pub exclude_paths: Vec<String>,
+    pub exclude_patterns: Vec<String>,
+    /// Maximum file size to scan, in MB
+    pub max_file_size: u64,
+    pub cache_dir: String,
+    pub config_dir: String,
+    pub log_dir: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkingSettings {
+    pub default_port: u16,
+    pub bind_address: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        toml::from_str(DEFAULTS).expect("built-in defaults are valid")
+    }
+}
+
+impl Settings {
+    /// Default location of the user configuration file
+    pub fn user_config_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("sview").join("config.toml"))
+    }
+}
+
+impl PathSettings {
+    /// `cache_dir` with a leading `~` expanded
+    pub fn cache_dir(&self) -> PathBuf {
+        expand_tilde(&self.cache_dir)
+    }
+
+    /// `scan_paths` with a leading `~` expanded
+    pub fn scan_paths(&self) -> Vec<PathBuf> {
+        self.scan_paths.iter().map(|p| expand_tilde(p)).collect()
+    }
+}
+
+/// Expand a leading `~` to the home directory
+pub fn expand_tilde(path: &str) -> PathBuf {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// A problem found by [`ConfigLayers::validate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    /// Where the offending key came from (file path or "environment")
+    pub source: String,
+    pub key: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}: {}", self.source, self.key, self.message)
+    }
+}
+
+/// The configuration layers in effect for this invocation
+pub struct ConfigLayers {
+    user_file: Option<PathBuf>,
+    env: HashMap<String, String>,
+    merged: toml::Value,
+}
+
+impl ConfigLayers {
+    /// Load defaults, the user file (or `override_file`) and the process environment
+    pub fn load(override_file: Option<&Path>) -> Result<Self> {
+        let env = std::env::vars()
+            .filter(|(k, _)| is_config_env_var(k))
+            .collect();
+        Self::load_with_env(override_file, env)
+    }
+
+    /// Like [`ConfigLayers::load`] with an explicit set of environment variables
+    pub fn load_with_env(
+        override_file: Option<&Path>,
+        env: HashMap<String, String>,
+    ) -> Result<Self> {
+        let user_file = match override_file {
+            Some(path) => {
+                if !path.exists() {
+                    return Err(anyhow::anyhow!(
+                        "Config file does not exist: {}",
+                        path.display()
+                    ));
+                }
+                Some(path.to_path_buf())
+            }
+            None => Settings::user_config_path().filter(|p| p.exists()),
+        };
+
+        let mut builder = Config::builder().add_source(File::from_str(DEFAULTS, FileFormat::Toml));
+        if let Some(path) = &user_file {
+            builder = builder.add_source(File::from(path.as_path()).format(FileFormat::Toml));
+        }
+        let env: HashMap<String, String> = env
+            .into_iter()
+            .filter(|(k, _)| is_config_env_var(k))
+            .collect();
+        let environment = Environment::with_prefix(ENV_PREFIX)
+            .prefix_separator("_")
+            .separator(ENV_SEPARATOR)
+            .try_parsing(true)
+            .list_separator(",")
+            .source(Some(env.clone()));
+        builder = builder.add_source(ENV_LIST_KEYS.iter().fold(environment, |environment, key| {
+            environment.with_list_parse_key(key)
+        }));
+
+        let merged = builder
+            .build()
+            .context("Failed to load configuration")?
+            .try_deserialize::<toml::Value>()
+            .context("Failed to merge configuration layers")?;
+
+        Ok(Self {
+            user_file,
+            env,
+            merged,
+        })
+    }
+
+    /// Typed settings, failing if any value has the wrong type
+    pub fn settings(&self) -> Result<Settings> {
+        self.merged.clone().try_into().map_err(|e| {
+            anyhow::anyhow!("Invalid configuration: {} (run `sview config validate`)", e)
+        })
+    }
+
+    /// Human-readable description of each layer in effect
+    pub fn sources(&self) -> Vec<String> {
+        let mut sources = vec!["built-in defaults".to_string()];
+        if let Some(path) = &self.user_file {
+            sources.push(path.display().to_string());
+        }
+        if !self.env.is_empty() {
+            let mut vars: Vec<&str> = self.env.keys().map(String::as_str).collect();
+            vars.sort_unstable();
+            sources.push(format!("environment ({})", vars.join(", ")));
+        }
+        sources
+    }
+
+    /// The merged configuration as a TOML document
+    pub fn merged(&self) -> &toml::Value {
+        &self.merged
+    }
+
+    /// Look up a dotted key (e.g. `general.scan_depth`) in the merged configuration
+    pub fn get(&self, key: &str) -> Option<&toml::Value> {
+        lookup(&self.merged, key)
+    }
+
+    /// Report unknown keys and values whose type differs from the defaults
+    pub fn validate(&self) -> Vec<ConfigIssue> {
+        let defaults: toml::Value = toml::from_str(DEFAULTS).expect("built-in defaults are valid");
+        let mut issues = Vec::new();
+
+        if let Some(path) = &self.user_file {
+            let source = path.display().to_string();
+            match fs::read_to_string(path)
+                .map_err(anyhow::Error::from)
+                .and_then(|s| toml::from_str::<toml::Value>(&s).map_err(anyhow::Error::from))
+            {
+                Ok(user) => {
+                    let mut leaves = Vec::new();
+                    collect_leaves(&user, "", &mut leaves);
+                    for (key, value) in leaves {
+                        if let Some(message) = check_value(&defaults, &key, &value) {
+                            issues.push(ConfigIssue {
+                                source: source.clone(),
+                                key,
+                                message,
+                            });
+                        }
+                    }
+                }
+                Err(e) => issues.push(ConfigIssue {
+                    source,
+                    key: "-".to_string(),
+                    message: format!("cannot parse file: {}", e),
+                }),
+            }
+        }
+
+        let mut env: Vec<_> = self.env.iter().collect();
+        env.sort();
+        for (var, raw) in env {
+            let key = env_var_key(var);
+            if let Some(message) = check_value(&defaults, &key, &parse_env_value(&key, raw)) {
+                issues.push(ConfigIssue {
+                    source: format!("environment ({})", var),
+                    key,
+                    message,
+                });
+            }
+        }
+
+        // Values can have the right TOML type but still not fit (e.g. an unknown view mode)
+        if issues.is_empty() {
+            if let Err(e) = self.settings() {
+                issues.push(ConfigIssue {
+                    source: "merged configuration".to_string(),
+                    key: "-".to_string(),
+                    message: e.to_string(),
+                });
+            }
+        }
+
+        issues
+    }
+}
+
+/// Set `key` to `value` in the TOML file at `path`, creating it if needed
+///
+/// The value is parsed as a TOML literal when possible (numbers, booleans,
+/// arrays) and as a plain string otherwise; it must match the type of the
+/// built-in default for that key.
+pub fn set_in_file(path: &Path, key: &str, value: &str) -> Result<()> {
+    let defaults: toml::Value = toml::from_str(DEFAULTS).expect("built-in defaults are valid");
+    let value = parse_scalar(value);
+    if let Some(message) = check_value(&defaults, key, &value) {
+        return Err(anyhow::anyhow!("{}: {}", key, message));
+    }
+
+    let mut doc: toml::Value = if path.exists() {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+        toml::from_str(&text)
+            .with_context(|| format!("Failed to parse config file: {}", path.display()))?
+    } else {
+        toml::Value::Table(Default::default())
+    };
+
+    let mut table = doc
+        .as_table_mut()
+        .ok_or_else(|| anyhow::anyhow!("Config file is not a table: {}", path.display()))?;
+    let mut parts: Vec<&str> = key.split('.').collect();
+    let leaf = parts.pop().unwrap_or(key);
+    for part in parts {
+        table = table
+            .entry(part.to_string())
+            .or_insert_with(|| toml::Value::Table(Default::default()))
+            .as_table_mut()
+            .ok_or_else(|| anyhow::anyhow!("{} is not a section", part))?;
+    }
+    table.insert(leaf.to_string(), value);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    fs::write(path, toml::to_string_pretty(&doc)?)
+        .with_context(|| format!("Failed to write config file: {}", path.display()))?;
+    Ok(())
+}
+
+/// Render a value for display (strings without quotes)
+pub fn display_value(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s.clone(),
+        toml::Value::Table(_) => toml::to_string_pretty(value).unwrap_or_default(),
+        other => other.to_string(),
+    }
+}
+
+fn is_config_env_var(name: &str) -> bool {
+    name.strip_prefix(ENV_PREFIX)
+        .and_then(|rest| rest.strip_prefix('_'))
+        .is_some_and(|rest| rest.contains(ENV_SEPARATOR))
+}
+
+fn env_var_key(name: &str) -> String {
+    name[ENV_PREFIX.len() + 1..]
+        .to_lowercase()
+        .replace(ENV_SEPARATOR, ".")
+}
+
+fn lookup<'a>(value: &'a toml::Value, key: &str) -> Option<&'a toml::Value> {
+    key.split('.')
+        .try_fold(value, |v, part| v.as_table().and_then(|t| t.get(part)))
+}
+
+fn collect_leaves(value: &toml::Value, prefix: &str, out: &mut Vec<(String, toml::Value)>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (k, v) in table {
+                let key = if prefix.is_empty() {
+                    k.clone()
+                } else {
+                    format!("{}.{}", prefix, k)
+                };
+                collect_leaves(v, &key, out);
+            }
+        }
+        other => out.push((prefix.to_string(), other.clone())),
+    }
+}
+
+/// Parse a command-line or environment value as a TOML literal, falling back to a string
+fn parse_scalar(raw: &str) -> toml::Value {
+    toml::from_str::<toml::Value>(&format!("v = {}", raw))
+        .ok()
+        .and_then(|doc| doc.get("v").cloned())
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
+
+/// Parse an environment value the way the environment layer reads it
+fn parse_env_value(key: &str, raw: &str) -> toml::Value {
+    if ENV_LIST_KEYS.contains(&key) {
+        toml::Value::Array(
+            raw.split(',')
+                .map(|item| toml::Value::String(item.to_string()))
+                .collect(),
+        )
+    } else {
+        parse_scalar(raw)
+    }
+}
+
+/// Compare `value` against the default for `key`, returning a description of any mismatch
+fn check_value(defaults: &toml::Value, key: &str, value: &toml::Value) -> Option<String> {
+    let expected = match lookup(defaults, key) {
+        Some(v) => v,
+        None => return Some("unknown key".to_string()),
+    };
+
+    let compatible = matches!(
+        (expected, value),
+        (toml::Value::String(_), toml::Value::String(_))
+            | (toml::Value::Integer(_), toml::Value::Integer(_))
+            | (
+                toml::Value::Float(_),
+                toml::Value::Float(_) | toml::Value::Integer(_)
+            )
+            | (toml::Value::Boolean(_), toml::Value::Boolean(_))
+            | (toml::Value::Array(_), toml::Value::Array(_))
+            | (toml::Value::Datetime(_), toml::Value::Datetime(_))
+    );
+    if compatible {
+        None
+    } else if expected.is_table() {
+        Some("is a section, not a value".to_string())
+    } else {
+        Some(format!(
+            "expected {}, found {}",
+            expected.type_str(),
+            value.type_str()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn env(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_layers_override_in_order() -> Result<()> {
+        let dir = tempdir()?;
+        let file = dir.path().join("config.toml");
+        fs::write(
+            &file,
+            "[general]\nscan_depth = 3\n\n[ui]\ndefault_view = \"list\"\n",
+        )?;
+
+        let layers = ConfigLayers::load_with_env(Some(&file), env(&[]))?;
+        let settings = layers.settings()?;
+        assert_eq!(settings.general.scan_depth, 3);
+        assert_eq!(settings.ui.default_view, ViewMode::List);
+        assert_eq!(settings.browser.command, "chromium");
+
+        let layers = ConfigLayers::load_with_env(
+            Some(&file),
+            env(&[
+                ("SVIEW_GENERAL__SCAN_DEPTH", "8"),
+                ("SVIEW_MEMORY_PASSPHRASE", "not config"),
+            ]),
+        )?;
+        assert_eq!(layers.settings()?.general.scan_depth, 8);
+        assert!(layers.get("memory_passphrase").is_none());
+        assert!(layers.validate().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_keys_from_env() -> Result<()> {
+        let layers = ConfigLayers::load_with_env(
+            None,
+            env(&[
+                ("SVIEW_LANGUAGES__SUPPORTED", "python,go"),
+                ("SVIEW_PATHS__EXCLUDE_PATTERNS", "*.tmp"),
+                ("SVIEW_BROWSER__COMMAND", "firefox,esr"),
+            ]),
+        )?;
+        let settings = layers.settings()?;
+        assert_eq!(settings.languages.supported, ["python", "go"]);
+        assert_eq!(settings.paths.exclude_patterns, ["*.tmp"]);
+        // Other strings keep their commas
+        assert_eq!(settings.browser.command, "firefox,esr");
+        assert!(layers.validate().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_reports_unknown_and_mistyped_keys() -> Result<()> {
+        let dir = tempdir()?;
+        let file = dir.path().join("config.toml");
+        fs::write(
+            &file,
+            "[general]\nscan_depth = \"deep\"\nscan_dpeth = 4\n\n[bogus]\nx = 1\n",
+        )?;
+
+        let layers = ConfigLayers::load_with_env(
+            Some(&file),
+            env(&[("SVIEW_UI__SHOW_HIDDEN_FILES", "sometimes")]),
+        )?;
+        let issues = layers.validate();
+        let keys: Vec<&str> = issues.iter().map(|i| i.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "bogus.x",
+                "general.scan_depth",
+                "general.scan_dpeth",
+                "ui.show_hidden_files"
+            ]
+        );
+        assert!(issues[1].message.contains("expected integer"));
+        assert_eq!(issues[2].message, "unknown key");
+        assert!(layers.settings().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_in_file() -> Result<()> {
+        let dir = tempdir()?;
+        let file = dir.path().join("nested").join("config.toml");
+
+        set_in_file(&file, "general.scan_depth", "4")?;
+        set_in_file(&file, "browser.alternatives", r#"["firefox"]"#)?;
+        set_in_file(&file, "browser.command", "firefox")?;
+        assert!(set_in_file(&file, "general.scan_depth", "deep").is_err());
+        assert!(set_in_file(&file, "general.nope", "1").is_err());
+
+        let settings = ConfigLayers::load_with_env(Some(&file), env(&[]))?.settings()?;
+        assert_eq!(settings.general.scan_depth, 4);
+        assert_eq!(settings.browser.command, "firefox");
+        assert_eq!(settings.browser.alternatives, ["firefox"]);
+        Ok(())
+    }
+}
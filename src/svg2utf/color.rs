@@ -0,0 +1,266 @@
+//! ANSI colour output for half-block terminal rendering
+//!
+//! Each character cell shows two vertically stacked pixels: the upper one as
+//! the foreground of `▀`, the lower one as its background.
+
+use image::RgbaImage;
+use std::fmt::Write;
+
+/// Pixels less opaque than this are left to the terminal background
+const ALPHA_THRESHOLD: u8 = 64;
+
+/// The 16 standard terminal colours (xterm defaults), in SGR order
+const ANSI16: [[u8; 3]; 16] = [
+    [0, 0, 0],
+    [205, 0, 0],
+    [0, 205, 0],
+    [205, 205, 0],
+    [0, 0, 238],
+    [205, 0, 205],
+    [0, 205, 205],
+    [229, 229, 229],
+    [127, 127, 127],
+    [255, 0, 0],
+    [0, 255, 0],
+    [255, 255, 0],
+    [92, 92, 255],
+    [255, 0, 255],
+    [0, 255, 255],
+    [255, 255, 255],
+];
+
+/// Levels of the 6x6x6 colour cube in the 256-colour palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// How many colours the terminal can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// 24-bit RGB
+    TrueColor,
+    /// xterm 256-colour palette
+    Ansi256,
+    /// The 16 basic colours
+    Ansi16,
+}
+
+impl ColorMode {
+    /// Colour support advertised by `COLORTERM` and `TERM`, or `None` if colour
+    /// is unavailable or disabled with `NO_COLOR`
+    pub fn detect() -> Option<Self> {
+        if std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            return None;
+        }
+        Self::from_env(
+            std::env::var("COLORTERM").ok().as_deref(),
+            std::env::var("TERM").ok().as_deref(),
+        )
+    }
+
+    fn from_env(colorterm: Option<&str>, term: Option<&str>) -> Option<Self> {
+        if matches!(colorterm, Some("truecolor") | Some("24bit")) {
+            return Some(ColorMode::TrueColor);
+        }
+        match term {
+            None | Some("") | Some("dumb") => None,
+            Some(term) if term.contains("direct") => Some(ColorMode::TrueColor),
+            Some(term) if term.contains("256color") => Some(ColorMode::Ansi256),
+            Some(_) => Some(ColorMode::Ansi16),
+        }
+    }
+
+    /// SGR parameters selecting `rgb` as the foreground (or background) colour
+    pub(super) fn sgr(self, rgb: [u8; 3], background: bool) -> String {
+        let [r, g, b] = rgb;
+        match self {
+            ColorMode::TrueColor => {
+                format!("{};2;{};{};{}", if background { 48 } else { 38 }, r, g, b)
+            }
+            ColorMode::Ansi256 => {
+                format!("{};5;{}", if background { 48 } else { 38 }, ansi256(rgb))
+            }
+            ColorMode::Ansi16 => {
+                let index = nearest(&ANSI16, rgb) as u8;
+                let base = match (background, index < 8) {
+                    (false, true) => 30,
+                    (false, false) => 90 - 8,
+                    (true, true) => 40,
+                    (true, false) => 100 - 8,
+                };
+                (base + index).to_string()
+            }
+        }
+    }
+}
+
+/// Index of the palette entry closest to `rgb`
+fn nearest(palette: &[[u8; 3]], rgb: [u8; 3]) -> usize {
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, c)| distance(**c, rgb))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+fn distance(a: [u8; 3], b: [u8; 3]) -> u32 {
+    a.iter()
+        .zip(b)
+        .map(|(&x, y)| (x as i32 - y as i32).pow(2) as u32)
+        .sum()
+}
+
+/// Closest entry of the 256-colour palette, from the colour cube or the grey ramp
+pub(super) fn ansi256(rgb: [u8; 3]) -> u8 {
+    let level = |v: u8| nearest(&CUBE_LEVELS.map(|l| [l, 0, 0]), [v, 0, 0]);
+    let [r, g, b] = rgb.map(level);
+    let cube = [CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]];
+
+    let average = (rgb.iter().map(|&v| v as u32).sum::<u32>() / 3) as u8;
+    let grey_index = (average.saturating_sub(3) / 10).min(23);
+    let grey_level = 8 + 10 * grey_index;
+
+    if distance([grey_level; 3], rgb) < distance(cube, rgb) {
+        232 + grey_index
+    } else {
+        16 + 36 * r as u8 + 6 * g as u8 + b as u8
+    }
+}
+
+/// The colour of a 256-colour palette entry returned by [`ansi256`]
+pub(super) fn ansi256_rgb(index: u8) -> [u8; 3] {
+    match index {
+        0..=15 => ANSI16[index as usize],
+        16..=231 => {
+            let i = (index - 16) as usize;
+            [
+                CUBE_LEVELS[i / 36],
+                CUBE_LEVELS[i / 6 % 6],
+                CUBE_LEVELS[i % 6],
+            ]
+        }
+        _ => [8 + 10 * (index - 232); 3],
+    }
+}
+
+/// Colour of the pixel at (`x`, `y`), or `None` if it is (nearly) transparent
+fn pixel(img: &RgbaImage, x: u32, y: u32) -> Option<[u8; 3]> {
+    if y >= img.height() {
+        return None;
+    }
+    let [r, g, b, a] = img.get_pixel(x, y).0;
+    (a >= ALPHA_THRESHOLD).then_some([r, g, b])
+}
+
+/// Render `img` as rows of half-block characters coloured with `mode`
+///
+/// Every row ends with an SGR reset so the terminal colours never leak.
+pub fn image_to_blocks(img: &RgbaImage, mode: ColorMode) -> String {
+    let (width, height) = img.dimensions();
+    let mut out = String::new();
+
+    for y in (0..height).step_by(2) {
+        // Active (foreground, background) parameters, to avoid repeating them
+        let mut fg: Option<String> = None;
+        let mut bg: Option<String> = None;
+
+        for x in 0..width {
+            let upper = pixel(img, x, y);
+            let lower = pixel(img, x, y + 1);
+
+            let (glyph, want_fg, want_bg) = match (upper, lower) {
+                (None, None) => (' ', None, None),
+                (Some(u), None) => ('▀', Some(mode.sgr(u, false)), None),
+                (None, Some(l)) => ('▄', Some(mode.sgr(l, false)), None),
+                (Some(u), Some(l)) => {
+                    let (u_fg, l_bg) = (mode.sgr(u, false), mode.sgr(l, true));
+                    if u_fg == mode.sgr(l, false) {
+                        ('█', Some(u_fg), None)
+                    } else {
+                        ('▀', Some(u_fg), Some(l_bg))
+                    }
+                }
+            };
+
+            // A blank cell only needs the background cleared
+            let want_fg = if glyph == ' ' { fg.clone() } else { want_fg };
+            if want_bg != bg {
+                match &want_bg {
+                    Some(code) => write!(out, "\x1b[{}m", code).unwrap(),
+                    None => out.push_str("\x1b[49m"),
+                }
+                bg = want_bg;
+            }
+            if want_fg != fg {
+                if let Some(code) = &want_fg {
+                    write!(out, "\x1b[{}m", code).unwrap();
+                }
+                fg = want_fg;
+            }
+            out.push(glyph);
+        }
+
+        out.push_str("\x1b[0m");
+        if y + 2 < height {
+            out.push('\n');
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_detect_from_env() {
+        assert_eq!(
+            ColorMode::from_env(Some("truecolor"), Some("xterm")),
+            Some(ColorMode::TrueColor)
+        );
+        assert_eq!(
+            ColorMode::from_env(None, Some("xterm-256color")),
+            Some(ColorMode::Ansi256)
+        );
+        assert_eq!(
+            ColorMode::from_env(None, Some("xterm")),
+            Some(ColorMode::Ansi16)
+        );
+        assert_eq!(ColorMode::from_env(None, Some("dumb")), None);
+        assert_eq!(ColorMode::from_env(None, None), None);
+    }
+
+    #[test]
+    fn test_palette_mapping() {
+        assert_eq!(ansi256([255, 0, 0]), 196);
+        assert_eq!(ansi256([0, 0, 0]), 16);
+        assert_eq!(ansi256([128, 128, 128]), 244);
+        assert_eq!(ansi256_rgb(196), [255, 0, 0]);
+        assert_eq!(ansi256_rgb(244), [128, 128, 128]);
+        assert_eq!(ColorMode::Ansi16.sgr([250, 10, 10], false), "91");
+        assert_eq!(ColorMode::Ansi16.sgr([0, 0, 230], true), "44");
+    }
+
+    #[test]
+    fn test_half_blocks() {
+        // Column 0: red over blue; column 1: green over transparent;
+        // column 2: transparent; column 3: green over green
+        let mut img = RgbaImage::new(4, 2);
+        img.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        img.put_pixel(0, 1, Rgba([0, 0, 255, 255]));
+        img.put_pixel(1, 0, Rgba([0, 255, 0, 255]));
+        img.put_pixel(3, 0, Rgba([0, 255, 0, 255]));
+        img.put_pixel(3, 1, Rgba([0, 255, 0, 255]));
+
+        assert_eq!(
+            image_to_blocks(&img, ColorMode::TrueColor),
+            "\x1b[48;2;0;0;255m\x1b[38;2;255;0;0m▀\
+             \x1b[49m\x1b[38;2;0;255;0m▀ █\x1b[0m"
+        );
+        assert_eq!(
+            image_to_blocks(&img, ColorMode::Ansi256),
+            "\x1b[48;5;21m\x1b[38;5;196m▀\x1b[49m\x1b[38;5;46m▀ █\x1b[0m"
+        );
+    }
+}
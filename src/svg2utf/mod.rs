@@ -0,0 +1,704 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use resvg::usvg::FitTo;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::Command;
+
+mod color;
+mod protocol;
+mod symbols;
+
+pub use color::ColorMode;
+pub use protocol::Protocol;
+pub use symbols::{Dither, Symbols};
+
+// Unicode block elements for better visual representation
+const BLOCKS: [char; 9] = [
+    ' ', // 0/8: No block
+    '▁', // 1/8: Lower one eighth block
+    '▂', // 2/8: Lower one quarter block
+    '▄', // 3/8: Lower three eighths block
+    '▅', // 4/8: Lower half block
+    '▆', // 5/8: Lower five eighths block
+    '▇', // 6/8: Lower three quarters block
+    '█', // 7/8: Lower seven eighths block
+    '█', // 8/8: Full block
+];
+
+// Dimensions used when the terminal size is unknown
+const WIDTH: u32 = 40;
+const HEIGHT: u32 = 20;
+
+// Cell size assumed when the terminal does not report its pixel size
+const CELL_WIDTH_PX: u32 = 8;
+const CELL_HEIGHT_PX: u32 = 16;
+
+// Target size for mini icon rendering (16x16 pixels)
+const MINI_ICON_SIZE: u32 = 16;
+
+/// How an SVG is scaled into the cells it is given
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Fit {
+    /// Scale to fit inside the area, keeping the aspect ratio
+    #[default]
+    Contain,
+    /// Scale to fill the area, keeping the aspect ratio and cropping the overflow
+    Cover,
+    /// Stretch to exactly the area
+    Fill,
+    /// Natural size, cropped to the area
+    Original,
+}
+
+/// How `render_svg_terminal` sizes and draws an SVG
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderOptions {
+    /// Columns to use (default: terminal width)
+    pub width: Option<u32>,
+    /// Rows to use (default: terminal height)
+    pub height: Option<u32>,
+    pub fit: Fit,
+    pub protocol: Protocol,
+    pub symbols: Symbols,
+    pub dither: Dither,
+}
+
+/// The terminal area a drawing may cover
+#[derive(Debug, Clone, Copy)]
+struct Canvas {
+    columns: u32,
+    rows: u32,
+    /// Pixel size of one cell, which sets the aspect ratio of block characters
+    cell: (u32, u32),
+}
+
+impl Canvas {
+    /// The area requested in `options`, defaulting to the terminal size
+    fn new(options: &RenderOptions) -> Self {
+        let terminal = terminal_size::terminal_size()
+            // Keep the blank line before the drawing and the prompt after it visible
+            .map(|(w, h)| (w.0 as u32, (h.0 as u32).saturating_sub(2)));
+        Self {
+            columns: options
+                .width
+                .or(terminal.map(|t| t.0))
+                .unwrap_or(WIDTH)
+                .max(1),
+            rows: options
+                .height
+                .or(terminal.map(|t| t.1))
+                .unwrap_or(HEIGHT)
+                .max(1),
+            cell: cell_size(),
+        }
+    }
+
+    /// Size of the canvas in pixels
+    fn pixels(&self) -> (f64, f64) {
+        (
+            (self.columns * self.cell.0) as f64,
+            (self.rows * self.cell.1) as f64,
+        )
+    }
+
+    /// Pixel size of one of the sub-cell pixels drawn with `symbols`
+    fn glyph_pixel(&self, symbols: Symbols) -> (f64, f64) {
+        let (columns, rows) = symbols.cell_pixels();
+        (
+            self.cell.0 as f64 / columns as f64,
+            self.cell.1 as f64 / rows as f64,
+        )
+    }
+}
+
+/// Scale, offset and resulting size, in pixels, of an SVG placed in an area
+#[derive(Debug, PartialEq)]
+struct Placement {
+    scale: (f64, f64),
+    offset: (f64, f64),
+    size: (f64, f64),
+}
+
+impl Placement {
+    fn new(svg: (f64, f64), area: (f64, f64), fit: Fit) -> Self {
+        let (fit_x, fit_y) = (area.0 / svg.0, area.1 / svg.1);
+        match fit {
+            Fit::Contain => {
+                let scale = fit_x.min(fit_y);
+                Self {
+                    scale: (scale, scale),
+                    offset: (0.0, 0.0),
+                    size: (svg.0 * scale, svg.1 * scale),
+                }
+            }
+            Fit::Cover => {
+                let scale = fit_x.max(fit_y);
+                Self {
+                    scale: (scale, scale),
+                    // Centre the SVG so the overflow is cropped evenly
+                    offset: (
+                        (area.0 - svg.0 * scale) / 2.0,
+                        (area.1 - svg.1 * scale) / 2.0,
+                    ),
+                    size: area,
+                }
+            }
+            Fit::Fill => Self {
+                scale: (fit_x, fit_y),
+                offset: (0.0, 0.0),
+                size: area,
+            },
+            Fit::Original => Self {
+                scale: (1.0, 1.0),
+                // Like Cover, an oversized SVG is cropped evenly on both sides
+                offset: (
+                    (area.0 - svg.0).min(0.0) / 2.0,
+                    (area.1 - svg.1).min(0.0) / 2.0,
+                ),
+                size: (svg.0.min(area.0), svg.1.min(area.1)),
+            },
+        }
+    }
+}
+
+/// Rasterizes an SVG placed in `area` pixels according to `fit`
+///
+/// Each image pixel covers `pixel` screen pixels, which lets block characters
+/// (taller than wide) keep the SVG's proportions. The returned image has
+/// straight (not premultiplied) alpha.
+fn rasterize(svg_path: &Path, area: (f64, f64), pixel: (f64, f64), fit: Fit) -> Result<RgbaImage> {
+    // Read the SVG file
+    let svg_data = std::fs::read(svg_path)
+        .with_context(|| format!("Failed to read SVG file: {}", svg_path.display()))?;
+
+    // Parse the SVG
+    let opt = usvg::Options::default();
+    let rtree = usvg::Tree::from_data(&svg_data, &opt)
+        .with_context(|| format!("Failed to parse SVG: {}", svg_path.display()))?;
+
+    let svg_size = (rtree.size.width(), rtree.size.height());
+    let placement = Placement::new(svg_size, area, fit);
+    let width = (placement.size.0 / pixel.0).round().max(1.0) as u32;
+    let height = (placement.size.1 / pixel.1).round().max(1.0) as u32;
+
+    // Create a pixmap to render into
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| anyhow::anyhow!("Failed to create pixmap"))?;
+
+    // Render the SVG scaled and shifted into place, in image pixels
+    let transform = tiny_skia::Transform::from_row(
+        (placement.scale.0 / pixel.0) as f32,
+        0.0,
+        0.0,
+        (placement.scale.1 / pixel.1) as f32,
+        (placement.offset.0 / pixel.0) as f32,
+        (placement.offset.1 / pixel.1) as f32,
+    );
+    resvg::render(&rtree, FitTo::Original, transform, pixmap.as_mut());
+
+    pixmap_to_image(&pixmap)
+}
+
+/// Converts a rendered pixmap to an image with straight (not premultiplied) alpha
+///
+/// tiny-skia stores premultiplied colours; undoing that keeps edges their hue.
+pub(crate) fn pixmap_to_image(pixmap: &tiny_skia::Pixmap) -> Result<RgbaImage> {
+    let data = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect();
+    RgbaImage::from_raw(pixmap.width(), pixmap.height(), data)
+        .ok_or_else(|| anyhow::anyhow!("Failed to create image from pixmap"))
+}
+
+/// Renders an SVG centred in a `size` x `size` square, transparent unless a `background` is given
+///
+/// This is the pipeline behind mini icons and `sview icons`.
+pub(crate) fn render_square(
+    svg_path: &Path,
+    size: u32,
+    background: Option<[u8; 4]>,
+) -> Result<RgbaImage> {
+    // Read the SVG file
+    let svg_data = fs::read(svg_path)
+        .with_context(|| format!("Failed to read SVG file: {}", svg_path.display()))?;
+
+    // Parse the SVG
+    let opt = usvg::Options::default();
+    let rtree = usvg::Tree::from_data(&svg_data, &opt)
+        .with_context(|| format!("Failed to parse SVG: {}", svg_path.display()))?;
+
+    let mut pixmap = tiny_skia::Pixmap::new(size, size)
+        .ok_or_else(|| anyhow::anyhow!("Failed to create pixmap"))?;
+    if let Some([r, g, b, a]) = background {
+        pixmap.fill(tiny_skia::Color::from_rgba8(r, g, b, a));
+    }
+
+    // Scale the SVG to fit, keeping a margin of 1/64 of the side so edge strokes are not clipped
+    let svg_width = rtree.size.width() as f32;
+    let svg_height = rtree.size.height() as f32;
+    let side = size as f32;
+    let scale = (side - side / 32.0) / svg_width.max(svg_height);
+
+    // Center the SVG in the pixmap
+    let dx = (side - svg_width * scale) / 2.0;
+    let dy = (side - svg_height * scale) / 2.0;
+
+    let transform = tiny_skia::Transform::from_scale(scale, scale).post_translate(dx, dy);
+    resvg::render(&rtree, FitTo::Original, transform, pixmap.as_mut());
+    pixmap_to_image(&pixmap)
+}
+
+/// Renders an SVG to ASCII art using resvg and image processing
+fn render_svg_to_ascii(svg_path: &Path, canvas: &Canvas, fit: Fit) -> Result<String> {
+    let img = rasterize(
+        svg_path,
+        canvas.pixels(),
+        canvas.glyph_pixel(Symbols::Blocks),
+        fit,
+    )?;
+
+    // Convert to grayscale and then to block characters
+    let img = DynamicImage::ImageRgba8(img).grayscale();
+    let ascii = image_to_ascii(&img);
+    Ok(ascii)
+}
+
+/// Renders an SVG as coloured half blocks, one pixel per half cell
+fn render_svg_to_color(
+    svg_path: &Path,
+    canvas: &Canvas,
+    fit: Fit,
+    mode: ColorMode,
+) -> Result<String> {
+    let img = rasterize(
+        svg_path,
+        canvas.pixels(),
+        canvas.glyph_pixel(Symbols::Blocks),
+        fit,
+    )?;
+    Ok(color::image_to_blocks(&img, mode))
+}
+
+/// Renders an SVG with sub-cell `symbols` such as Braille dots
+fn render_svg_to_symbols(
+    svg_path: &Path,
+    canvas: &Canvas,
+    options: &RenderOptions,
+) -> Result<String> {
+    let pixel = canvas.glyph_pixel(options.symbols);
+    let img = rasterize(svg_path, canvas.pixels(), pixel, options.fit)?;
+    let color = ColorMode::detect().filter(|_| options.symbols != Symbols::Ascii);
+    Ok(symbols::image_to_symbols(
+        &img,
+        options.symbols,
+        options.dither,
+        color,
+    ))
+}
+
+/// Pixel size of one terminal cell, as reported by the terminal or assumed
+fn cell_size() -> (u32, u32) {
+    match crossterm::terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => (
+            (size.width / size.columns).max(1) as u32,
+            (size.height / size.rows).max(1) as u32,
+        ),
+        _ => (CELL_WIDTH_PX, CELL_HEIGHT_PX),
+    }
+}
+
+/// Renders an SVG as a pixel image for a graphics `protocol`
+fn render_svg_to_graphics(
+    svg_path: &Path,
+    canvas: &Canvas,
+    fit: Fit,
+    protocol: Protocol,
+) -> Result<String> {
+    let img = rasterize(svg_path, canvas.pixels(), (1.0, 1.0), fit)?;
+    let (cell_width, cell_height) = canvas.cell;
+    let columns = (img.width() + cell_width - 1) / cell_width;
+    let rows = (img.height() + cell_height - 1) / cell_height;
+
+    match protocol {
+        Protocol::Kitty => protocol::kitty(&img, columns, rows),
+        Protocol::Iterm2 => protocol::iterm2(&img, columns),
+        Protocol::Sixel => Ok(protocol::sixel(&img)),
+        Protocol::Auto | Protocol::Blocks => Err(anyhow::anyhow!("Not a graphics protocol")),
+    }
+}
+
+/// Converts an image to Unicode block characters
+fn image_to_ascii(img: &DynamicImage) -> String {
+    let (width, height) = img.dimensions();
+    let img = img.to_luma8();
+    let mut result = String::with_capacity((width * height) as usize);
+
+    // Each output row covers two pixel rows (upper and lower half)
+    for y in (0..height).step_by(2) {
+        for x in 0..width {
+            // Get the upper and lower pixels (or just the upper if we're at the last row)
+            let upper = img.get_pixel(x, y).0[0] as f32 / 255.0;
+            let lower = if y + 1 < height {
+                img.get_pixel(x, y + 1).0[0] as f32 / 255.0
+            } else {
+                0.0 // Empty for odd number of rows
+            };
+
+            // Map brightness to block characters
+            let upper_idx = (upper * 8.0).round() as usize;
+            let lower_idx = (lower * 8.0).round() as usize;
+
+            // Use different block characters based on upper and lower brightness
+            let block = match (upper_idx, lower_idx) {
+                (0, 0) => ' ',                        // Empty
+                (8, 8) => '█',                        // Full block
+                (u, 0) => BLOCKS[u.min(8)],           // Upper half block
+                (0, l) => BLOCKS[l.min(8)],           // Lower half block
+                (u, l) if u == l => BLOCKS[u.min(8)], // Same brightness
+                (u, l) if u > l => '▀',               // Upper half block with lower half dot
+                _ => '▄',                             // Lower half block with upper half dot
+            };
+
+            result.push(block);
+        }
+
+        if y + 2 < height {
+            result.push('\n');
+        }
+    }
+
+    result
+}
+
+/// Renders an SVG to terminal with visual representation
+///
+/// Pixel graphics are drawn with the chosen protocol; block characters are the fallback.
+pub fn render_svg_terminal(svg_path: &Path, options: &RenderOptions) -> Result<()> {
+    let canvas = Canvas::new(options);
+    // Asking for particular symbols means characters, unless a protocol is forced
+    let protocol = match options.protocol {
+        Protocol::Auto if options.symbols != Symbols::Blocks => Protocol::Blocks,
+        protocol => protocol.resolve(),
+    };
+    if protocol != Protocol::Blocks {
+        match render_svg_to_graphics(svg_path, &canvas, options.fit, protocol) {
+            Ok(image) => {
+                writeln!(io::stdout(), "{}", image)?;
+                return Ok(());
+            }
+            Err(e) => eprintln!("Warning: falling back to block characters: {:#}", e),
+        }
+    }
+
+    if options.symbols != Symbols::Blocks {
+        let text = render_svg_to_symbols(svg_path, &canvas, options)?;
+        writeln!(io::stdout(), "\n{}", text)?;
+        return Ok(());
+    }
+
+    // Try to use chafa first if available; it cannot crop, so only for contain and fill
+    let chafa_fits = matches!(options.fit, Fit::Contain | Fit::Fill);
+    if let Ok(output) = Command::new("chafa").arg("--version").output() {
+        if output.status.success() && chafa_fits {
+            // Use chafa for better SVG rendering
+            let mut chafa = Command::new("chafa");
+            if options.fit == Fit::Fill {
+                chafa.arg("--stretch");
+            }
+            let status = chafa
+                .arg(format!("--size={}x{}", canvas.columns, canvas.rows))
+                .arg("--symbols=block")
+                .arg("--colors=full")
+                .arg("--color-space=rgb")
+                .arg(svg_path)
+                .status()
+                .with_context(|| format!("Failed to execute chafa on {}", svg_path.display()))?;
+
+            if status.success() {
+                return Ok(());
+            }
+        }
+    }
+
+    // Fallback to our Rust-based renderer, in colour if the terminal has any
+    if let Some(mode) = ColorMode::detect() {
+        if let Ok(blocks) = render_svg_to_color(svg_path, &canvas, options.fit, mode) {
+            writeln!(io::stdout(), "\n{}", blocks)?;
+            return Ok(());
+        }
+    }
+    if let Ok(ascii) = render_svg_to_ascii(svg_path, &canvas, options.fit) {
+        writeln!(io::stdout(), "\n{}", ascii)?;
+        return Ok(());
+    }
+
+    // If all else fails, use a simple icon
+    let icon = get_simple_icon(svg_path).unwrap_or("🖼️ ");
+    writeln!(io::stdout(), "\n{}", icon)?;
+    Ok(())
+}
+
+/// Converts an SVG to a single-character mini icon by rendering to a 16x16 bitmap first
+pub(crate) fn svg_to_mini_icon(svg_path: &Path) -> Result<char> {
+    // Try to render a detailed mini icon first
+    if let Ok(icon) = render_svg_to_mini_icon(svg_path) {
+        return Ok(icon);
+    }
+
+    // Fallback to simple icon if detailed rendering fails
+    get_simple_icon(svg_path).map(|s| s.chars().next().unwrap_or('▦'))
+}
+
+/// Renders an SVG to a single character by first rendering to a 16x16 bitmap
+fn render_svg_to_mini_icon(svg_path: &Path) -> Result<char> {
+    // Render at 4x resolution for better quality
+    let scale_factor = 4;
+    let img = render_square(svg_path, MINI_ICON_SIZE * scale_factor, None)?;
+    Ok(image_to_mini_icon(img))
+}
+
+/// Picks the mini icon character for a square rendering (see [`render_square`])
+pub(crate) fn image_to_mini_icon(img: RgbaImage) -> char {
+    // Downscale the image to 16x16 using high-quality filtering
+    let img = image::DynamicImage::ImageRgba8(img);
+    let img = img.resize_exact(
+        MINI_ICON_SIZE,
+        MINI_ICON_SIZE,
+        image::imageops::FilterType::Lanczos3,
+    );
+
+    // Convert to 2x2 character blocks
+    let (width, height) = img.dimensions();
+    let luma_img = img.to_luma8();
+
+    // Calculate average brightness for each 2x2 block
+    let mut blocks = Vec::with_capacity((width * height / 4) as usize);
+
+    for y in (0..height).step_by(2) {
+        for x in (0..width).step_by(2) {
+            let mut total = 0.0;
+            let mut count = 0;
+
+            // Sample 2x2 block
+            for dy in 0..2 {
+                for dx in 0..2 {
+                    if x + dx < width && y + dy < height {
+                        let pixel = luma_img.get_pixel(x + dx, y + dy);
+                        total += pixel.0[0] as f32 / 255.0;
+                        count += 1;
+                    }
+                }
+            }
+
+            let avg = if count > 0 { total / count as f32 } else { 0.0 };
+            blocks.push(avg);
+        }
+    }
+
+    // If we have no blocks, return a space
+    if blocks.is_empty() {
+        return ' ';
+    }
+
+    // Calculate average brightness of the entire image
+    let avg_brightness: f32 = blocks.iter().sum::<f32>() / blocks.len() as f32;
+
+    // Calculate contrast (standard deviation of brightness)
+    let variance = blocks
+        .iter()
+        .map(|&b| (b - avg_brightness).powi(2))
+        .sum::<f32>()
+        / blocks.len() as f32;
+    let contrast = variance.sqrt();
+
+    // Select character set based on contrast
+    let char_set = if contrast > 0.3 {
+        // High contrast - use detailed blocks
+        [' ', '░', '▒', '▓', '█', '▄', '▀', '▌', '▐']
+    } else if contrast > 0.15 {
+        // Medium contrast - use simpler blocks
+        [' ', '░', '▒', '▓', '█', '▄', '▀', '▌', '▐']
+    } else {
+        // Low contrast - use minimal blocks
+        [' ', '░', '▒', '▓', '█', '▄', '▀', '▌', '▐']
+    };
+
+    // Map average brightness to character
+    let index = (avg_brightness * (char_set.len() - 1) as f32).round() as usize;
+    let selected_char = char_set.get(index).copied().unwrap_or('▦');
+
+    // Special case for very bright or dark images
+    if avg_brightness < 0.1 {
+        return ' '; // Very dark
+    } else if avg_brightness > 0.9 {
+        return '█'; // Very bright
+    }
+
+    selected_char
+}
+
+/// Gets a simple character representation based on SVG content
+///
+/// # Errors
+/// Returns an error if the file doesn't exist or can't be read
+pub fn get_simple_icon(svg_path: &Path) -> Result<&'static str> {
+    // First, check if file exists and is accessible
+    if !svg_path.exists() {
+        return Err(anyhow::anyhow!(
+            "File does not exist: {}",
+            svg_path.display()
+        ));
+    }
+
+    // First, try to determine from filename
+    if let Some(stem) = svg_path.file_stem() {
+        let name = stem.to_string_lossy().to_lowercase();
+
+        // Common SVG icon names
+        if name.contains("logo") || name.contains("brand") {
+            return Ok("🆒");
+        } else if name.contains("icon") {
+            return Ok("🛠️");
+        } else if name.contains("diagram") || name.contains("chart") {
+            return Ok("📊");
+        } else if name.contains("graph") {
+            return Ok("📈");
+        } else if name.contains("user") || name.contains("profile") {
+            return Ok("👤");
+        } else if name.contains("home") {
+            return Ok("🏠");
+        } else if name.contains("folder") || name.contains("directory") {
+            return Ok("📁");
+        } else if name.contains("document") || name.contains("file") {
+            return Ok("📄");
+        } else if name.contains("image") || name.contains("picture") || name.contains("photo") {
+            return Ok("🖼️");
+        } else if name.contains("download") || name.contains("upload") {
+            return Ok("📥");
+        } else if name.contains("settings") || name.contains("preferences") {
+            return Ok("⚙️");
+        } else if name.contains("search") || name.contains("find") {
+            return Ok("🔍");
+        }
+    }
+
+    // If filename doesn't give enough info, check the content
+    let content = std::fs::read_to_string(svg_path)
+        .with_context(|| format!("Failed to read file: {}", svg_path.display()))?;
+
+    // Convert to lowercase for case-insensitive matching
+    let content_lower = content.to_lowercase();
+
+    // Check for SVG content
+    if !content_lower.contains("<svg") {
+        return Ok("❓");
+    }
+
+    // Try to determine the type of SVG by its content
+    if content_lower.contains("<circle") || content_lower.contains("<ellipse") {
+        Ok("⭕")
+    } else if content_lower.contains("<rect") || content_lower.contains("<polygon") {
+        Ok("⬜")
+    } else if content_lower.contains("<path") {
+        Ok("🔷")
+    } else if content_lower.contains("<text") || content_lower.contains("<tspan") {
+        Ok("🔤")
+    } else if content_lower.contains("<image") {
+        Ok("🖼️ ")
+    } else {
+        // Default icon for other SVG content
+        Ok("🖱️ ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_get_simple_icon() {
+        // Test with non-existent file (should return an error)
+        let result = get_simple_icon(Path::new("/nonexistent/file.svg"));
+        assert!(result.is_err());
+
+        // Test with a test SVG file (mocked for the test)
+        let test_svg = "<svg><rect/></svg>";
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(temp_file.path(), test_svg).unwrap();
+
+        let result = get_simple_icon(temp_file.path()).unwrap();
+        assert_eq!(result, "⬜");
+    }
+
+    #[test]
+    fn test_placement() {
+        let svg = (200.0, 100.0);
+        let area = (100.0, 100.0);
+
+        let contain = Placement::new(svg, area, Fit::Contain);
+        assert_eq!(contain.scale, (0.5, 0.5));
+        assert_eq!(contain.size, (100.0, 50.0));
+
+        let cover = Placement::new(svg, area, Fit::Cover);
+        assert_eq!(cover.scale, (1.0, 1.0));
+        assert_eq!(cover.offset, (-50.0, 0.0));
+        assert_eq!(cover.size, area);
+
+        let fill = Placement::new(svg, area, Fit::Fill);
+        assert_eq!(fill.scale, (0.5, 1.0));
+        assert_eq!(fill.size, area);
+
+        let original = Placement::new(svg, area, Fit::Original);
+        assert_eq!(original.scale, (1.0, 1.0));
+        assert_eq!(original.offset, (-50.0, 0.0));
+        assert_eq!(original.size, (100.0, 100.0));
+
+        // A smaller SVG keeps its own size and is not shifted
+        let small = Placement::new((40.0, 20.0), area, Fit::Original);
+        assert_eq!(small.offset, (0.0, 0.0));
+        assert_eq!(small.size, (40.0, 20.0));
+    }
+
+    #[test]
+    fn test_rasterize_keeps_aspect_in_half_blocks() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            temp_file.path(),
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100"><rect width="200" height="100"/></svg>"#,
+        )
+        .unwrap();
+
+        // 40x20 cells of 8x16 pixels; half blocks are 8x8 pixels
+        let canvas = Canvas {
+            columns: 40,
+            rows: 20,
+            cell: (8, 16),
+        };
+        let img = rasterize(
+            temp_file.path(),
+            canvas.pixels(),
+            canvas.glyph_pixel(Symbols::Blocks),
+            Fit::Contain,
+        )
+        .unwrap();
+        assert_eq!(img.dimensions(), (40, 20));
+        assert_eq!(
+            image_to_ascii(&DynamicImage::ImageRgba8(img))
+                .lines()
+                .count(),
+            10
+        );
+
+        let img = rasterize(temp_file.path(), canvas.pixels(), (1.0, 1.0), Fit::Fill).unwrap();
+        assert_eq!(img.dimensions(), (320, 320));
+    }
+}
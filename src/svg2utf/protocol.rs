@@ -0,0 +1,288 @@
+//! Pixel graphics for terminals that can display images
+//!
+//! Each encoder takes an already rasterized image and returns the escape
+//! sequence that draws it at the cursor.
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::ValueEnum;
+use image::{codecs::png::PngEncoder, ColorType, ImageEncoder, RgbaImage};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::io::IsTerminal;
+
+use super::color::{ansi256, ansi256_rgb};
+
+/// Largest payload the Kitty protocol accepts in one escape sequence
+const KITTY_CHUNK: usize = 4096;
+
+/// Pixels less opaque than this are left unpainted in Sixel output
+const SIXEL_ALPHA_THRESHOLD: u8 = 128;
+
+/// How `view` draws an SVG in the terminal
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Protocol {
+    /// Pick the best protocol the terminal advertises
+    #[default]
+    Auto,
+    /// Kitty graphics protocol (kitty, Ghostty)
+    Kitty,
+    /// iTerm2 inline images (iTerm2, WezTerm)
+    Iterm2,
+    /// DEC Sixel graphics (foot, mlterm, xterm -ti vt340)
+    Sixel,
+    /// Unicode block characters
+    Blocks,
+}
+
+impl Protocol {
+    /// The protocol to use on the current stdout, resolving [`Protocol::Auto`]
+    pub fn resolve(self) -> Self {
+        match self {
+            Protocol::Auto if !std::io::stdout().is_terminal() => Protocol::Blocks,
+            Protocol::Auto => Self::from_env(|name| std::env::var(name).ok()),
+            protocol => protocol,
+        }
+    }
+
+    /// Recognise the terminal from its environment variables
+    fn from_env(var: impl Fn(&str) -> Option<String>) -> Self {
+        // Multiplexers swallow image sequences unless they are wrapped for passthrough
+        if var("TMUX").is_some() || var("STY").is_some() {
+            return Protocol::Blocks;
+        }
+
+        let term = var("TERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+        if var("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty" || term == "xterm-ghostty" {
+            Protocol::Kitty
+        } else if program == "iTerm.app"
+            || program == "WezTerm"
+            || var("LC_TERMINAL").as_deref() == Some("iTerm2")
+        {
+            Protocol::Iterm2
+        } else if term.contains("sixel")
+            || term.starts_with("foot")
+            || term.starts_with("mlterm")
+            || program == "mlterm"
+        {
+            Protocol::Sixel
+        } else {
+            Protocol::Blocks
+        }
+    }
+}
+
+fn encode_png(img: &RgbaImage) -> Result<Vec<u8>> {
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png)
+        .write_image(img.as_raw(), img.width(), img.height(), ColorType::Rgba8)
+        .context("Failed to encode PNG")?;
+    Ok(png)
+}
+
+/// Kitty graphics protocol: a PNG transmitted in chunks and scaled to `columns` x `rows` cells
+pub fn kitty(img: &RgbaImage, columns: u32, rows: u32) -> Result<String> {
+    let data = STANDARD.encode(encode_png(img)?);
+    let chunks: Vec<&str> = data
+        .as_bytes()
+        .chunks(KITTY_CHUNK)
+        .map(|c| std::str::from_utf8(c).unwrap_or_default())
+        .collect();
+
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        if i == 0 {
+            // q=2 keeps the terminal from answering on our stdin
+            write!(
+                out,
+                "\x1b_Gf=100,a=T,q=2,c={},r={},m={};",
+                columns, rows, more
+            )?;
+        } else {
+            write!(out, "\x1b_Gm={};", more)?;
+        }
+        write!(out, "{}\x1b\\", chunk)?;
+    }
+    Ok(out)
+}
+
+/// iTerm2 inline image: a PNG scaled to `columns` cells wide
+pub fn iterm2(img: &RgbaImage, columns: u32) -> Result<String> {
+    let png = encode_png(img)?;
+    Ok(format!(
+        "\x1b]1337;File=inline=1;size={};width={};preserveAspectRatio=1:{}\x07",
+        png.len(),
+        columns,
+        STANDARD.encode(&png)
+    ))
+}
+
+/// Sixel graphics, with colours reduced to the 256-colour palette
+pub fn sixel(img: &RgbaImage) -> String {
+    let (width, height) = img.dimensions();
+    let colors: Vec<Option<u8>> = img
+        .pixels()
+        .map(|p| {
+            let [r, g, b, a] = p.0;
+            (a >= SIXEL_ALPHA_THRESHOLD).then(|| ansi256([r, g, b]))
+        })
+        .collect();
+
+    // P2=1: pixels we do not paint stay transparent
+    let mut out = format!("\x1bP0;1;0q\"1;1;{};{}", width, height);
+    let percent = |v: u8| (v as u32 * 100 + 127) / 255;
+    for index in colors.iter().flatten().collect::<BTreeSet<_>>() {
+        let [r, g, b] = ansi256_rgb(*index);
+        let _ = write!(
+            out,
+            "#{};2;{};{};{}",
+            index,
+            percent(r),
+            percent(g),
+            percent(b)
+        );
+    }
+
+    for band in (0..height).step_by(6) {
+        if band > 0 {
+            out.push('-');
+        }
+        let rows = band..(band + 6).min(height);
+        let in_band: BTreeSet<u8> = rows
+            .clone()
+            .flat_map(|y| &colors[(y * width) as usize..((y + 1) * width) as usize])
+            .flatten()
+            .copied()
+            .collect();
+
+        for (i, index) in in_band.iter().enumerate() {
+            if i > 0 {
+                // Back to the start of the band for the next colour
+                out.push('$');
+            }
+            let _ = write!(out, "#{}", index);
+            let sixels = (0..width).map(|x| {
+                let bits = rows
+                    .clone()
+                    .filter(|&y| colors[(y * width + x) as usize] == Some(*index))
+                    .fold(0u8, |bits, y| bits | 1 << (y - band));
+                (63 + bits) as char
+            });
+            push_runs(&mut out, sixels);
+        }
+    }
+
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Append sixel characters, run-length encoding repeats
+fn push_runs(out: &mut String, sixels: impl Iterator<Item = char>) {
+    let flush = |out: &mut String, ch: char, count: usize| {
+        if count > 3 {
+            let _ = write!(out, "!{}{}", count, ch);
+        } else {
+            out.extend(std::iter::repeat(ch).take(count));
+        }
+    };
+
+    let mut run: Option<(char, usize)> = None;
+    for ch in sixels {
+        run = match run {
+            Some((prev, count)) if prev == ch => Some((prev, count + 1)),
+            Some((prev, count)) => {
+                flush(out, prev, count);
+                Some((ch, 1))
+            }
+            None => Some((ch, 1)),
+        };
+    }
+    if let Some((ch, count)) = run {
+        flush(out, ch, count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn detect(vars: &[(&str, &str)]) -> Protocol {
+        Protocol::from_env(|name| {
+            vars.iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| v.to_string())
+        })
+    }
+
+    #[test]
+    fn test_detect_from_env() {
+        assert_eq!(detect(&[("TERM", "xterm-kitty")]), Protocol::Kitty);
+        assert_eq!(
+            detect(&[("TERM", "xterm-256color"), ("KITTY_WINDOW_ID", "1")]),
+            Protocol::Kitty
+        );
+        assert_eq!(detect(&[("TERM_PROGRAM", "iTerm.app")]), Protocol::Iterm2);
+        assert_eq!(detect(&[("TERM", "foot")]), Protocol::Sixel);
+        assert_eq!(detect(&[("TERM", "xterm-256color")]), Protocol::Blocks);
+        assert_eq!(
+            detect(&[("TERM", "xterm-kitty"), ("TMUX", "/tmp/tmux-0/default")]),
+            Protocol::Blocks
+        );
+    }
+
+    #[test]
+    fn test_kitty_and_iterm2_carry_png() {
+        let img = RgbaImage::from_pixel(4, 4, Rgba([255, 0, 0, 255]));
+
+        let out = kitty(&img, 2, 1).unwrap();
+        let payload = out
+            .strip_prefix("\x1b_Gf=100,a=T,q=2,c=2,r=1,m=0;")
+            .and_then(|rest| rest.strip_suffix("\x1b\\"))
+            .unwrap();
+        assert!(STANDARD.decode(payload).unwrap().starts_with(b"\x89PNG"));
+
+        let out = iterm2(&img, 2).unwrap();
+        assert!(out.starts_with("\x1b]1337;File=inline=1;size="));
+        let payload = out.split_once(':').unwrap().1.strip_suffix('\x07').unwrap();
+        assert!(STANDARD.decode(payload).unwrap().starts_with(b"\x89PNG"));
+    }
+
+    #[test]
+    fn test_kitty_splits_large_payloads() {
+        // Noise does not compress, so the PNG spans several chunks
+        let mut state = 0x2545_f491_u32;
+        let img = RgbaImage::from_fn(64, 64, |_, _| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            Rgba(state.to_le_bytes())
+        });
+        let out = kitty(&img, 8, 4).unwrap();
+        let chunks: Vec<&str> = out.split_terminator("\x1b\\").collect();
+        assert!(chunks.len() > 1);
+        assert!(chunks[0].contains("m=1;"));
+        assert!(chunks[1..chunks.len() - 1]
+            .iter()
+            .all(|c| c.starts_with("\x1b_Gm=1;")));
+        assert!(chunks.last().unwrap().starts_with("\x1b_Gm=0;"));
+    }
+
+    #[test]
+    fn test_sixel_encoding() {
+        // Five red pixels on the top row, one blue below the last, the rest transparent
+        let mut img = RgbaImage::new(5, 7);
+        for x in 0..5 {
+            img.put_pixel(x, 0, Rgba([255, 0, 0, 255]));
+        }
+        img.put_pixel(4, 6, Rgba([0, 0, 255, 255]));
+
+        assert_eq!(
+            sixel(&img),
+            "\x1bP0;1;0q\"1;1;5;7#21;2;0;0;100#196;2;100;0;0\
+             #196!5@-#21!4?@\x1b\\"
+        );
+    }
+}
@@ -0,0 +1,300 @@
+//! Sub-cell character rendering: Braille, quadrant and sextant glyphs
+//!
+//! Each character cell covers a small grid of pixels (2x4 for Braille, 2x2 for
+//! quadrants, 2x3 for sextants). Pixels are reduced to ink or no ink, by how
+//! much they stand out from the background, with an optional dither; this
+//! resolves strokes far thinner than a cell.
+
+use clap::ValueEnum;
+use image::RgbaImage;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::color::ColorMode;
+
+/// Brightness ramp for `--symbols ascii`, from no ink to full ink
+const ASCII_RAMP: &[u8] = b" .:-=+*#%@";
+
+/// Quadrant glyphs indexed by bits: 1 upper left, 2 upper right, 4 lower left, 8 lower right
+const QUADRANTS: [char; 16] = [
+    ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+/// 4x4 Bayer matrix for ordered dithering
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Characters used to draw an SVG
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Symbols {
+    /// Half blocks, two pixels per cell
+    #[default]
+    Blocks,
+    /// Braille dots, 2x4 pixels per cell
+    Braille,
+    /// Quadrant blocks, 2x2 pixels per cell
+    Quadrant,
+    /// Sextant blocks, 2x3 pixels per cell
+    Sextant,
+    /// Plain ASCII shading, one pixel per cell
+    Ascii,
+}
+
+impl Symbols {
+    /// Pixels covered by one character cell (columns, rows)
+    pub(super) fn cell_pixels(self) -> (u32, u32) {
+        match self {
+            Symbols::Blocks => (1, 2),
+            Symbols::Braille => (2, 4),
+            Symbols::Quadrant => (2, 2),
+            Symbols::Sextant => (2, 3),
+            Symbols::Ascii => (1, 1),
+        }
+    }
+
+    /// Number of ink levels a pixel is reduced to
+    fn levels(self) -> usize {
+        match self {
+            Symbols::Ascii => ASCII_RAMP.len(),
+            _ => 2,
+        }
+    }
+
+    /// The glyph for a cell, given the ink level of each of its pixels in row order
+    fn glyph(self, cell: &[u8]) -> char {
+        let bits = |order: &[u32]| {
+            cell.iter()
+                .zip(order)
+                .filter(|(&level, _)| level > 0)
+                .fold(0u32, |bits, (_, &bit)| bits | bit)
+        };
+        match self {
+            Symbols::Blocks => QUADRANTS[bits(&[3, 12]) as usize],
+            // Dots 1-8 in Braille order, row by row; an empty cell is a plain space
+            Symbols::Braille => match bits(&[0x01, 0x08, 0x02, 0x10, 0x04, 0x20, 0x40, 0x80]) {
+                0 => ' ',
+                dots => char::from_u32(0x2800 + dots).unwrap_or(' '),
+            },
+            Symbols::Quadrant => QUADRANTS[bits(&[1, 2, 4, 8]) as usize],
+            Symbols::Sextant => sextant(bits(&[1, 2, 4, 8, 16, 32])),
+            Symbols::Ascii => ASCII_RAMP[cell[0] as usize] as char,
+        }
+    }
+}
+
+/// The sextant glyph for bits 1..32, upper left to lower right in row order
+fn sextant(bits: u32) -> char {
+    match bits {
+        0 => ' ',
+        // These patterns are the existing half and full blocks
+        21 => '▌',
+        42 => '▐',
+        63 => '█',
+        // U+1FB00 onwards lists the others in order, skipping the two halves
+        _ => {
+            let skipped = u32::from(bits > 21) + u32::from(bits > 42);
+            char::from_u32(0x1FB00 + bits - 1 - skipped).unwrap_or(' ')
+        }
+    }
+}
+
+/// How pixel ink is reduced to the levels a glyph can show
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dither {
+    /// Plain threshold, sharpest for line art
+    None,
+    /// Error diffusion, smoothest for shaded areas
+    #[default]
+    FloydSteinberg,
+    /// 4x4 Bayer pattern, stable between redraws
+    Ordered,
+}
+
+impl Dither {
+    /// Quantize `ink` values in 0..=1 (row-major, `width` wide) to `levels` levels
+    fn apply(self, ink: &[f32], width: usize, levels: usize) -> Vec<u8> {
+        let top = (levels - 1) as f32;
+        let quantize = |v: f32| (v * top).round().clamp(0.0, top);
+        match self {
+            Dither::None => ink.iter().map(|&v| quantize(v) as u8).collect(),
+            Dither::Ordered => ink
+                .iter()
+                .enumerate()
+                .map(|(i, &v)| {
+                    let threshold = (BAYER[(i / width) % 4][i % width % 4] as f32 + 0.5) / 16.0;
+                    (v * top + threshold - 0.5).round().clamp(0.0, top) as u8
+                })
+                .collect(),
+            Dither::FloydSteinberg => {
+                let mut ink = ink.to_vec();
+                let mut out = vec![0; ink.len()];
+                for i in 0..ink.len() {
+                    let (x, level) = (i % width, quantize(ink[i]));
+                    out[i] = level as u8;
+                    let error = ink[i] - level / top;
+                    let mut spread = |j: usize, weight: f32| {
+                        if let Some(v) = ink.get_mut(j) {
+                            *v += error * weight;
+                        }
+                    };
+                    if x + 1 < width {
+                        spread(i + 1, 7.0 / 16.0);
+                        spread(i + width + 1, 1.0 / 16.0);
+                    }
+                    if x > 0 {
+                        spread(i + width - 1, 3.0 / 16.0);
+                    }
+                    spread(i + width, 5.0 / 16.0);
+                }
+                out
+            }
+        }
+    }
+}
+
+/// A pixel composited over white paper
+fn over_white(pixel: [u8; 4]) -> [u8; 3] {
+    let [r, g, b, a] = pixel;
+    let alpha = a as u32;
+    [r, g, b].map(|c| ((c as u32 * alpha + 255 * (255 - alpha)) / 255) as u8)
+}
+
+/// The most common colour, taken to be the background the drawing is on
+fn background(pixels: &[[u8; 3]]) -> [u8; 3] {
+    let mut counts = HashMap::new();
+    for rgb in pixels {
+        *counts.entry(*rgb).or_insert(0usize) += 1;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|&(rgb, count)| (count, rgb))
+        .map(|(rgb, _)| rgb)
+        .unwrap_or([255; 3])
+}
+
+/// How far a colour stands out from the background: 0 for background, 1 for full contrast
+///
+/// The square root lifts faint, antialiased strokes so thin lines survive thresholding.
+fn ink(rgb: [u8; 3], background: [u8; 3]) -> f32 {
+    let difference = rgb
+        .iter()
+        .zip(background)
+        .map(|(&c, b)| (c as i32 - b as i32).unsigned_abs())
+        .max()
+        .unwrap_or(0);
+    (difference as f32 / 255.0).sqrt()
+}
+
+/// Render `img` with `symbols`, reducing ink with `dither`
+///
+/// With a colour mode, each glyph takes the average colour of its inked pixels.
+pub(super) fn image_to_symbols(
+    img: &RgbaImage,
+    symbols: Symbols,
+    dither: Dither,
+    color: Option<ColorMode>,
+) -> String {
+    let (cell_w, cell_h) = symbols.cell_pixels();
+    // Pad to whole cells with paper
+    let columns = (img.width() + cell_w - 1) / cell_w;
+    let rows = (img.height() + cell_h - 1) / cell_h;
+    let (width, height) = (columns * cell_w, rows * cell_h);
+
+    let pixels: Vec<[u8; 3]> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| match img.get_pixel_checked(x, y) {
+            Some(p) => over_white(p.0),
+            None => [255; 3],
+        })
+        .collect();
+    let paper = background(&pixels);
+    let ink: Vec<f32> = pixels.iter().map(|&rgb| ink(rgb, paper)).collect();
+    let levels = dither.apply(&ink, width as usize, symbols.levels());
+
+    let mut out = String::new();
+    for row in 0..rows {
+        let mut fg: Option<String> = None;
+        for column in 0..columns {
+            let indices: Vec<usize> = (0..cell_h)
+                .flat_map(|dy| (0..cell_w).map(move |dx| (dx, dy)))
+                .map(|(dx, dy)| ((row * cell_h + dy) * width + column * cell_w + dx) as usize)
+                .collect();
+            let cell: Vec<u8> = indices.iter().map(|&i| levels[i]).collect();
+            let glyph = symbols.glyph(&cell);
+
+            if let (Some(mode), true) = (color, glyph != ' ') {
+                let inked: Vec<[u8; 3]> = indices
+                    .iter()
+                    .filter(|&&i| levels[i] > 0)
+                    .map(|&i| pixels[i])
+                    .collect();
+                let count = inked.len().max(1) as u32;
+                let average = [0, 1, 2]
+                    .map(|c| (inked.iter().map(|rgb| rgb[c] as u32).sum::<u32>() / count) as u8);
+                let code = mode.sgr(average, false);
+                if fg.as_ref() != Some(&code) {
+                    let _ = write!(out, "\x1b[{}m", code);
+                    fg = Some(code);
+                }
+            }
+            out.push(glyph);
+        }
+        if fg.is_some() {
+            out.push_str("\x1b[0m");
+        }
+        if row + 1 < rows {
+            out.push('\n');
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_glyphs() {
+        assert_eq!(Symbols::Braille.glyph(&[0; 8]), ' ');
+        assert_eq!(Symbols::Braille.glyph(&[1, 0, 0, 0, 0, 0, 0, 1]), '⢁');
+        assert_eq!(Symbols::Braille.glyph(&[1; 8]), '⣿');
+        assert_eq!(Symbols::Quadrant.glyph(&[1, 0, 0, 1]), '▚');
+        assert_eq!(Symbols::Sextant.glyph(&[1, 0, 0, 0, 0, 0]), '\u{1FB00}');
+        assert_eq!(Symbols::Sextant.glyph(&[1, 0, 1, 0, 1, 0]), '▌');
+        assert_eq!(Symbols::Sextant.glyph(&[0, 1, 1, 0, 1, 0]), '\u{1FB14}');
+        assert_eq!(Symbols::Sextant.glyph(&[0, 1, 1, 1, 1, 1]), '\u{1FB3B}');
+        assert_eq!(Symbols::Ascii.glyph(&[9]), '@');
+    }
+
+    #[test]
+    fn test_dithering_preserves_average_ink() {
+        let grey = vec![0.25; 64];
+        for dither in [Dither::FloydSteinberg, Dither::Ordered] {
+            let inked = dither.apply(&grey, 8, 2).iter().filter(|&&l| l > 0).count();
+            assert!((14..=18).contains(&inked), "{:?} inked {}", dither, inked);
+        }
+        assert!(Dither::None.apply(&grey, 8, 2).iter().all(|&l| l == 0));
+    }
+
+    #[test]
+    fn test_thin_line_in_braille() {
+        // A one pixel vertical black line on transparent paper
+        let mut img = RgbaImage::new(4, 4);
+        for y in 0..4 {
+            img.put_pixel(1, y, Rgba([0, 0, 0, 255]));
+        }
+        assert_eq!(
+            image_to_symbols(&img, Symbols::Braille, Dither::None, None),
+            "⢸ "
+        );
+        assert_eq!(
+            image_to_symbols(
+                &img,
+                Symbols::Braille,
+                Dither::None,
+                Some(ColorMode::TrueColor)
+            ),
+            "\x1b[38;2;0;0;0m⢸ \x1b[0m"
+        );
+    }
+}
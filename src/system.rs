@@ -0,0 +1,602 @@
+//! Diagnostics, environment checks and cache cleanup behind `sview system`
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use sysinfo::{CpuExt, System, SystemExt};
+
+use crate::browser::{self, Invocation};
+use crate::index::ScanIndex;
+use crate::memory::{crypto::EncryptedFile, MemoryStore};
+use crate::runtime::RUNTIMES;
+use crate::settings::{BrowserSettings, ConfigLayers, Settings};
+use crate::svg2utf::{ColorMode, Protocol};
+use crate::thumbnail::ThumbnailCache;
+
+/// Everything `system info` reports, serialized as is for `--json`
+#[derive(Debug, Serialize)]
+pub struct SystemInfo {
+    pub version: &'static str,
+    pub os: String,
+    pub arch: &'static str,
+    pub home_dir: Option<PathBuf>,
+    /// Cargo features this binary was built with
+    pub features: Vec<&'static str>,
+    pub config: ConfigInfo,
+    pub cache: CacheInfo,
+    pub memory: MemoryInfo,
+    pub terminal: TerminalInfo,
+    pub hardware: HardwareInfo,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfigInfo {
+    /// Layers in effect, lowest precedence first
+    pub sources: Vec<String>,
+    /// Where a user configuration file is looked for
+    pub user_file: Option<PathBuf>,
+    pub valid: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheInfo {
+    pub dir: PathBuf,
+    pub total_bytes: u64,
+    pub thumbnails: usize,
+    pub thumbnail_bytes: u64,
+    /// Size of the scan index, if one has been built
+    pub index_bytes: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemoryInfo {
+    pub path: PathBuf,
+    pub bytes: Option<u64>,
+    pub encrypted: bool,
+    /// Entries per memory type; unknown while the store is encrypted
+    pub entries: Option<BTreeMap<String, usize>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TerminalInfo {
+    pub is_terminal: bool,
+    pub term: Option<String>,
+    pub term_program: Option<String>,
+    /// Size in columns and rows
+    pub size: Option<(u16, u16)>,
+    /// Image protocol `view` would use
+    pub graphics: String,
+    pub colour: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HardwareInfo {
+    pub cpu: String,
+    pub cpus: usize,
+    pub physical_cores: Option<usize>,
+    pub total_memory: u64,
+    pub available_memory: u64,
+}
+
+/// Gather the `system info` report
+pub fn collect_info(layers: &ConfigLayers) -> SystemInfo {
+    let settings = layers.settings();
+    let valid = settings.is_ok() && layers.validate().is_empty();
+    let settings = settings.unwrap_or_else(|_| Settings::default());
+
+    let mut sys = System::new();
+    sys.refresh_memory();
+    sys.refresh_cpu();
+    let cpu = sys
+        .cpus()
+        .first()
+        .map(|cpu| cpu.brand().trim().to_string())
+        .unwrap_or_default();
+    let os = match sys.long_os_version() {
+        Some(name) => format!("{} ({})", std::env::consts::OS, name),
+        None => std::env::consts::OS.to_string(),
+    };
+
+    SystemInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        os,
+        arch: std::env::consts::ARCH,
+        home_dir: dirs::home_dir(),
+        features: enabled_features(),
+        config: ConfigInfo {
+            sources: layers.sources(),
+            user_file: Settings::user_config_path(),
+            valid,
+        },
+        cache: cache_info(&settings.paths.cache_dir()),
+        memory: memory_info(&MemoryStore::default_path()),
+        terminal: TerminalInfo {
+            is_terminal: std::io::stdout().is_terminal(),
+            term: std::env::var("TERM").ok(),
+            term_program: std::env::var("TERM_PROGRAM").ok(),
+            size: terminal_size::terminal_size().map(|(w, h)| (w.0, h.0)),
+            graphics: Protocol::Auto
+                .resolve()
+                .to_possible_value()
+                .map(|v| v.get_name().to_string())
+                .unwrap_or_default(),
+            colour: ColorMode::detect().map(|mode| match mode {
+                ColorMode::TrueColor => "24-bit",
+                ColorMode::Ansi256 => "256",
+                ColorMode::Ansi16 => "16",
+            }),
+        },
+        hardware: HardwareInfo {
+            cpu,
+            cpus: sys.cpus().len(),
+            physical_cores: sys.physical_core_count(),
+            total_memory: sys.total_memory(),
+            available_memory: sys.available_memory(),
+        },
+    }
+}
+
+fn enabled_features() -> Vec<&'static str> {
+    [
+        ("encryption", cfg!(feature = "encryption")),
+        ("watch", cfg!(feature = "watch")),
+        ("gui", cfg!(feature = "gui")),
+        ("experimental", cfg!(feature = "experimental")),
+    ]
+    .into_iter()
+    .filter_map(|(name, enabled)| enabled.then_some(name))
+    .collect()
+}
+
+fn cache_info(dir: &Path) -> CacheInfo {
+    let mut thumbnails = Vec::new();
+    // Unreadable directories simply count as empty
+    let _ = collect_files(&ThumbnailCache::dir_in(dir), &mut thumbnails, |name| {
+        name.ends_with(".png")
+    });
+    CacheInfo {
+        dir: dir.to_path_buf(),
+        total_bytes: dir_size(dir),
+        thumbnails: thumbnails.len(),
+        thumbnail_bytes: thumbnails.iter().map(|t| t.size).sum(),
+        index_bytes: fs::metadata(ScanIndex::path_in(dir)).ok().map(|m| m.len()),
+    }
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+fn memory_info(path: &Path) -> MemoryInfo {
+    let data = fs::read(path).ok();
+    let encrypted = data.as_deref().is_some_and(EncryptedFile::detect);
+    // Never prompt for a passphrase just to count entries
+    let entries = if encrypted {
+        None
+    } else {
+        MemoryStore::open(path).ok().map(|store| {
+            let mut counts = BTreeMap::new();
+            for entry in store.entries() {
+                *counts.entry(entry.memory_type.to_string()).or_insert(0) += 1;
+            }
+            counts
+        })
+    };
+    MemoryInfo {
+        path: path.to_path_buf(),
+        bytes: data.map(|d| d.len() as u64),
+        encrypted,
+        entries,
+    }
+}
+
+/// Outcome of one check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Pass,
+    /// Works, but with a fallback or reduced functionality
+    Warn,
+    Fail,
+}
+
+impl Status {
+    pub fn label(self) -> &'static str {
+        match self {
+            Status::Pass => "PASS",
+            Status::Warn => "WARN",
+            Status::Fail => "FAIL",
+        }
+    }
+}
+
+/// One line of the `system check` report
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: impl Into<String>, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Check the configuration and everything sview relies on at runtime
+///
+/// With an invalid configuration the remaining checks use the built-in defaults.
+pub fn run_checks(layers: &ConfigLayers) -> Vec<Check> {
+    let (config, settings) = check_config(layers);
+    let settings = settings.unwrap_or_default();
+
+    let mut checks = vec![config, check_cache_dir(&settings.paths.cache_dir())];
+    checks.push(check_browser(
+        &settings.browser,
+        std::env::var("BROWSER").ok().as_deref(),
+    ));
+    checks.push(match find_executable("chafa") {
+        Some(path) => Check::new("chafa", Status::Pass, path.display().to_string()),
+        None => Check::new(
+            "chafa",
+            Status::Warn,
+            "not installed; the built-in renderer is used",
+        ),
+    });
+    for runtime in &RUNTIMES {
+        let status = runtime.status(&settings.languages.supported);
+        if status.enabled {
+            checks.push(match status.program {
+                Some(path) => Check::new(runtime.name, Status::Pass, path.display().to_string()),
+                None => Check::new(
+                    runtime.name,
+                    Status::Warn,
+                    format!(
+                        "{} not installed; {} code blocks cannot run",
+                        runtime.programs.join(" or "),
+                        runtime.name
+                    ),
+                ),
+            });
+        }
+    }
+    checks.push(check_graphics(Protocol::Auto.resolve()));
+    checks.push(check_color(ColorMode::detect()));
+    checks
+}
+
+fn check_config(layers: &ConfigLayers) -> (Check, Option<Settings>) {
+    let sources = layers.sources().join(", ");
+    let issues = layers.validate();
+    if let Some(first) = issues.first() {
+        let detail = format!(
+            "{} issue(s), first: {} (run `sview config validate`)",
+            issues.len(),
+            first
+        );
+        return (
+            Check::new("configuration", Status::Fail, detail),
+            layers.settings().ok(),
+        );
+    }
+    match layers.settings() {
+        Ok(settings) => (
+            Check::new(
+                "configuration",
+                Status::Pass,
+                format!("valid ({})", sources),
+            ),
+            Some(settings),
+        ),
+        Err(e) => (
+            Check::new("configuration", Status::Fail, e.to_string()),
+            None,
+        ),
+    }
+}
+
+/// Whether the cache directory is writable, or could be created
+///
+/// A missing directory is not created; its nearest existing ancestor is probed instead.
+fn check_cache_dir(dir: &Path) -> Check {
+    let existing = dir.ancestors().find(|path| path.exists()).unwrap_or(dir);
+    let probe = existing.join(".sview-write-test");
+    let result = fs::write(&probe, b"").and_then(|_| fs::remove_file(&probe));
+    match result {
+        Ok(()) if existing == dir => Check::new(
+            "cache directory",
+            Status::Pass,
+            format!("{} is writable", dir.display()),
+        ),
+        Ok(()) => Check::new(
+            "cache directory",
+            Status::Pass,
+            format!("{} will be created", dir.display()),
+        ),
+        Err(e) => Check::new(
+            "cache directory",
+            Status::Fail,
+            format!("{} is not writable: {}", dir.display(), e),
+        ),
+    }
+}
+
+fn check_browser(settings: &BrowserSettings, env_browser: Option<&str>) -> Check {
+    let candidates = browser::candidates(settings, env_browser, "");
+    let found: Vec<&Invocation> = candidates
+        .iter()
+        .filter(|invocation| find_executable(&invocation.program).is_some())
+        .collect();
+    match found.first() {
+        Some(first) if first.name == candidates[0].name => {
+            let path = find_executable(&first.program).unwrap_or_default();
+            Check::new("browser", Status::Pass, path.display().to_string())
+        }
+        Some(first) => Check::new(
+            "browser",
+            Status::Warn,
+            format!(
+                "{} not found, falling back to {} (available: {})",
+                candidates[0].name,
+                first.name,
+                found
+                    .iter()
+                    .map(|i| i.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        ),
+        None => Check::new(
+            "browser",
+            Status::Warn,
+            format!(
+                "none of {} found; the system default opener is used",
+                candidates
+                    .iter()
+                    .map(|i| i.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        ),
+    }
+}
+
+fn check_graphics(protocol: Protocol) -> Check {
+    let name = match protocol {
+        Protocol::Kitty => "Kitty graphics protocol",
+        Protocol::Iterm2 => "iTerm2 inline images",
+        Protocol::Sixel => "Sixel graphics",
+        Protocol::Auto | Protocol::Blocks => {
+            return Check::new(
+                "terminal graphics",
+                Status::Warn,
+                "no image protocol detected; SVGs are drawn with block characters",
+            )
+        }
+    };
+    Check::new("terminal graphics", Status::Pass, name)
+}
+
+fn check_color(mode: Option<ColorMode>) -> Check {
+    match mode {
+        Some(ColorMode::TrueColor) => Check::new("terminal colour", Status::Pass, "24-bit colour"),
+        Some(ColorMode::Ansi256) => Check::new("terminal colour", Status::Pass, "256 colours"),
+        Some(ColorMode::Ansi16) => Check::new(
+            "terminal colour",
+            Status::Warn,
+            "16 colours; set COLORTERM=truecolor if the terminal supports more",
+        ),
+        None => Check::new(
+            "terminal colour",
+            Status::Warn,
+            "no colour (NO_COLOR set or TERM unknown); SVGs are drawn in grayscale",
+        ),
+    }
+}
+
+/// The full path of `name` if it is an executable on `PATH`
+pub fn find_executable(name: &str) -> Option<PathBuf> {
+    if name.contains(std::path::MAIN_SEPARATOR) {
+        let path = PathBuf::from(name);
+        return is_executable(&path).then_some(path);
+    }
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| {
+            let path = dir.join(name);
+            match std::env::consts::EXE_EXTENSION {
+                "" => path,
+                ext => path.with_extension(ext),
+            }
+        })
+        .find(|path| is_executable(path))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// A file `system clean` removes
+#[derive(Debug, Clone)]
+pub struct CleanTarget {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// Cached thumbnails and leftover temporary files under `cache_dir`
+///
+/// The scan index is kept; `sview index drop` removes it.
+pub fn clean_targets(cache_dir: &Path) -> Result<Vec<CleanTarget>> {
+    let mut targets = Vec::new();
+    collect_files(&ThumbnailCache::dir_in(cache_dir), &mut targets, |_| true)?;
+    collect_files(cache_dir, &mut targets, |name| {
+        // PWA wrappers written by the launcher and interrupted atomic writes
+        (name.starts_with("temp_pwa") && name.ends_with(".html")) || name.ends_with(".tmp")
+    })?;
+    targets.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(targets)
+}
+
+fn collect_files(
+    dir: &Path,
+    targets: &mut Vec<CleanTarget>,
+    matches: impl Fn(&str) -> bool,
+) -> Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+    };
+    for entry in entries {
+        let entry = entry.with_context(|| format!("Failed to read {}", dir.display()))?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() && matches(&entry.file_name().to_string_lossy()) {
+            targets.push(CleanTarget {
+                path: entry.path(),
+                size: metadata.len(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Delete `targets`, returning the bytes freed
+pub fn remove(targets: &[CleanTarget]) -> Result<u64> {
+    let mut freed = 0;
+    for target in targets {
+        match fs::remove_file(&target.path) {
+            Ok(()) => freed += target.size,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to remove {}", target.path.display()))
+            }
+        }
+    }
+    Ok(freed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_clean_targets() -> Result<()> {
+        let dir = tempdir()?;
+        let cache = dir.path();
+        let thumbnails = ThumbnailCache::dir_in(cache);
+        fs::create_dir_all(&thumbnails)?;
+        fs::write(thumbnails.join("abc-120.png"), [0u8; 10])?;
+        fs::write(thumbnails.join("index.json"), "{}")?;
+        fs::write(cache.join("temp_pwa.html"), "<html>")?;
+        fs::write(cache.join("index.json.tmp"), "{")?;
+        fs::write(cache.join("index.json"), "{}")?;
+
+        let targets = clean_targets(cache)?;
+        let names: Vec<_> = targets
+            .iter()
+            .map(|t| t.path.strip_prefix(cache).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                PathBuf::from("index.json.tmp"),
+                PathBuf::from("temp_pwa.html"),
+                PathBuf::from("thumbnails/abc-120.png"),
+                PathBuf::from("thumbnails/index.json"),
+            ]
+        );
+
+        assert_eq!(remove(&targets)?, 10 + 2 + 6 + 1);
+        assert!(clean_targets(cache)?.is_empty());
+        // The scan index survives
+        assert!(cache.join("index.json").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_and_memory_info() -> Result<()> {
+        let dir = tempdir()?;
+        let cache = dir.path().join("cache");
+        let thumbnails = ThumbnailCache::dir_in(&cache);
+        fs::create_dir_all(&thumbnails)?;
+        fs::write(thumbnails.join("a-120.png"), [0u8; 100])?;
+        fs::write(thumbnails.join("index.json"), "{}")?;
+        fs::write(ScanIndex::path_in(&cache), [b' '; 50])?;
+
+        let info = cache_info(&cache);
+        assert_eq!(info.thumbnails, 1);
+        assert_eq!(info.thumbnail_bytes, 100);
+        assert_eq!(info.index_bytes, Some(50));
+        assert_eq!(info.total_bytes, 152);
+
+        let path = dir.path().join("memory.json");
+        let info = memory_info(&path);
+        assert_eq!(info.bytes, None);
+        assert_eq!(info.entries, Some(BTreeMap::new()));
+
+        let mut store = MemoryStore::open(&path)?;
+        store.add(crate::memory::MemoryType::Factual, "a", "1");
+        store.add(crate::memory::MemoryType::Factual, "b", "2");
+        store.save()?;
+        let info = memory_info(&path);
+        assert!(!info.encrypted);
+        assert_eq!(info.entries.unwrap()["factual"], 2);
+
+        fs::write(&path, r#"{"ciphertext": "x"}"#)?;
+        let info = memory_info(&path);
+        assert!(info.encrypted);
+        assert!(info.entries.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_checks() {
+        assert_eq!(check_graphics(Protocol::Kitty).status, Status::Pass);
+        assert_eq!(check_graphics(Protocol::Blocks).status, Status::Warn);
+        assert_eq!(check_color(None).status, Status::Warn);
+
+        let dir = tempdir().unwrap();
+        assert_eq!(check_cache_dir(dir.path()).status, Status::Pass);
+        let missing = dir.path().join("cache/thumbnails");
+        let check = check_cache_dir(&missing);
+        assert_eq!(check.status, Status::Pass);
+        assert!(check.detail.ends_with("will be created"));
+        // Checking leaves the filesystem alone
+        assert!(!dir.path().join("cache").exists());
+        let file = dir.path().join("file");
+        fs::write(&file, "").unwrap();
+        assert_eq!(check_cache_dir(&file.join("cache")).status, Status::Fail);
+
+        let mut settings = Settings::default().browser;
+        settings.command = "no-such-browser-sview".to_string();
+        settings.alternatives = vec!["sh".to_string()];
+        let browser = check_browser(&settings, None);
+        assert_eq!(browser.status, Status::Warn);
+        assert!(browser.detail.contains("falling back to sh"));
+        assert_eq!(check_browser(&settings, Some("sh %s")).status, Status::Pass);
+        assert!(find_executable("no-such-program-sview").is_none());
+    }
+}
@@ -0,0 +1,323 @@
+//! Thumbnail cache for SVG previews
+//!
+//! Thumbnails are stored in `<cache_dir>/thumbnails` as `<sha256>-<size>.png`,
+//! keyed by the SVG's content so renamed or copied files share an entry. An
+//! `index.json` next to them remembers each file's size and mtime, so an
+//! unchanged file is not re-hashed, and the mini icon glyph of every thumbnail.
+
+use anyhow::{Context, Result};
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::settings::Settings;
+use crate::svg2utf;
+
+/// Version of the on-disk index format
+const INDEX_VERSION: u32 = 1;
+
+/// Name of the index file inside the thumbnail directory
+const INDEX_FILE: &str = "index.json";
+
+/// What was last seen of an SVG file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FileRecord {
+    size: u64,
+    modified: Option<SystemTime>,
+    hash: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IndexFile {
+    version: u32,
+    files: BTreeMap<PathBuf, FileRecord>,
+    /// Mini icon glyphs by thumbnail name
+    glyphs: BTreeMap<String, char>,
+}
+
+/// Content-hash keyed thumbnails and mini icons
+#[derive(Debug)]
+pub struct ThumbnailCache {
+    dir: PathBuf,
+    size: u32,
+    max_bytes: u64,
+    index: IndexFile,
+    dirty: bool,
+}
+
+impl ThumbnailCache {
+    /// Directory of the thumbnail cache inside `cache_dir`
+    pub fn dir_in(cache_dir: &Path) -> PathBuf {
+        cache_dir.join("thumbnails")
+    }
+
+    /// The cache configured in `settings`, or `None` when `cache_thumbnails` is off
+    pub fn from_settings(settings: &Settings) -> Result<Option<Self>> {
+        if !settings.general.cache_thumbnails {
+            return Ok(None);
+        }
+        let dir = Self::dir_in(&settings.paths.cache_dir());
+        let max_bytes = settings.performance.cache_size.saturating_mul(1024 * 1024);
+        Self::open(&dir, settings.ui.thumbnail_size, max_bytes).map(Some)
+    }
+
+    /// Load the cache in `dir`, starting empty if it does not exist yet
+    ///
+    /// An index that is corrupt or written by another version is discarded
+    /// rather than reported; the next save replaces it.
+    pub fn open(dir: &Path, size: u32, max_bytes: u64) -> Result<Self> {
+        let path = dir.join(INDEX_FILE);
+        let index = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice::<IndexFile>(&data)
+                .ok()
+                .filter(|file| file.version == INDEX_VERSION)
+                .unwrap_or_default(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => IndexFile::default(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            size: size.max(1),
+            max_bytes,
+            index,
+            dirty: false,
+        })
+    }
+
+    /// The mini icon of `svg_path`, rendering and caching its thumbnail if needed
+    pub fn mini_icon(&mut self, svg_path: &Path) -> Result<char> {
+        let name = self.thumbnail_name(svg_path)?;
+        if let Some(&glyph) = self.index.glyphs.get(&name) {
+            return Ok(glyph);
+        }
+
+        let img = self.load_or_render(svg_path, &name)?;
+        let glyph = svg2utf::image_to_mini_icon(img);
+        self.index.glyphs.insert(name, glyph);
+        self.dirty = true;
+        Ok(glyph)
+    }
+
+    /// Prune the cache to its size limit and write the index if anything changed
+    pub fn save(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        self.prune()?;
+
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create directory: {}", self.dir.display()))?;
+        let path = self.dir.join(INDEX_FILE);
+        self.index.version = INDEX_VERSION;
+        let data = serde_json::to_vec(&self.index)?;
+
+        // Write to a sibling file first so a crash never leaves a truncated index
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, data)
+            .with_context(|| format!("Failed to write thumbnail index: {}", tmp.display()))?;
+        fs::rename(&tmp, &path)
+            .with_context(|| format!("Failed to replace thumbnail index: {}", path.display()))?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// `<sha256>-<size>` for the current content of `svg_path`
+    fn thumbnail_name(&mut self, svg_path: &Path) -> Result<String> {
+        let path = svg_path
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve {}", svg_path.display()))?;
+        let metadata = fs::metadata(&path)
+            .with_context(|| format!("Failed to read metadata: {}", path.display()))?;
+        let modified = metadata.modified().ok();
+
+        let hash = match self.index.files.get(&path) {
+            Some(record) if record.size == metadata.len() && record.modified == modified => {
+                record.hash.clone()
+            }
+            _ => {
+                let data = fs::read(&path)
+                    .with_context(|| format!("Failed to read SVG file: {}", path.display()))?;
+                let hash = format!("{:x}", Sha256::digest(&data));
+                self.index.files.insert(
+                    path,
+                    FileRecord {
+                        size: metadata.len(),
+                        modified,
+                        hash: hash.clone(),
+                    },
+                );
+                self.dirty = true;
+                hash
+            }
+        };
+        Ok(format!("{}-{}", hash, self.size))
+    }
+
+    /// The cached thumbnail `name`, rendered from `svg_path` and stored when missing
+    fn load_or_render(&mut self, svg_path: &Path, name: &str) -> Result<RgbaImage> {
+        let path = self.dir.join(format!("{}.png", name));
+        if let Ok(img) = image::open(&path) {
+            return Ok(img.to_rgba8());
+        }
+
+        let img = svg2utf::render_square(svg_path, self.size, None)?;
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create directory: {}", self.dir.display()))?;
+        img.save_with_format(&path, image::ImageFormat::Png)
+            .with_context(|| format!("Failed to write thumbnail: {}", path.display()))?;
+        self.dirty = true;
+        Ok(img)
+    }
+
+    /// Forget vanished files and delete the oldest thumbnails beyond `max_bytes`
+    fn prune(&mut self) -> Result<()> {
+        self.index.files.retain(|path, _| path.exists());
+
+        let mut thumbnails = Vec::new();
+        if let Ok(entries) = fs::read_dir(&self.dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) != Some("png") {
+                    continue;
+                }
+                if let Ok(metadata) = entry.metadata() {
+                    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                    thumbnails.push((modified, metadata.len(), path));
+                }
+            }
+        }
+
+        let mut total: u64 = thumbnails.iter().map(|(_, len, _)| len).sum();
+        thumbnails.sort();
+        for (_, len, path) in thumbnails {
+            if total <= self.max_bytes {
+                break;
+            }
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+            total -= len;
+        }
+
+        // Glyphs outlive their PNG only while some file still has that content
+        let live: Vec<&str> = self.index.files.values().map(|r| r.hash.as_str()).collect();
+        self.index
+            .glyphs
+            .retain(|name, _| live.iter().any(|hash| name.starts_with(hash)));
+        Ok(())
+    }
+}
+
+/// Mini icons for a listing, served from the thumbnail cache when it is enabled
+///
+/// Cache problems are reported as warnings; the icons are then rendered directly.
+pub struct MiniIcons {
+    cache: Option<ThumbnailCache>,
+}
+
+impl MiniIcons {
+    pub fn new(settings: &Settings) -> Self {
+        let cache = ThumbnailCache::from_settings(settings).unwrap_or_else(|e| {
+            eprintln!("Warning: thumbnail cache unavailable: {:#}", e);
+            None
+        });
+        Self { cache }
+    }
+
+    /// The mini icon of `svg_path`, or a space if it cannot be drawn
+    pub fn get(&mut self, svg_path: &Path) -> char {
+        self.cache
+            .as_mut()
+            .and_then(|cache| cache.mini_icon(svg_path).ok())
+            .or_else(|| svg2utf::svg_to_mini_icon(svg_path).ok())
+            .unwrap_or(' ')
+    }
+
+    /// Write back anything newly cached
+    pub fn save(&mut self) {
+        if let Some(cache) = self.cache.as_mut() {
+            if let Err(e) = cache.save() {
+                eprintln!("Warning: failed to save thumbnail cache: {:#}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const RED: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20"><rect width="20" height="20" fill="#f00"/></svg>"##;
+    const BLUE: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20"><rect width="10" height="20" fill="#00f"/></svg>"##;
+
+    fn pngs(dir: &Path) -> usize {
+        fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|e| e.path().extension().is_some_and(|ext| ext == "png"))
+                    .count()
+            })
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn test_thumbnail_cache() -> Result<()> {
+        let dir = tempdir()?;
+        let cache_dir = ThumbnailCache::dir_in(dir.path());
+        let svg = dir.path().join("a.svg");
+        let copy = dir.path().join("b.svg");
+        fs::write(&svg, RED)?;
+        fs::write(&copy, RED)?;
+
+        let mut cache = ThumbnailCache::open(&cache_dir, 32, u64::MAX)?;
+        let glyph = cache.mini_icon(&svg)?;
+        assert_eq!(glyph, svg2utf::svg_to_mini_icon(&svg)?);
+        // Identical content shares one thumbnail
+        assert_eq!(cache.mini_icon(&copy)?, glyph);
+        cache.save()?;
+        assert_eq!(pngs(&cache_dir), 1);
+        let thumbnail = fs::read_dir(&cache_dir)?
+            .flatten()
+            .map(|e| e.path())
+            .find(|p| p.extension().is_some_and(|ext| ext == "png"))
+            .unwrap();
+        assert_eq!(image::open(thumbnail)?.to_rgba8().dimensions(), (32, 32));
+
+        // A reopened cache answers from the index
+        let mut cache = ThumbnailCache::open(&cache_dir, 32, u64::MAX)?;
+        assert_eq!(cache.mini_icon(&svg)?, glyph);
+        assert!(!cache.dirty);
+
+        // Changed content gets a new entry
+        fs::write(&svg, BLUE)?;
+        cache.mini_icon(&svg)?;
+        assert!(cache.dirty);
+        cache.save()?;
+        assert_eq!(pngs(&cache_dir), 2);
+
+        // A zero limit prunes every thumbnail, but the glyphs stay usable
+        let mut cache = ThumbnailCache::open(&cache_dir, 16, 0)?;
+        cache.mini_icon(&svg)?;
+        cache.save()?;
+        assert_eq!(pngs(&cache_dir), 0);
+
+        // A truncated index starts the cache afresh and is rewritten
+        fs::write(cache_dir.join(INDEX_FILE), "{\"version\":")?;
+        let mut cache = ThumbnailCache::open(&cache_dir, 16, u64::MAX)?;
+        assert!(cache.index.glyphs.is_empty());
+        assert_eq!(cache.mini_icon(&svg)?, svg2utf::svg_to_mini_icon(&svg)?);
+        cache.save()?;
+        assert!(ThumbnailCache::open(&cache_dir, 16, u64::MAX)?
+            .index
+            .glyphs
+            .contains_key(&cache.thumbnail_name(&svg)?));
+
+        Ok(())
+    }
+}
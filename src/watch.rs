@@ -0,0 +1,270 @@
+//! Live change notifications for a scanned directory (`watch` feature)
+//!
+//! A [`ScanWatcher`] keeps the set of files a [`FileScanner`] search would
+//! report and turns raw filesystem notifications into added/removed/modified
+//! events for that set.
+
+use anyhow::{Context, Result};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crate::scanner::{FileEntry, FileScanner, SearchQuery};
+
+/// How long to keep collecting notifications after the first one, so an
+/// editor's save (truncate, write, rename) is reported once
+const DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// What happened to a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+impl std::fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Modified => "modified",
+        })
+    }
+}
+
+/// A change to the set of matching files
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangeEvent {
+    pub kind: ChangeKind,
+    /// The file as it is now, or as it was last seen if it was removed
+    pub entry: FileEntry,
+}
+
+/// Watches a directory and reports changes to the files matching a query
+pub struct ScanWatcher {
+    scanner: FileScanner,
+    root: PathBuf,
+    /// `root` resolved, which is what notifications are reported against
+    canonical_root: PathBuf,
+    query: SearchQuery,
+    known: BTreeMap<PathBuf, FileEntry>,
+    events: mpsc::Receiver<notify::Result<notify::Event>>,
+    // Dropping the watcher stops notifications
+    _watcher: RecommendedWatcher,
+}
+
+impl ScanWatcher {
+    pub(crate) fn new(scanner: FileScanner, root: &Path, query: SearchQuery) -> Result<Self> {
+        let canonical_root = root
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve {}", root.display()))?;
+
+        // Start watching before the initial scan so nothing slips in between
+        let (tx, events) = mpsc::channel();
+        let mut watcher =
+            notify::recommended_watcher(tx).context("Failed to start the filesystem watcher")?;
+        let mode = if scanner.config().recursive && scanner.config().max_depth != Some(1) {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        watcher
+            .watch(&canonical_root, mode)
+            .with_context(|| format!("Failed to watch {}", root.display()))?;
+
+        let mut known = BTreeMap::new();
+        scanner.search(root, &query, |entry| {
+            known.insert(entry.path.clone(), entry.clone());
+            true
+        })?;
+
+        Ok(Self {
+            // Rescans must see the filesystem, not a snapshot of it
+            scanner: scanner.with_index(None),
+            root: root.to_path_buf(),
+            canonical_root,
+            query,
+            known,
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    /// Files currently matching, ordered by path
+    pub fn entries(&self) -> impl Iterator<Item = &FileEntry> {
+        self.known.values()
+    }
+
+    /// Block until files were added, removed or modified and return those changes
+    ///
+    /// With a `timeout`, returns an empty list if nothing changed in time.
+    pub fn next_changes(&mut self, timeout: Option<Duration>) -> Result<Vec<ChangeEvent>> {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        loop {
+            let first = match deadline {
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    match self.events.recv_timeout(left) {
+                        Ok(event) => event,
+                        Err(mpsc::RecvTimeoutError::Timeout) => return Ok(Vec::new()),
+                        Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                None => match self.events.recv() {
+                    Ok(event) => event,
+                    Err(_) => break,
+                },
+            };
+
+            let mut touched = BTreeSet::new();
+            let mut collect = |event: notify::Result<notify::Event>| {
+                if let Ok(event) = event {
+                    touched.extend(event.paths);
+                }
+            };
+            collect(first);
+            while let Ok(event) = self.events.recv_timeout(DEBOUNCE) {
+                collect(event);
+            }
+
+            let changes = self.apply(&touched)?;
+            if !changes.is_empty() {
+                return Ok(changes);
+            }
+        }
+        Err(anyhow::anyhow!("Filesystem watcher stopped"))
+    }
+
+    /// Re-evaluate the notified paths against what is known
+    fn apply(&mut self, touched: &BTreeSet<PathBuf>) -> Result<Vec<ChangeEvent>> {
+        let mut changes = Vec::new();
+        for notified in touched {
+            let relative = match notified.strip_prefix(&self.canonical_root) {
+                Ok(relative) => relative,
+                Err(_) => continue,
+            };
+            let path = self.root.join(relative);
+
+            // Everything at or below the path as it is now
+            let mut current = BTreeMap::new();
+            if path.is_dir() {
+                self.scanner
+                    .search_below(&self.root, &path, &self.query, |entry| {
+                        current.insert(entry.path.clone(), entry.clone());
+                        true
+                    })?;
+            } else if path.exists() {
+                if let Some(entry) = self.scanner.match_one(&self.root, &path, &self.query) {
+                    current.insert(entry.path.clone(), entry);
+                }
+            }
+
+            let gone: Vec<PathBuf> = self
+                .known
+                .range(path.clone()..)
+                .take_while(|(known, _)| known.starts_with(&path))
+                .filter(|(known, _)| !current.contains_key(*known))
+                .map(|(known, _)| known.clone())
+                .collect();
+            for known in gone {
+                if let Some(entry) = self.known.remove(&known) {
+                    changes.push(ChangeEvent {
+                        kind: ChangeKind::Removed,
+                        entry,
+                    });
+                }
+            }
+
+            for (path, entry) in current {
+                let kind = match self.known.get(&path) {
+                    None => ChangeKind::Added,
+                    Some(old) if old.size != entry.size || old.modified != entry.modified => {
+                        ChangeKind::Modified
+                    }
+                    Some(_) => continue,
+                };
+                self.known.insert(path, entry.clone());
+                changes.push(ChangeEvent { kind, entry });
+            }
+        }
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::ScannerConfig;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn kinds(changes: &[ChangeEvent]) -> Vec<(ChangeKind, String)> {
+        let mut kinds: Vec<_> = changes
+            .iter()
+            .map(|c| {
+                let name = c.entry.path.file_name().unwrap().to_string_lossy();
+                (c.kind, name.to_string())
+            })
+            .collect();
+        kinds.sort_by(|a, b| a.1.cmp(&b.1));
+        kinds
+    }
+
+    /// Wait for changes, tolerating notifications that carry no relevant change
+    fn wait(watcher: &mut ScanWatcher) -> Vec<(ChangeKind, String)> {
+        kinds(&watcher.next_changes(Some(Duration::from_secs(5))).unwrap())
+    }
+
+    #[test]
+    fn test_watch_reports_added_modified_removed() -> Result<()> {
+        let dir = tempdir()?;
+        fs::write(dir.path().join("old.svg"), "<svg/>")?;
+        fs::write(dir.path().join("notes.txt"), "ignored")?;
+
+        let scanner = FileScanner::new().with_config(ScannerConfig {
+            extensions: Some(vec!["svg".to_string()]),
+            ..Default::default()
+        });
+        let mut watcher = scanner.watch(dir.path(), SearchQuery::all())?;
+        assert_eq!(watcher.entries().count(), 1);
+
+        fs::write(dir.path().join("new.svg"), "<svg/>")?;
+        fs::write(dir.path().join("other.txt"), "ignored")?;
+        assert_eq!(wait(&mut watcher), [(ChangeKind::Added, "new.svg".into())]);
+
+        fs::write(dir.path().join("new.svg"), "<svg width=\"10\"/>")?;
+        assert_eq!(
+            wait(&mut watcher),
+            [(ChangeKind::Modified, "new.svg".into())]
+        );
+
+        let sub = dir.path().join("sub");
+        fs::create_dir(&sub)?;
+        fs::rename(dir.path().join("old.svg"), sub.join("moved.svg"))?;
+        let mut seen = wait(&mut watcher);
+        if seen.len() < 2 {
+            seen.extend(wait(&mut watcher));
+            seen.sort_by(|a, b| a.1.cmp(&b.1));
+        }
+        assert_eq!(
+            seen,
+            [
+                (ChangeKind::Added, "moved.svg".into()),
+                (ChangeKind::Removed, "old.svg".into())
+            ]
+        );
+
+        fs::remove_dir_all(&sub)?;
+        assert_eq!(
+            wait(&mut watcher),
+            [(ChangeKind::Removed, "moved.svg".into())]
+        );
+        assert_eq!(watcher.entries().count(), 1);
+        Ok(())
+    }
+}